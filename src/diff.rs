@@ -0,0 +1,314 @@
+use hash_database::HashDatabase;
+use json_writer::bindata_to_json;
+use path::{BinPath, PathSegment};
+use structs::*;
+use text_writer::typed_value_to_text;
+
+use json::JsonValue;
+use std::collections::HashMap;
+
+/// A value with the type it is stored as, which the data alone does not tell
+/// for containers and structs, or pointers and embeds.
+pub type TypedData = (BinType, BinData);
+
+/// A value that was added, removed or changed between two bin files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub path: BinPath,
+    /// `None` when the value was added.
+    pub old: Option<TypedData>,
+    /// `None` when the value was removed.
+    pub new: Option<TypedData>,
+}
+
+impl Change {
+    fn added(path: &BinPath, btype: BinType, data: &BinData) -> Change {
+        Change {
+            path: path.clone(),
+            old: None,
+            new: Some((btype, data.clone())),
+        }
+    }
+
+    fn removed(path: &BinPath, btype: BinType, data: &BinData) -> Change {
+        Change {
+            path: path.clone(),
+            old: Some((btype, data.clone())),
+            new: None,
+        }
+    }
+
+    /// `added`, `removed` or `changed`.
+    pub fn kind(&self) -> &'static str {
+        match (&self.old, &self.new) {
+            (None, _) => "added",
+            (_, None) => "removed",
+            _ => "changed",
+        }
+    }
+
+    /// Formats the change as `+`, `-` or `~`, the path and the ritobin values.
+    pub fn to_string_with(&self, hash_database: &HashDatabase) -> String {
+        let value = |(btype, data): &TypedData| typed_value_to_text(btype, data, hash_database);
+        let path = self.path.to_string_with(hash_database);
+        match (&self.old, &self.new) {
+            (None, Some(new)) => format!("+ {}: {}", path, value(new)),
+            (Some(old), None) => format!("- {}: {}", path, value(old)),
+            (Some(old), Some(new)) => format!("~ {}: {} -> {}", path, value(old), value(new)),
+            (None, None) => format!("  {}", path),
+        }
+    }
+
+    /// The change as a JSON object of `op`, `path`, and `old` and `new`
+    /// values written like the fields of `json_writer`.
+    pub fn to_json(&self, hash_database: &HashDatabase) -> JsonValue {
+        let value = |(btype, data): &TypedData| {
+            let mut object = JsonValue::new_object();
+            object.insert("type", format!("{:?}", btype)).unwrap();
+            object
+                .insert("data", bindata_to_json(data, hash_database))
+                .unwrap();
+            object
+        };
+        let mut object = JsonValue::new_object();
+        object.insert("op", self.kind()).unwrap();
+        object
+            .insert("path", self.path.to_string_with(hash_database))
+            .unwrap();
+        if let Some(old) = &self.old {
+            object.insert("old", value(old)).unwrap();
+        }
+        if let Some(new) = &self.new {
+            object.insert("new", value(new)).unwrap();
+        }
+        object
+    }
+}
+
+/// Compares two bin files, matching entries and map pairs by key and fields
+/// by name, and items of containers by position.
+///
+/// Values of different types or classes are reported as one change of the
+/// whole value rather than of what is inside.
+pub fn diff_bins(old: &BinFile, new: &BinFile) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut path = BinPath::new();
+
+    if old.version != new.version {
+        changes.push(Change {
+            path: path.with(PathSegment::Root("Version")),
+            old: Some((BinType::UInt32, BinData::UInt32(old.version))),
+            new: Some((BinType::UInt32, BinData::UInt32(new.version))),
+        });
+    }
+
+    path.push(PathSegment::Root("LinkedList"));
+    for (index, linked) in old.linked_list.iter().enumerate() {
+        if !new.linked_list.contains(linked) {
+            let data = BinData::String(linked.clone());
+            changes.push(Change::removed(
+                &path.with(PathSegment::Index(index)),
+                BinType::String,
+                &data,
+            ));
+        }
+    }
+    for (index, linked) in new.linked_list.iter().enumerate() {
+        if !old.linked_list.contains(linked) {
+            let data = BinData::String(linked.clone());
+            changes.push(Change::added(
+                &path.with(PathSegment::Index(index)),
+                BinType::String,
+                &data,
+            ));
+        }
+    }
+    path.pop();
+
+    let empty = Map::new(BinType::Hash, BinType::Embedded, Vec::new());
+    let sections = [
+        ("Entries", &old.entries, &new.entries),
+        (
+            "Patches",
+            old.patches.as_ref().unwrap_or(&empty),
+            new.patches.as_ref().unwrap_or(&empty),
+        ),
+    ];
+    for (name, old_map, new_map) in sections.iter() {
+        path.push(PathSegment::Root(name));
+        diff_map(&mut path, old_map, new_map, &mut changes);
+        path.pop();
+    }
+
+    changes
+}
+
+/// Compares two values of the given types, adding to `changes`.
+pub fn diff_data(
+    path: &mut BinPath,
+    old: (BinType, &BinData),
+    new: (BinType, &BinData),
+    changes: &mut Vec<Change>,
+) {
+    if old.0 == new.0 {
+        match (old.1, new.1) {
+            (BinData::PointerOrEmbedded(old_pe), BinData::PointerOrEmbedded(new_pe))
+                if old_pe.name == new_pe.name =>
+            {
+                return diff_fields(path, old_pe, new_pe, changes);
+            }
+            (BinData::ContainerOrStruct(old_cs), BinData::ContainerOrStruct(new_cs))
+                if old_cs.btype == new_cs.btype =>
+            {
+                return diff_items(path, old_cs, new_cs, changes);
+            }
+            (BinData::Map(old_map), BinData::Map(new_map))
+                if old_map.keytype == new_map.keytype && old_map.valuetype == new_map.valuetype =>
+            {
+                return diff_map(path, old_map, new_map, changes);
+            }
+            (BinData::Optional(old_optional), BinData::Optional(new_optional))
+                if old_optional.btype == new_optional.btype =>
+            {
+                if let (Some(old_data), Some(new_data)) = (&old_optional.data, &new_optional.data) {
+                    let btype = old_optional.btype;
+                    return diff_data(path, (btype, old_data), (btype, new_data), changes);
+                }
+            }
+            _ => {}
+        }
+    }
+    if old.0 != new.0 || old.1 != new.1 {
+        changes.push(Change {
+            path: path.clone(),
+            old: Some((old.0, old.1.clone())),
+            new: Some((new.0, new.1.clone())),
+        });
+    }
+}
+
+fn diff_fields(
+    path: &mut BinPath,
+    old: &PointerOrEmbedded,
+    new: &PointerOrEmbedded,
+    changes: &mut Vec<Change>,
+) {
+    for old_field in &old.items {
+        path.push(PathSegment::Field(old_field.name));
+        match new.items.iter().find(|field| field.name == old_field.name) {
+            Some(new_field) => diff_data(
+                path,
+                (old_field.btype, &old_field.data),
+                (new_field.btype, &new_field.data),
+                changes,
+            ),
+            None => changes.push(Change::removed(path, old_field.btype, &old_field.data)),
+        }
+        path.pop();
+    }
+    for new_field in &new.items {
+        if !old.items.iter().any(|field| field.name == new_field.name) {
+            path.push(PathSegment::Field(new_field.name));
+            changes.push(Change::added(path, new_field.btype, &new_field.data));
+            path.pop();
+        }
+    }
+}
+
+fn diff_items(
+    path: &mut BinPath,
+    old: &ContainerOrStruct,
+    new: &ContainerOrStruct,
+    changes: &mut Vec<Change>,
+) {
+    let btype = old.btype;
+    for index in 0..old.items.len().max(new.items.len()) {
+        path.push(PathSegment::Index(index));
+        match (old.items.get(index), new.items.get(index)) {
+            (Some(old_data), Some(new_data)) => {
+                diff_data(path, (btype, old_data), (btype, new_data), changes)
+            }
+            (Some(old_data), None) => changes.push(Change::removed(path, btype, old_data)),
+            (None, Some(new_data)) => changes.push(Change::added(path, btype, new_data)),
+            (None, None) => {}
+        }
+        path.pop();
+    }
+}
+
+fn diff_map(path: &mut BinPath, old: &Map, new: &Map, changes: &mut Vec<Change>) {
+    let valuetype = old.valuetype;
+    let mut matched = vec![false; new.items.len()];
+    // Keys are indexed by their text since floats keep `BinData` from `Hash`,
+    // so full-game maps are not scanned once per old pair.
+    let mut new_indices: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, new_pair) in new.items.iter().enumerate() {
+        new_indices
+            .entry(format!("{:?}", new_pair.keydata))
+            .or_default()
+            .push(index);
+    }
+    for (index, old_pair) in old.items.iter().enumerate() {
+        path.push(PathSegment::from_map_key(&old_pair.keydata, index));
+        let new_index = new_indices
+            .get(&format!("{:?}", old_pair.keydata))
+            .and_then(|indices| {
+                indices
+                    .iter()
+                    .copied()
+                    .find(|new_index| new.items[*new_index].keydata == old_pair.keydata)
+            });
+        match new_index {
+            Some(new_index) => {
+                matched[new_index] = true;
+                diff_data(
+                    path,
+                    (valuetype, &old_pair.valuedata),
+                    (valuetype, &new.items[new_index].valuedata),
+                    changes,
+                );
+            }
+            None => changes.push(Change::removed(path, valuetype, &old_pair.valuedata)),
+        }
+        path.pop();
+    }
+    for (index, new_pair) in new.items.iter().enumerate() {
+        if !matched[index] {
+            path.push(PathSegment::from_map_key(&new_pair.keydata, index));
+            changes.push(Change::added(path, valuetype, &new_pair.valuedata));
+            path.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use text_reader::convert_text_to_bin;
+
+    fn bin_file(tags: &str) -> BinFile {
+        convert_text_to_bin(&format!(
+            "entries: map[hash,embed] = {{
+                \"Skin0\" = SkinCharacterDataProperties {{
+                    tags: map[hash,u8] = {{ {} }}
+                }}
+            }}",
+            tags
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn matches_map_pairs_by_key() {
+        let old = bin_file("\"a\" = 1 \"b\" = 2 \"c\" = 3");
+        let new = bin_file("\"d\" = 4 \"c\" = 3 \"a\" = 5");
+        let changes: Vec<String> = diff_bins(&old, &new)
+            .iter()
+            .map(|change| change.to_string_with(&HashDatabase::new()))
+            .collect();
+        assert_eq!(changes.len(), 3, "{:?}", changes);
+        assert!(changes[0].starts_with("~ ") && changes[0].ends_with("u8 = 1 -> u8 = 5"));
+        assert!(changes[1].starts_with("- ") && changes[1].ends_with("u8 = 2"));
+        assert!(changes[2].starts_with("+ ") && changes[2].ends_with("u8 = 4"));
+    }
+}
@@ -0,0 +1,350 @@
+use error::BinError;
+use path::{BinPath, PathSegment};
+use query::Query;
+use schema::FieldType;
+use structs::*;
+use text_reader::{parse_text_field, parse_text_map_pair, parse_text_value};
+
+/// What a path points at: a whole section, or a value with its type.
+pub(crate) enum Target<'a> {
+    Section(&'a mut Map),
+    Value(BinType, &'a mut BinData),
+}
+
+/// Replaces every value `query` selects with `value`, parsed as ritobin text
+/// of the type the value already has. Returns how many values were set.
+pub fn set_values(bin_file: &mut BinFile, query: &Query, value: &str) -> Result<usize, BinError> {
+    let paths = matched_paths(bin_file, query);
+    for path in &paths {
+        let in_path = |e: BinError| at_path(e, path);
+        let (btype, data) = match resolve_mut(bin_file, path) {
+            Some(Target::Value(btype, data)) => (btype, data),
+            _ => return Err(in_path(BinError::edit("Value is not in the bin"))),
+        };
+        let inner = element_types(btype, data);
+        *data = parse_text_value(value, &btype, &inner).map_err(in_path)?;
+    }
+    Ok(paths.len())
+}
+
+/// Removes every field, item, map pair or entry `query` selects. Returns how
+/// many values were removed.
+pub fn delete_values(bin_file: &mut BinFile, query: &Query) -> Result<usize, BinError> {
+    let paths = matched_paths(bin_file, query);
+    // Later siblings go first, so the indices of earlier ones stay valid.
+    for path in paths.iter().rev() {
+        let mut parent = path.clone();
+        let segment = match parent.pop() {
+            Some(segment) => segment,
+            None => continue,
+        };
+        let not_found = || at_path(BinError::edit("Value is not in the bin"), path);
+        let removed = match resolve_mut(bin_file, &parent) {
+            Some(Target::Section(map)) => remove_pair(map, &segment),
+            Some(Target::Value(_, data)) => match (look_through_mut(data), &segment) {
+                (BinData::PointerOrEmbedded(pe), PathSegment::Field(name)) => {
+                    let length = pe.items.len();
+                    pe.items.retain(|field| field.name != *name);
+                    pe.items.len() != length
+                }
+                (BinData::ContainerOrStruct(cs), PathSegment::Index(index))
+                    if *index < cs.items.len() =>
+                {
+                    cs.items.remove(*index);
+                    true
+                }
+                (BinData::Map(map), segment) => remove_pair(map, segment),
+                _ => false,
+            },
+            None => false,
+        };
+        if !removed {
+            return Err(not_found());
+        }
+    }
+    Ok(paths.len())
+}
+
+/// Adds `value` to every value `query` selects: a `name: type = value` field
+/// to embeds and pointers, a `key = value` pair to maps and sections, or an
+/// item to containers and structs at `index`, or at the end.
+///
+/// A query of only `Entries` or `Patches` selects that section. Returns how
+/// many values were inserted.
+pub fn insert_values(
+    bin_file: &mut BinFile,
+    query: &Query,
+    value: &str,
+    index: Option<usize>,
+) -> Result<usize, BinError> {
+    let paths = match query.root {
+        Some(root) if query.steps.is_empty() => {
+            let mut path = BinPath::new();
+            path.push(PathSegment::Root(root));
+            vec![path]
+        }
+        _ => matched_paths(bin_file, query),
+    };
+    for path in &paths {
+        let in_path = |e: BinError| at_path(e, path);
+        match resolve_mut(bin_file, path) {
+            Some(Target::Section(map)) => insert_pair(map, value).map_err(in_path)?,
+            Some(Target::Value(btype, data)) => match look_through_mut(data) {
+                BinData::PointerOrEmbedded(pe) if pe.name != 0 => {
+                    let field = parse_text_field(value).map_err(in_path)?;
+                    if pe.items.iter().any(|existing| existing.name == field.name) {
+                        return Err(in_path(
+                            BinError::edit("Field is already in the embed")
+                                .in_path(PathSegment::Field(field.name)),
+                        ));
+                    }
+                    pe.items.push(field);
+                }
+                BinData::ContainerOrStruct(cs) => {
+                    let inner = sibling_element_types(cs.btype, cs.items.first());
+                    let item = parse_text_value(value, &cs.btype, &inner).map_err(in_path)?;
+                    let index = index.unwrap_or(cs.items.len());
+                    if index > cs.items.len() {
+                        return Err(in_path(BinError::edit(format!(
+                            "Cannot insert at {}, the list has {} item(s)",
+                            index,
+                            cs.items.len()
+                        ))));
+                    }
+                    cs.items.insert(index, item);
+                }
+                BinData::Map(map) => insert_pair(map, value).map_err(in_path)?,
+                _ => {
+                    return Err(in_path(BinError::edit(format!(
+                        "Cannot insert into a {:?}",
+                        btype
+                    ))))
+                }
+            },
+            None => return Err(in_path(BinError::edit("Value is not in the bin"))),
+        }
+    }
+    Ok(paths.len())
+}
+
+fn matched_paths(bin_file: &BinFile, query: &Query) -> Vec<BinPath> {
+    query
+        .run(bin_file)
+        .into_iter()
+        .map(|found| found.path)
+        .collect()
+}
+
+fn at_path(error: BinError, path: &BinPath) -> BinError {
+    path.segments
+        .iter()
+        .rev()
+        .fold(error, |error, segment| error.in_path(segment.clone()))
+}
+
+/// Element types of a `list`, `list2`, `option` or `map` value, as
+/// `parse_text_value` takes them.
+fn element_types(btype: BinType, data: &BinData) -> Vec<BinType> {
+    let field_type = FieldType::of_data(btype, data);
+    field_type
+        .keytype
+        .into_iter()
+        .chain(field_type.valuetype)
+        .collect()
+}
+
+/// Element types for a new item or map value of `btype`, taken from an
+/// existing one since bins store them with each value. Empty when there is
+/// none, so nested lists and maps cannot be added to empty ones.
+fn sibling_element_types(btype: BinType, sibling: Option<&BinData>) -> Vec<BinType> {
+    sibling.map_or_else(Vec::new, |sibling| element_types(btype, sibling))
+}
+
+fn insert_pair(map: &mut Map, value: &str) -> Result<(), BinError> {
+    let value_inner = sibling_element_types(
+        map.valuetype,
+        map.items.first().map(|pair| &*pair.valuedata),
+    );
+    let pair = parse_text_map_pair(value, &map.keytype, &map.valuetype, &value_inner)?;
+    if map
+        .items
+        .iter()
+        .any(|existing| existing.keydata == pair.keydata)
+    {
+        let segment = PathSegment::from_map_key(&pair.keydata, map.items.len());
+        return Err(BinError::edit("Key is already in the map").in_path(segment));
+    }
+    map.items.push(pair);
+    Ok(())
+}
+
+fn remove_pair(map: &mut Map, segment: &PathSegment) -> bool {
+    let position = map
+        .items
+        .iter()
+        .enumerate()
+        .position(|(index, pair)| PathSegment::from_map_key(&pair.keydata, index) == *segment);
+    match position {
+        Some(position) => {
+            map.items.remove(position);
+            true
+        }
+        None => false,
+    }
+}
+
+/// The value inside an optional, or the value itself, as queries see it.
+pub(crate) fn look_through_mut(data: &mut BinData) -> &mut BinData {
+    match data {
+        BinData::Optional(Optional {
+            data: Some(data), ..
+        }) => data,
+        data => data,
+    }
+}
+
+/// Finds what `path` points at, looking through optionals like queries do.
+pub(crate) fn resolve_mut<'a>(bin_file: &'a mut BinFile, path: &BinPath) -> Option<Target<'a>> {
+    let mut segments = path.segments.iter();
+    let mut target = match segments.next()? {
+        PathSegment::Root("Entries") => Target::Section(&mut bin_file.entries),
+        PathSegment::Root("Patches") => Target::Section(bin_file.patches.as_mut()?),
+        _ => return None,
+    };
+    for segment in segments {
+        target = match target {
+            Target::Section(map) => pair_value(map, segment)?,
+            Target::Value(_, data) => match (look_through_mut(data), segment) {
+                (BinData::PointerOrEmbedded(pe), PathSegment::Field(name)) => {
+                    let field = pe.items.iter_mut().find(|field| field.name == *name)?;
+                    Target::Value(field.btype, &mut field.data)
+                }
+                (BinData::ContainerOrStruct(cs), PathSegment::Index(index)) => {
+                    let item_type = cs.btype;
+                    Target::Value(item_type, cs.items.get_mut(*index)?)
+                }
+                (BinData::Map(map), segment) => pair_value(map, segment)?,
+                _ => return None,
+            },
+        };
+    }
+    Some(target)
+}
+
+fn pair_value<'a>(map: &'a mut Map, segment: &PathSegment) -> Option<Target<'a>> {
+    let valuetype = map.valuetype;
+    let pair = map
+        .items
+        .iter_mut()
+        .enumerate()
+        .find(|(index, pair)| PathSegment::from_map_key(&pair.keydata, *index) == *segment)
+        .map(|(_, pair)| pair)?;
+    Some(Target::Value(valuetype, &mut pair.valuedata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hash_database::HashDatabase;
+    use text_reader::convert_text_to_bin;
+    use text_writer::typed_value_to_text;
+
+    fn bin_file() -> BinFile {
+        convert_text_to_bin(
+            "entries: map[hash,embed] = {
+                \"Skin0\" = SkinCharacterDataProperties {
+                    name: string = \"Ahri\"
+                    scale: u8 = 1
+                    items: list[u8] = { 1 2 3 4 }
+                    tags: map[hash,u8] = { \"a\" = 1 }
+                }
+            }",
+        )
+        .unwrap()
+    }
+
+    fn query(query: &str) -> Query {
+        Query::parse(query).unwrap()
+    }
+
+    fn values(bin_file: &BinFile, query_string: &str) -> Vec<String> {
+        query(query_string)
+            .run(bin_file)
+            .iter()
+            .map(|found| typed_value_to_text(&found.btype, found.data, &HashDatabase::new()))
+            .collect()
+    }
+
+    fn edit_error(result: Result<usize, BinError>) -> String {
+        match result {
+            Err(BinError::At { source, .. }) => source.to_string(),
+            Err(error) => panic!("Expected an error at a path but got {}", error),
+            Ok(count) => panic!("Expected an error but {} value(s) changed", count),
+        }
+    }
+
+    #[test]
+    fn deletes_siblings() {
+        let mut bin_file = bin_file();
+        let count = delete_values(&mut bin_file, &query("Entries[*].items[?@ != 2]")).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(values(&bin_file, "Entries[*].items[*]"), vec!["u8 = 2"]);
+
+        let count = delete_values(&mut bin_file, &query("Entries[*].scale")).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(values(&bin_file, "Entries[*].scale"), Vec::<String>::new());
+        assert_eq!(
+            values(&bin_file, "Entries[*].name"),
+            vec!["string = \"Ahri\""]
+        );
+
+        assert_eq!(
+            delete_values(&mut bin_file, &query("Entries[*]")).unwrap(),
+            1
+        );
+        assert!(bin_file.entries.items.is_empty());
+    }
+
+    #[test]
+    fn sets_values_of_their_type() {
+        let mut bin_file = bin_file();
+        assert_eq!(
+            set_values(&mut bin_file, &query("..scale"), "7").unwrap(),
+            1
+        );
+        assert_eq!(values(&bin_file, "..scale"), vec!["u8 = 7"]);
+
+        let error = edit_error(set_values(&mut bin_file, &query("..scale"), "\"big\""));
+        assert!(error.contains("Expected UInt8"), "{}", error);
+        let error = edit_error(set_values(&mut bin_file, &query("..scale"), "300"));
+        assert!(error.contains("UInt8"), "{}", error);
+        assert_eq!(values(&bin_file, "..scale"), vec!["u8 = 7"]);
+    }
+
+    #[test]
+    fn inserts_new_values_only() {
+        let mut bin_file = bin_file();
+        let entry = query("Entries[*]");
+        insert_values(&mut bin_file, &entry, "size: f32 = 2", None).unwrap();
+        assert_eq!(values(&bin_file, "..size"), vec!["f32 = 2"]);
+        let error = edit_error(insert_values(
+            &mut bin_file,
+            &entry,
+            "name: string = \"Ahri\"",
+            None,
+        ));
+        assert_eq!(error, "Field is already in the embed");
+
+        let tags = query("..tags");
+        insert_values(&mut bin_file, &tags, "\"b\" = 2", None).unwrap();
+        assert_eq!(values(&bin_file, "..tags[*]"), vec!["u8 = 1", "u8 = 2"]);
+        let error = edit_error(insert_values(&mut bin_file, &tags, "\"a\" = 3", None));
+        assert_eq!(error, "Key is already in the map");
+
+        let items = query("..items");
+        insert_values(&mut bin_file, &items, "0", Some(0)).unwrap();
+        assert_eq!(values(&bin_file, "..items[0]"), vec!["u8 = 0"]);
+        let error = edit_error(insert_values(&mut bin_file, &items, "9", Some(9)));
+        assert_eq!(error, "Cannot insert at 9, the list has 5 item(s)");
+    }
+}
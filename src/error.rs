@@ -0,0 +1,287 @@
+use hash_database::HashDatabase;
+use path::{BinPath, PathSegment};
+
+use std::{error, fmt, io};
+
+/// Everything that can go wrong while reading, writing or converting a bin.
+#[derive(Debug)]
+pub enum BinError {
+    /// The bin ended before `what` could be read at `offset`.
+    Truncated {
+        offset: u64,
+        what: String,
+    },
+    /// The file does not start with `PROP` (or `PTCH` followed by `PROP`).
+    BadSignature {
+        offset: u64,
+        signature: Vec<u8>,
+    },
+    /// A type byte that does not map to any `BinType`.
+    UnknownType {
+        offset: u64,
+        type_byte: u8,
+    },
+    /// A length prefix disagrees with the number of bytes actually read.
+    SizeMismatch {
+        what: String,
+        offset: u64,
+        expected: u64,
+        actual: u64,
+    },
+    /// A string in the bin is not valid UTF-8.
+    InvalidUtf8 {
+        offset: u64,
+    },
+    /// The JSON text itself could not be parsed.
+    JsonParse(json::Error),
+    /// The JSON parsed but does not have the shape of a bin file.
+    Json {
+        message: String,
+    },
+    /// The ritobin text could not be parsed at `line` and `column`.
+    Text {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A rewritten bin differs from the original, starting at `offset`.
+    RoundTrip {
+        offset: u64,
+        expected: Option<u8>,
+        actual: Option<u8>,
+    },
+    /// A `BinFile` that cannot be serialized, e.g. a `Vector3` with two items.
+    InvalidData {
+        message: String,
+    },
+    /// A PTCH patch that cannot be applied to the base bin.
+    Patch {
+        message: String,
+    },
+    /// A value that cannot be set, deleted or inserted where a query points.
+    Edit {
+        message: String,
+    },
+    /// A script that failed to compile or run.
+    Script {
+        message: String,
+    },
+    /// A query that could not be parsed at `column`.
+    Query {
+        column: usize,
+        message: String,
+    },
+    /// A WAD archive or entry that cannot be read.
+    Wad {
+        message: String,
+    },
+    Io(io::Error),
+    /// Another error, with the logical path and innermost class it happened in.
+    At {
+        path: BinPath,
+        class: Option<u32>,
+        source: Box<BinError>,
+    },
+}
+
+impl BinError {
+    pub(crate) fn json<S: Into<String>>(message: S) -> BinError {
+        BinError::Json {
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn invalid_data<S: Into<String>>(message: S) -> BinError {
+        BinError::InvalidData {
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn patch<S: Into<String>>(message: S) -> BinError {
+        BinError::Patch {
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn edit<S: Into<String>>(message: S) -> BinError {
+        BinError::Edit {
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn script<S: Into<String>>(message: S) -> BinError {
+        BinError::Script {
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn wad<S: Into<String>>(message: S) -> BinError {
+        BinError::Wad {
+            message: message.into(),
+        }
+    }
+
+    /// Prefixes the error path with the segment of the parent node.
+    pub(crate) fn in_path(self, segment: PathSegment) -> BinError {
+        match self {
+            BinError::At {
+                mut path,
+                class,
+                source,
+            } => {
+                path.segments.insert(0, segment);
+                BinError::At {
+                    path,
+                    class,
+                    source,
+                }
+            }
+            error => BinError::At {
+                path: BinPath {
+                    segments: vec![segment],
+                },
+                class: None,
+                source: Box::new(error),
+            },
+        }
+    }
+
+    /// Records the class the error happened in, unless a nested one already was.
+    pub(crate) fn in_class(self, name: u32) -> BinError {
+        match self {
+            BinError::At {
+                path,
+                class: None,
+                source,
+            } => BinError::At {
+                path,
+                class: Some(name),
+                source,
+            },
+            error @ BinError::At { .. } => error,
+            error => BinError::At {
+                path: BinPath::new(),
+                class: Some(name),
+                source: Box::new(error),
+            },
+        }
+    }
+
+    /// Logical path of the value the error happened at, if known.
+    pub fn path(&self) -> Option<&BinPath> {
+        match self {
+            BinError::At { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Formats the error, resolving path and class names through `hash_database`.
+    pub fn to_string_with(&self, hash_database: &HashDatabase) -> String {
+        match self {
+            BinError::At {
+                path,
+                class,
+                source,
+            } => {
+                let mut string = source.to_string_with(hash_database);
+                if !path.is_empty() {
+                    string.push_str(&format!(" at {}", path.to_string_with(hash_database)));
+                }
+                if let Some(class) = class {
+                    string.push_str(&format!(" in {}", hash_database.type_to_string(*class)));
+                }
+                string
+            }
+            error => error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for BinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinError::Truncated { offset, what } => {
+                write!(
+                    f,
+                    "Could not read {} at offset {}: unexpected end of file",
+                    what, offset
+                )
+            }
+            BinError::BadSignature { offset, signature } => write!(
+                f,
+                "Bin has no valid signature at offset {}: {:?}",
+                offset,
+                String::from_utf8_lossy(signature)
+            ),
+            BinError::UnknownType { offset, type_byte } => {
+                write!(f, "Unknown bin type {} at offset {}", type_byte, offset)
+            }
+            BinError::SizeMismatch {
+                what,
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Wrong {} size starting at offset {}. Its {}, but should have been {}",
+                what, offset, actual, expected
+            ),
+            BinError::InvalidUtf8 { offset } => {
+                write!(f, "Invalid UTF-8 sequence in string at offset {}", offset)
+            }
+            BinError::JsonParse(error) => write!(f, "Could not parse json: {}", error),
+            BinError::Json { message } => write!(f, "{}", message),
+            BinError::Text {
+                line,
+                column,
+                message,
+            } => write!(f, "{} at line {} column {}", message, line, column),
+            BinError::RoundTrip {
+                offset,
+                expected,
+                actual,
+            } => match (expected, actual) {
+                (Some(expected), Some(actual)) => write!(
+                    f,
+                    "Round trip differs at offset {}: 0x{:02X} became 0x{:02X}",
+                    offset, expected, actual
+                ),
+                (Some(_), None) => write!(f, "Round trip is truncated at offset {}", offset),
+                _ => write!(f, "Round trip has extra bytes at offset {}", offset),
+            },
+            BinError::InvalidData { message } => write!(f, "{}", message),
+            BinError::Patch { message } => write!(f, "{}", message),
+            BinError::Edit { message } => write!(f, "{}", message),
+            BinError::Script { message } => write!(f, "{}", message),
+            BinError::Query { column, message } => {
+                write!(f, "{} at column {}", message, column)
+            }
+            BinError::Wad { message } => write!(f, "{}", message),
+            BinError::Io(error) => write!(f, "{}", error),
+            BinError::At { .. } => write!(f, "{}", self.to_string_with(&HashDatabase::default())),
+        }
+    }
+}
+
+impl error::Error for BinError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BinError::JsonParse(error) => Some(error),
+            BinError::Io(error) => Some(error),
+            BinError::At { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BinError {
+    fn from(error: io::Error) -> BinError {
+        BinError::Io(error)
+    }
+}
+
+impl From<json::Error> for BinError {
+    fn from(error: json::Error) -> BinError {
+        BinError::JsonParse(error)
+    }
+}
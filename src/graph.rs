@@ -0,0 +1,250 @@
+use hash_database::HashDatabase;
+use links::{linked_files, resolve_links, BinLinks, LinkStatus};
+use path::{BinPath, PathSegment};
+
+use json::JsonValue;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    File,
+    /// A `linked_list` path that matches none of the files.
+    MissingFile,
+    Entry,
+    /// A link target that is the entry of none of the files.
+    MissingEntry,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// A file names another in its `linked_list`.
+    Includes,
+    /// A file has an entry.
+    Defines,
+    /// An entry, or a file with `files_only`, has a `Link` to another.
+    Links,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub id: String,
+    pub kind: NodeKind,
+    pub label: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+    /// Where the link is below its entry, like `mSpell.mEffects[3]`.
+    pub label: Option<String>,
+}
+
+/// Files and entries of a set of bins, with `linked_list` includes and `Link`
+/// references between them.
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    ids: HashMap<String, usize>,
+    edge_keys: HashSet<(usize, usize, EdgeKind, Option<String>)>,
+}
+
+impl NodeKind {
+    fn name(self) -> &'static str {
+        match self {
+            NodeKind::File => "file",
+            NodeKind::MissingFile => "missing-file",
+            NodeKind::Entry => "entry",
+            NodeKind::MissingEntry => "missing-entry",
+        }
+    }
+}
+
+impl EdgeKind {
+    fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Includes => "includes",
+            EdgeKind::Defines => "defines",
+            EdgeKind::Links => "links",
+        }
+    }
+}
+
+impl Graph {
+    /// Builds the graph of `bins`, naming entries through `hash_database`.
+    ///
+    /// With `files_only` there are no entry nodes, and links become edges from
+    /// the linking file to the files that have the target entry.
+    pub fn build(bins: &[BinLinks], hash_database: &HashDatabase, files_only: bool) -> Graph {
+        let mut graph = Graph::default();
+        let files: Vec<usize> = bins
+            .iter()
+            .map(|bin| graph.node(format!("file:{}", bin.name), NodeKind::File, &bin.name))
+            .collect();
+
+        let (linked, missing_files) = linked_files(bins);
+        for (file, linked) in linked.iter().enumerate() {
+            for to in linked {
+                graph.edge(files[file], files[*to], EdgeKind::Includes, None);
+            }
+        }
+        for missing in &missing_files {
+            let to = graph.node(
+                format!("file:{}", missing.linked),
+                NodeKind::MissingFile,
+                &missing.linked,
+            );
+            graph.edge(files[missing.file], to, EdgeKind::Includes, None);
+        }
+
+        if files_only {
+            for link in &resolve_links(bins).links {
+                let targets = match &link.status {
+                    LinkStatus::Resolved(file) => vec![*file],
+                    LinkStatus::NotLinked(targets) => targets.clone(),
+                    LinkStatus::Dangling => Vec::new(),
+                };
+                for target in targets.into_iter().filter(|target| *target != link.file) {
+                    graph.edge(files[link.file], files[target], EdgeKind::Links, None);
+                }
+            }
+            return graph;
+        }
+
+        let mut entries = HashMap::new();
+        for (file, bin) in bins.iter().enumerate() {
+            for entry in &bin.entries {
+                let node = graph.entry(*entry, NodeKind::Entry, hash_database);
+                entries.insert(*entry, node);
+                graph.edge(files[file], node, EdgeKind::Defines, None);
+            }
+        }
+        for (file, bin) in bins.iter().enumerate() {
+            for (path, target) in &bin.links {
+                let from = match path.segments.get(1) {
+                    Some(PathSegment::Hash(entry)) if entries.contains_key(entry) => entries[entry],
+                    _ => files[file],
+                };
+                let to = match entries.get(target) {
+                    Some(to) => *to,
+                    None => graph.entry(*target, NodeKind::MissingEntry, hash_database),
+                };
+                let below = BinPath {
+                    segments: path.segments.iter().skip(2).cloned().collect(),
+                };
+                graph.edge(
+                    from,
+                    to,
+                    EdgeKind::Links,
+                    Some(below.to_string_with(hash_database)),
+                );
+            }
+        }
+        graph
+    }
+
+    /// The graph as Graphviz DOT. Missing files and entries are dashed and red.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph bins {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            let style = match node.kind {
+                NodeKind::File => "shape=box",
+                NodeKind::MissingFile => "shape=box, style=dashed, color=red",
+                NodeKind::Entry => "shape=ellipse",
+                NodeKind::MissingEntry => "shape=ellipse, style=dashed, color=red",
+            };
+            dot.push_str(&format!(
+                "    {} [label={}, {}];\n",
+                dot_string(&node.id),
+                dot_string(&node.label),
+                style
+            ));
+        }
+        for edge in &self.edges {
+            let mut attributes = vec![match edge.kind {
+                EdgeKind::Includes => "style=bold".to_string(),
+                EdgeKind::Defines => "style=dotted, arrowhead=none".to_string(),
+                EdgeKind::Links => "color=blue".to_string(),
+            }];
+            if let Some(label) = &edge.label {
+                attributes.push(format!("label={}", dot_string(label)));
+            }
+            dot.push_str(&format!(
+                "    {} -> {} [{}];\n",
+                dot_string(&self.nodes[edge.from].id),
+                dot_string(&self.nodes[edge.to].id),
+                attributes.join(", ")
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The graph as a JSON object of `nodes` with `id`, `kind` and `label`,
+    /// and `edges` with `from` and `to` ids, `kind` and an optional `path`.
+    pub fn to_json(&self) -> JsonValue {
+        let mut nodes = JsonValue::new_array();
+        for node in &self.nodes {
+            let mut object = JsonValue::new_object();
+            object.insert("id", node.id.as_str()).unwrap();
+            object.insert("kind", node.kind.name()).unwrap();
+            object.insert("label", node.label.as_str()).unwrap();
+            nodes.push(object).unwrap();
+        }
+        let mut edges = JsonValue::new_array();
+        for edge in &self.edges {
+            let mut object = JsonValue::new_object();
+            object
+                .insert("from", self.nodes[edge.from].id.as_str())
+                .unwrap();
+            object
+                .insert("to", self.nodes[edge.to].id.as_str())
+                .unwrap();
+            object.insert("kind", edge.kind.name()).unwrap();
+            if let Some(label) = &edge.label {
+                object.insert("path", label.as_str()).unwrap();
+            }
+            edges.push(object).unwrap();
+        }
+        let mut object = JsonValue::new_object();
+        object.insert("nodes", nodes).unwrap();
+        object.insert("edges", edges).unwrap();
+        object
+    }
+
+    fn node(&mut self, id: String, kind: NodeKind, label: &str) -> usize {
+        if let Some(index) = self.ids.get(&id) {
+            return *index;
+        }
+        self.nodes.push(GraphNode {
+            id: id.clone(),
+            kind,
+            label: label.to_string(),
+        });
+        self.ids.insert(id, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn entry(&mut self, hash: u32, kind: NodeKind, hash_database: &HashDatabase) -> usize {
+        let label = hash_database.hash_to_string(hash);
+        self.node(format!("entry:0x{:08X}", hash), kind, &label)
+    }
+
+    /// Adds an edge unless the same one is already there.
+    fn edge(&mut self, from: usize, to: usize, kind: EdgeKind, label: Option<String>) {
+        if self.edge_keys.insert((from, to, kind, label.clone())) {
+            self.edges.push(GraphEdge {
+                from,
+                to,
+                kind,
+                label,
+            });
+        }
+    }
+}
+
+fn dot_string(string: &str) -> String {
+    format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,233 @@
+use hashes::{fnv1a_update, FNV1A_BASIS};
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// How many candidates a thread hashes before checking the shared budget.
+const BUDGET_CHUNK: u64 = 1 << 16;
+
+/// Splits CamelCase names like `mSpellDataID` into `Spell`, `Data` and `ID`,
+/// most frequent tokens first. A single lowercase prefix letter is dropped.
+pub fn camel_case_tokens<'a, I>(names: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for name in names {
+        let chars: Vec<char> = name.chars().collect();
+        let mut start = 0;
+        if chars.len() > 1 && chars[0].is_ascii_lowercase() && chars[1].is_ascii_uppercase() {
+            start = 1;
+        }
+
+        let mut token = String::new();
+        for index in start..chars.len() {
+            let c = chars[index];
+            if !c.is_ascii_alphanumeric() {
+                push_token(&mut token, &mut counts);
+                continue;
+            }
+            let previous = if index > start { chars[index - 1] } else { ' ' };
+            let next = chars.get(index + 1).cloned().unwrap_or(' ');
+            let boundary = (c.is_ascii_uppercase()
+                && (previous.is_ascii_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_ascii_uppercase() && next.is_ascii_lowercase())))
+                || (c.is_ascii_digit() && !previous.is_ascii_digit());
+            if boundary {
+                push_token(&mut token, &mut counts);
+            }
+            token.push(c);
+        }
+        push_token(&mut token, &mut counts);
+    }
+
+    let mut tokens: Vec<(String, usize)> = counts.into_iter().collect();
+    tokens.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    tokens.into_iter().map(|(token, _)| token).collect()
+}
+
+fn push_token(token: &mut String, counts: &mut HashMap<String, usize>) {
+    if !token.is_empty() {
+        *counts.entry(std::mem::take(token)).or_insert(0) += 1;
+    }
+}
+
+/// Words of a wordlist as tokens, capitalized so joined names read as CamelCase.
+pub fn wordlist_tokens(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty() && !word.starts_with('#'))
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// What `guess` combines and how much work it may do.
+#[derive(Debug, Clone)]
+pub struct GuessOptions {
+    /// Put in front of every candidate, like `m`, `b` and `f`. Include `""` for none.
+    pub prefixes: Vec<String>,
+    /// Joined in every order, one to `max_words` at a time.
+    pub tokens: Vec<String>,
+    pub max_words: usize,
+    /// Stops after about this many candidates have been hashed.
+    pub budget: u64,
+    pub threads: usize,
+}
+
+/// Names found by `guess`.
+#[derive(Debug, Default)]
+pub struct GuessResult {
+    pub matches: Vec<(u32, String)>,
+    /// Candidates hashed.
+    pub tried: u64,
+    /// Whether the budget ran out before every combination was tried.
+    pub stopped: bool,
+}
+
+struct Search<'a> {
+    targets: &'a HashSet<u32>,
+    options: &'a GuessOptions,
+    tried: AtomicU64,
+    stopped: AtomicBool,
+    matches: Mutex<Vec<(u32, String)>>,
+}
+
+impl<'a> Search<'a> {
+    /// Hashes every combination of `remaining` more tokens after `stack`.
+    fn extend(&self, state: u32, stack: &mut Vec<usize>, remaining: usize, local: &mut u64) {
+        if remaining == 0 {
+            *local += 1;
+            if self.targets.contains(&state) {
+                self.matches.lock().unwrap().push((state, self.name(stack)));
+            }
+            if *local >= BUDGET_CHUNK {
+                self.spend(local);
+            }
+            return;
+        }
+        for (index, token) in self.options.tokens.iter().enumerate() {
+            if self.stopped.load(Ordering::Relaxed) {
+                return;
+            }
+            stack.push(index);
+            self.extend(fnv1a_update(state, token), stack, remaining - 1, local);
+            stack.pop();
+        }
+    }
+
+    fn spend(&self, local: &mut u64) {
+        let tried = self.tried.fetch_add(*local, Ordering::Relaxed) + *local;
+        *local = 0;
+        if tried >= self.options.budget {
+            self.stopped.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn name(&self, stack: &[usize]) -> String {
+        let mut name = self.options.prefixes[stack[0]].clone();
+        for index in &stack[1..] {
+            name.push_str(&self.options.tokens[*index]);
+        }
+        name
+    }
+}
+
+/// Searches `prefix + token + token ...` names whose FNV-1a is in `targets`,
+/// shortest names first, on `options.threads` threads until the budget is spent.
+pub fn guess(targets: &HashSet<u32>, options: &GuessOptions) -> GuessResult {
+    let search = Search {
+        targets,
+        options,
+        tried: AtomicU64::new(0),
+        stopped: AtomicBool::new(false),
+        matches: Mutex::new(Vec::new()),
+    };
+    let token_count = options.tokens.len();
+    if targets.is_empty() || token_count == 0 || options.prefixes.is_empty() {
+        return GuessResult::default();
+    }
+
+    for words in 1..=options.max_words {
+        // Each work item is a prefix and a first token, taken by whichever thread is free.
+        let items = options.prefixes.len() * token_count;
+        let next = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..options.threads.max(1) {
+                scope.spawn(|| {
+                    let mut local = 0;
+                    let mut stack = Vec::with_capacity(words + 1);
+                    loop {
+                        let item = next.fetch_add(1, Ordering::Relaxed);
+                        if item >= items || search.stopped.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        let (prefix, token) = (item / token_count, item % token_count);
+                        let state = fnv1a_update(
+                            fnv1a_update(FNV1A_BASIS, &options.prefixes[prefix]),
+                            &options.tokens[token],
+                        );
+                        stack.clear();
+                        stack.push(prefix);
+                        stack.push(token);
+                        search.extend(state, &mut stack, words - 1, &mut local);
+                    }
+                    search.spend(&mut local);
+                });
+            }
+        });
+        if search.stopped.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+
+    let mut matches = search.matches.into_inner().unwrap();
+    matches.sort();
+    matches.dedup();
+    GuessResult {
+        matches,
+        tried: search.tried.into_inner(),
+        stopped: search.stopped.into_inner(),
+    }
+}
+
+/// Reads target hashes from lines starting with a hex hash, like a plain
+/// list or the `--unresolved` export, of which only the `# field` and
+/// `# class` sections are used. Other `#` lines are skipped.
+pub fn parse_targets(contents: &str) -> HashSet<u32> {
+    let mut targets = HashSet::new();
+    let mut wanted = true;
+    for line in contents.lines() {
+        if line.starts_with('#') {
+            wanted = line.starts_with("# field") || line.starts_with("# class");
+            continue;
+        }
+        if !wanted {
+            continue;
+        }
+        let word = match line.split_whitespace().next() {
+            Some(word) => word,
+            None => continue,
+        };
+        let hex = word.trim_start_matches("0x").trim_start_matches("0X");
+        if hex.len() <= 8 {
+            if let Ok(hash) = u32::from_str_radix(hex, 16) {
+                targets.insert(hash);
+            }
+        }
+    }
+    targets
+}
@@ -0,0 +1,211 @@
+use hashes::{fnv1a, xxhash};
+use structs::*;
+use unresolved::{HashRole, UnresolvedHashes};
+use walk::for_each_value;
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::OpenOptions,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// A name found for a hash that had none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Learned {
+    pub role: HashRole,
+    pub hash: u64,
+    pub name: String,
+}
+
+/// Adds every `String` value and linked file name of `bin_file` to `strings`.
+pub fn collect_strings(bin_file: &BinFile, strings: &mut HashSet<String>) {
+    for linked in &bin_file.linked_list {
+        strings.insert(linked.clone());
+    }
+    for_each_value(bin_file, |_, bin_data| {
+        if let BinData::String(string) = bin_data {
+            strings.insert(string.clone());
+        }
+    });
+}
+
+/// Names a string may have been hashed from: itself, lowercase, with `/`
+/// separators, without its extension and with `.dds`/`.tex` swapped.
+pub fn string_variants(string: &str) -> Vec<String> {
+    let mut variants: Vec<String> = Vec::new();
+    let mut push = |variant: String| {
+        if !variant.is_empty() && !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    };
+
+    let trimmed = string.trim();
+    let slashed = trimmed.replace('\\', "/");
+    push(trimmed.to_string());
+    push(slashed.clone());
+    push(slashed.to_lowercase());
+
+    let file_start = slashed.rfind('/').map_or(0, |index| index + 1);
+    if let Some(dot) = slashed[file_start..].rfind('.') {
+        let (stem, extension) = slashed.split_at(file_start + dot);
+        push(stem.to_string());
+        let swapped = match extension.to_lowercase().as_str() {
+            ".dds" => Some(".tex"),
+            ".tex" => Some(".dds"),
+            _ => None,
+        };
+        if let Some(swapped) = swapped {
+            push(format!("{}{}", stem, swapped).to_lowercase());
+        }
+    }
+
+    variants
+}
+
+/// Hashes every variant of `strings` and returns the ones matching an
+/// unresolved hash, with FNV-1a for bin names and XXHash for game paths.
+///
+/// Only entry names, `Hash` and `Link` values and game paths are matched
+/// unless `names` is set, since the many variants of every string collide
+/// with 32-bit class and field names.
+pub fn harvest(
+    strings: &HashSet<String>,
+    unresolved: &UnresolvedHashes,
+    names: bool,
+) -> Vec<Learned> {
+    let mut wanted_u32: BTreeMap<u32, Vec<HashRole>> = BTreeMap::new();
+    let mut wanted_u64: HashSet<u64> = HashSet::new();
+    for (role, hash) in unresolved.hashes.keys() {
+        match role {
+            HashRole::WadLink => {
+                wanted_u64.insert(*hash);
+            }
+            HashRole::Class | HashRole::Field if !names => {}
+            _ => wanted_u32.entry(*hash as u32).or_default().push(*role),
+        }
+    }
+
+    let mut learned: Vec<Learned> = Vec::new();
+    let mut found: HashSet<(HashRole, u64)> = HashSet::new();
+    let mut strings: Vec<&String> = strings.iter().collect();
+    strings.sort();
+
+    for string in strings {
+        for variant in string_variants(string) {
+            if variant.contains(char::is_whitespace) {
+                continue;
+            }
+            if let Some(roles) = wanted_u32.get(&fnv1a(&variant)) {
+                for role in roles {
+                    let hash = fnv1a(&variant) as u64;
+                    if found.insert((*role, hash)) {
+                        learned.push(Learned {
+                            role: *role,
+                            hash,
+                            name: variant.clone(),
+                        });
+                    }
+                }
+            }
+            let lowercase = variant.to_lowercase();
+            let hash = xxhash(&lowercase);
+            if wanted_u64.contains(&hash) && found.insert((HashRole::WadLink, hash)) {
+                learned.push(Learned {
+                    role: HashRole::WadLink,
+                    hash,
+                    name: lowercase,
+                });
+            }
+        }
+    }
+
+    learned
+}
+
+/// Appends learned names to the dictionary of their role in `dir`, one
+/// `hash name` line each, returning how many lines were written.
+pub fn append_to_dictionaries(dir: &Path, learned: &[Learned]) -> io::Result<usize> {
+    let mut by_file: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut written: HashSet<(&str, u64)> = HashSet::new();
+    for learned in learned {
+        let file_name = learned.role.file_name();
+        if !written.insert((file_name, learned.hash)) {
+            continue;
+        }
+        let line = if learned.role == HashRole::WadLink {
+            format!("{:016x} {}\n", learned.hash, learned.name)
+        } else {
+            format!("{:08x} {}\n", learned.hash, learned.name)
+        };
+        by_file.entry(file_name).or_default().push(line);
+    }
+
+    let mut lines = 0;
+    for (file_name, file_lines) in by_file {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(dir.join(file_name))?;
+
+        // Keep the first new line off the end of an unterminated last line.
+        if file.seek(SeekFrom::End(0))? > 0 {
+            file.seek(SeekFrom::End(-1))?;
+            let mut last = [0u8; 1];
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+
+        for line in &file_lines {
+            file.write_all(line.as_bytes())?;
+        }
+        lines += file_lines.len();
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hash_database::HashDatabase;
+    use text_reader::convert_text_to_bin;
+
+    fn learned(names: bool) -> Vec<(HashRole, String)> {
+        let bin_file = convert_text_to_bin(
+            "entries: map[hash,embed] = {
+                \"Skin0\" = SkinCharacterDataProperties {
+                    target: hash = 0x7b10d6ab
+                    paths: list[string] = { \"Skin1\" \"scale\" }
+                    scale: f32 = 1
+                }
+            }",
+        )
+        .unwrap();
+        let mut unresolved = UnresolvedHashes::new();
+        unresolved.collect(&bin_file, &HashDatabase::new());
+        let mut strings = HashSet::new();
+        collect_strings(&bin_file, &mut strings);
+        harvest(&strings, &unresolved, names)
+            .into_iter()
+            .map(|learned| (learned.role, learned.name))
+            .collect()
+    }
+
+    #[test]
+    fn learns_hash_values() {
+        assert_eq!(
+            learned(false),
+            vec![(HashRole::HashValue, "Skin1".to_string())]
+        );
+    }
+
+    #[test]
+    fn learns_field_names_only_when_asked() {
+        let learned = learned(true);
+        assert!(learned.contains(&(HashRole::Field, "scale".to_string())));
+        assert!(learned.contains(&(HashRole::HashValue, "Skin1".to_string())));
+    }
+}
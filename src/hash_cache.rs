@@ -0,0 +1,369 @@
+use hash_database::HASH_FILES;
+use hashes::{fnv1a, load_hashes_u32_from_file, load_hashes_u64_from_file};
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use memmap2::Mmap;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+const MAGIC: &[u8; 4] = b"BRHC";
+const FORMAT_VERSION: u32 = 1;
+
+/// Name of the cache file when it is written next to the text dictionaries.
+pub const CACHE_FILE: &str = "hashes.cache";
+
+/// Table index of each role in the cache, matching `HashDatabase`.
+pub const TYPES: usize = 0;
+pub const FIELDS: usize = 1;
+pub const HASHES: usize = 2;
+pub const GAME: usize = 3;
+
+const TABLE_COUNT: usize = 4;
+const STAMP_SIZE: usize = 24;
+const TABLE_HEADER_SIZE: usize = 16;
+const HEADER_SIZE: usize = 8 + HASH_FILES.len() * STAMP_SIZE + TABLE_COUNT * TABLE_HEADER_SIZE + 8;
+
+/// Size and modification time of a source dictionary, all zero when it is missing.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Stamp {
+    size: u64,
+    seconds: u64,
+    nanos: u32,
+    present: u32,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Stamp {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => return Stamp::default(),
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Stamp {
+            size: metadata.len(),
+            seconds: modified.as_secs(),
+            nanos: modified.subsec_nanos(),
+            present: 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Backing {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+/// Sorted, memory-mappable copy of the text dictionaries of one directory.
+///
+/// Layout, all little endian:
+/// `BRHC`, format version, one size/mtime stamp per file of `HASH_FILES`,
+/// count and offset of the types, fields, hashes and game tables, offset of
+/// the string pool, then the tables of `(key, string offset, string length)`
+/// records sorted by key, then the string pool.
+#[derive(Debug)]
+pub struct HashCache {
+    backing: Backing,
+}
+
+impl HashCache {
+    /// Maps the cache of `dir`, rebuilding it first if any dictionary changed.
+    ///
+    /// The cache is written to the per-user cache directory, or to `dir` when
+    /// there is none or it cannot be written, and kept in memory if neither
+    /// can be written.
+    /// Returns `None` when `dir` has no dictionaries.
+    pub fn open(dir: &Path) -> Option<HashCache> {
+        let stamps: Vec<Stamp> = HASH_FILES
+            .iter()
+            .map(|file| Stamp::of(&dir.join(file)))
+            .collect();
+        if stamps.iter().all(|stamp| stamp.present == 0) {
+            return None;
+        }
+
+        let locations = cache_locations(dir);
+        for location in &locations {
+            if let Some(cache) = HashCache::map(location) {
+                if cache.stamps() == stamps {
+                    return Some(cache);
+                }
+            }
+        }
+
+        eprintln!("Building hash cache for {}", dir.display());
+        let bytes = build(dir, &stamps);
+        for location in &locations {
+            if write_cache(location, &bytes).is_ok() {
+                if let Some(cache) = HashCache::map(location) {
+                    eprintln!("Wrote hash cache: {}", location.display());
+                    return Some(cache);
+                }
+            }
+        }
+        eprintln!("Could not write hash cache, keeping it in memory");
+        Some(HashCache {
+            backing: Backing::Owned(bytes),
+        })
+    }
+
+    fn map(path: &Path) -> Option<HashCache> {
+        let file = File::open(path).ok()?;
+        // Safety: the cache is only ever replaced through a rename, never
+        // written in place, so the mapped bytes do not change under us.
+        let mmap = unsafe { Mmap::map(&file) }.ok()?;
+        let cache = HashCache {
+            backing: Backing::Mapped(mmap),
+        };
+        if cache.is_valid() {
+            Some(cache)
+        } else {
+            None
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match &self.backing {
+            Backing::Mapped(mmap) => mmap,
+            Backing::Owned(bytes) => bytes,
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        let bytes = self.bytes();
+        if bytes.len() < HEADER_SIZE
+            || &bytes[0..4] != MAGIC
+            || LittleEndian::read_u32(&bytes[4..8]) != FORMAT_VERSION
+        {
+            return false;
+        }
+        let strings = self.strings_offset();
+        strings <= bytes.len()
+            && (0..TABLE_COUNT).all(|table| {
+                let (count, offset) = self.table(table);
+                count
+                    .checked_mul(record_size(table))
+                    .and_then(|size| offset.checked_add(size))
+                    .is_some_and(|end| end <= strings)
+            })
+    }
+
+    fn stamps(&self) -> Vec<Stamp> {
+        let bytes = self.bytes();
+        (0..HASH_FILES.len())
+            .map(|index| {
+                let stamp = &bytes[8 + index * STAMP_SIZE..];
+                Stamp {
+                    size: LittleEndian::read_u64(&stamp[0..8]),
+                    seconds: LittleEndian::read_u64(&stamp[8..16]),
+                    nanos: LittleEndian::read_u32(&stamp[16..20]),
+                    present: LittleEndian::read_u32(&stamp[20..24]),
+                }
+            })
+            .collect()
+    }
+
+    fn table(&self, table: usize) -> (usize, usize) {
+        let start = 8 + HASH_FILES.len() * STAMP_SIZE + table * TABLE_HEADER_SIZE;
+        let header = &self.bytes()[start..start + TABLE_HEADER_SIZE];
+        (
+            LittleEndian::read_u64(&header[0..8]) as usize,
+            LittleEndian::read_u64(&header[8..16]) as usize,
+        )
+    }
+
+    fn strings_offset(&self) -> usize {
+        LittleEndian::read_u64(&self.bytes()[HEADER_SIZE - 8..HEADER_SIZE]) as usize
+    }
+
+    /// Number of names in `table`.
+    pub fn len(&self, table: usize) -> usize {
+        self.table(table).0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..TABLE_COUNT).all(|table| self.len(table) == 0)
+    }
+
+    /// Looks up `key` in `table` by binary search over its sorted records.
+    pub fn get(&self, table: usize, key: u64) -> Option<&str> {
+        let (count, offset) = self.table(table);
+        let size = record_size(table);
+        let bytes = self.bytes();
+        let key_size = size - 8;
+
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let middle = (low + high) / 2;
+            let record = &bytes[offset + middle * size..offset + (middle + 1) * size];
+            let record_key = if key_size == 8 {
+                LittleEndian::read_u64(record)
+            } else {
+                LittleEndian::read_u32(record) as u64
+            };
+            if record_key < key {
+                low = middle + 1;
+            } else if record_key > key {
+                high = middle;
+            } else {
+                // Offsets come from the file, so a corrupt cache gives `None`.
+                let start = self
+                    .strings_offset()
+                    .checked_add(LittleEndian::read_u32(&record[key_size..key_size + 4]) as usize)?;
+                let len = LittleEndian::read_u32(&record[key_size + 4..]) as usize;
+                return bytes
+                    .get(start..start.checked_add(len)?)
+                    .and_then(|name| std::str::from_utf8(name).ok());
+            }
+        }
+        None
+    }
+}
+
+fn record_size(table: usize) -> usize {
+    if table == GAME {
+        16
+    } else {
+        12
+    }
+}
+
+/// Where the cache of `dir` may live, in order of preference.
+fn cache_locations(dir: &Path) -> Vec<PathBuf> {
+    let mut locations = Vec::new();
+    // Keeps the dictionary folder, often a checkout, free of generated files.
+    if let Some(cache_dir) = dirs::cache_dir() {
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let name = format!("hashes-{:08x}.cache", fnv1a(&dir.to_string_lossy()));
+        locations.push(cache_dir.join("binreader-rust").join(name));
+    }
+    locations.push(dir.join(CACHE_FILE));
+    locations
+}
+
+/// Writes through a temporary file and a rename, so mapped caches stay intact.
+fn write_cache(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp_path = path.with_extension("cache.tmp");
+    let mut file = File::create(&temp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+fn build(dir: &Path, stamps: &[Stamp]) -> Vec<u8> {
+    let mut tables: Vec<HashMap<u64, String>> = (0..TABLE_COUNT).map(|_| HashMap::new()).collect();
+    for file in HASH_FILES.iter() {
+        let path = dir.join(file);
+        if !path.is_file() {
+            continue;
+        }
+        let table = match *file {
+            "hashes.bintypes.txt" => TYPES,
+            "hashes.binfields.txt" => FIELDS,
+            "hashes.binhashes.txt" | "hashes.binentries.txt" => HASHES,
+            _ => GAME,
+        };
+        if table == GAME {
+            load_hashes_u64_from_file(&path, &mut tables[GAME]);
+        } else {
+            let mut hash_map: HashMap<u32, String> = HashMap::new();
+            load_hashes_u32_from_file(&path, &mut hash_map);
+            tables[table].extend(hash_map.into_iter().map(|(key, name)| (key as u64, name)));
+        }
+    }
+
+    let mut strings: Vec<u8> = Vec::new();
+    let mut records: Vec<Vec<u8>> = Vec::with_capacity(TABLE_COUNT);
+    for (table, hash_map) in tables.iter().enumerate() {
+        let mut keys: Vec<&u64> = hash_map.keys().collect();
+        keys.sort();
+
+        let mut table_records = Vec::with_capacity(keys.len() * record_size(table));
+        for key in keys {
+            let name = &hash_map[key];
+            if table == GAME {
+                table_records.write_u64::<LittleEndian>(*key).unwrap();
+            } else {
+                table_records
+                    .write_u32::<LittleEndian>(*key as u32)
+                    .unwrap();
+            }
+            table_records
+                .write_u32::<LittleEndian>(strings.len() as u32)
+                .unwrap();
+            table_records
+                .write_u32::<LittleEndian>(name.len() as u32)
+                .unwrap();
+            strings.extend_from_slice(name.as_bytes());
+        }
+        records.push(table_records);
+    }
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(
+        HEADER_SIZE + records.iter().map(Vec::len).sum::<usize>() + strings.len(),
+    );
+    bytes.extend_from_slice(MAGIC);
+    bytes.write_u32::<LittleEndian>(FORMAT_VERSION).unwrap();
+    for stamp in stamps {
+        bytes.write_u64::<LittleEndian>(stamp.size).unwrap();
+        bytes.write_u64::<LittleEndian>(stamp.seconds).unwrap();
+        bytes.write_u32::<LittleEndian>(stamp.nanos).unwrap();
+        bytes.write_u32::<LittleEndian>(stamp.present).unwrap();
+    }
+    let mut offset = HEADER_SIZE;
+    for (table, table_records) in records.iter().enumerate() {
+        bytes
+            .write_u64::<LittleEndian>((table_records.len() / record_size(table)) as u64)
+            .unwrap();
+        bytes.write_u64::<LittleEndian>(offset as u64).unwrap();
+        offset += table_records.len();
+    }
+    bytes.write_u64::<LittleEndian>(offset as u64).unwrap();
+
+    for table_records in &records {
+        bytes.extend_from_slice(table_records);
+    }
+    bytes.extend_from_slice(&strings);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_corrupt_string_offsets() {
+        let dir = std::env::temp_dir().join(format!("binreader-hash-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("hashes.binfields.txt"), "0000000a name\n").unwrap();
+        let mut bytes = build(&dir, &[Stamp::default(); HASH_FILES.len()]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let cache = HashCache {
+            backing: Backing::Owned(bytes.clone()),
+        };
+        assert!(cache.is_valid());
+        assert_eq!(cache.get(FIELDS, 10), Some("name"));
+
+        // The only record is the field's: key, string offset and length.
+        LittleEndian::write_u32(&mut bytes[HEADER_SIZE + 4..], u32::MAX);
+        LittleEndian::write_u32(&mut bytes[HEADER_SIZE + 8..], u32::MAX);
+        let cache = HashCache {
+            backing: Backing::Owned(bytes),
+        };
+        assert!(cache.is_valid());
+        assert_eq!(cache.get(FIELDS, 10), None);
+    }
+}
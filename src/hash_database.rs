@@ -0,0 +1,143 @@
+use hash_cache::{self, HashCache};
+use hashes::{add_to_hash_map, load_hashes_u32_from_file, load_hashes_u64_from_file};
+
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Hash dictionaries kept apart by the role of the hashed name, so a field
+/// name is never replaced by an unrelated entry name with the same hash.
+///
+/// Names in the tables below are looked up first, then the `HashCache` of
+/// the directory given to `open_dir`, which is only opened on first use.
+#[derive(Debug, Default)]
+pub struct HashDatabase {
+    /// Class names of Pointers and Embeddeds, from `hashes.bintypes.txt`.
+    pub types: HashMap<u32, String>,
+    /// Field names, from `hashes.binfields.txt`.
+    pub fields: HashMap<u32, String>,
+    /// Entry names and `Hash`/`Link` values, from `hashes.binhashes.txt` and `hashes.binentries.txt`.
+    pub hashes: HashMap<u32, String>,
+    /// `WadEntryLink` paths, from `hashes.game.txt` and `hashes.lcu.txt`.
+    pub game: HashMap<u64, String>,
+    cache_dir: Option<PathBuf>,
+    cache: OnceCell<Option<HashCache>>,
+}
+
+impl HashDatabase {
+    /// An empty database that only knows the names used by patches and merge conflicts.
+    pub fn new() -> HashDatabase {
+        let mut hash_database = HashDatabase::default();
+        add_to_hash_map(&["patch", "MergeConflict"], &mut hash_database.types);
+        add_to_hash_map(
+            &["path", "value", "base", "ours", "theirs"],
+            &mut hash_database.fields,
+        );
+        hash_database
+    }
+
+    /// Uses the dictionaries of `dir` through its binary cache, which is
+    /// opened, and rebuilt if stale, on the first lookup.
+    pub fn open_dir(&mut self, dir: &Path) {
+        self.cache_dir = Some(dir.to_path_buf());
+        self.cache = OnceCell::new();
+    }
+
+    fn cache(&self) -> Option<&HashCache> {
+        self.cache
+            .get_or_init(|| self.cache_dir.as_ref().and_then(|dir| HashCache::open(dir)))
+            .as_ref()
+    }
+
+    fn get_u32<'a>(
+        &'a self,
+        names: &'a HashMap<u32, String>,
+        table: usize,
+        hash: u32,
+    ) -> Option<&'a str> {
+        match names.get(&hash) {
+            Some(name) => Some(name),
+            None => self.cache()?.get(table, hash as u64),
+        }
+    }
+
+    /// Class name of a Pointer or Embedded.
+    pub fn get_type(&self, hash: u32) -> Option<&str> {
+        self.get_u32(&self.types, hash_cache::TYPES, hash)
+    }
+
+    pub fn get_field(&self, hash: u32) -> Option<&str> {
+        self.get_u32(&self.fields, hash_cache::FIELDS, hash)
+    }
+
+    /// Entry name or `Hash`/`Link` value.
+    pub fn get_hash(&self, hash: u32) -> Option<&str> {
+        self.get_u32(&self.hashes, hash_cache::HASHES, hash)
+    }
+
+    /// Game path of a `WadEntryLink`.
+    pub fn get_game(&self, hash: u64) -> Option<&str> {
+        match self.game.get(&hash) {
+            Some(name) => Some(name),
+            None => self.cache()?.get(hash_cache::GAME, hash),
+        }
+    }
+
+    /// Loads every known dictionary found in `dir` into the tables, returning how many hashes were added.
+    pub fn load_dir(&mut self, dir: &Path) -> u32 {
+        let mut lines = 0;
+        for file in HASH_FILES.iter() {
+            let path = dir.join(file);
+            if !path.is_file() {
+                continue;
+            }
+            lines += match *file {
+                "hashes.bintypes.txt" => load_hashes_u32_from_file(&path, &mut self.types),
+                "hashes.binfields.txt" => load_hashes_u32_from_file(&path, &mut self.fields),
+                "hashes.binhashes.txt" | "hashes.binentries.txt" => {
+                    load_hashes_u32_from_file(&path, &mut self.hashes)
+                }
+                _ => load_hashes_u64_from_file(&path, &mut self.game),
+            };
+        }
+        lines
+    }
+
+    pub fn type_to_string(&self, hash: u32) -> String {
+        u32_to_string(self.get_type(hash), hash)
+    }
+
+    pub fn field_to_string(&self, hash: u32) -> String {
+        u32_to_string(self.get_field(hash), hash)
+    }
+
+    pub fn hash_to_string(&self, hash: u32) -> String {
+        u32_to_string(self.get_hash(hash), hash)
+    }
+
+    pub fn game_to_string(&self, hash: u64) -> String {
+        match self.get_game(hash) {
+            Some(name) => name.to_string(),
+            None => format!("0x{:016X}", hash),
+        }
+    }
+}
+
+fn u32_to_string(name: Option<&str>, hash: u32) -> String {
+    match name {
+        Some(name) => name.to_string(),
+        None => format!("0x{:08X}", hash),
+    }
+}
+
+/// Dictionary files `HashDatabase::load_dir` looks for.
+pub const HASH_FILES: [&str; 6] = [
+    "hashes.bintypes.txt",
+    "hashes.binfields.txt",
+    "hashes.binhashes.txt",
+    "hashes.binentries.txt",
+    "hashes.lcu.txt",
+    "hashes.game.txt",
+];
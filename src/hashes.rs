@@ -1,14 +1,29 @@
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::io::Cursor;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Cursor},
+    path::Path,
+};
 
+/// Initial state of `fnv1a`, the hash of an empty string.
+pub const FNV1A_BASIS: u32 = 0x811c9dc5;
+
+/// Lowercase 32-bit FNV-1a, used for class, field, entry and `Hash` names.
 pub fn fnv1a(string: &str) -> u32 {
-    let mut hash: u32 = 0x811c9dc5;
+    fnv1a_update(FNV1A_BASIS, string)
+}
+
+/// Continues an FNV-1a `hash` with more characters, so
+/// `fnv1a_update(fnv1a(a), b) == fnv1a(a + b)`.
+pub fn fnv1a_update(mut hash: u32, string: &str) -> u32 {
     for c in string.chars() {
         hash = (hash ^ c.to_ascii_lowercase() as u32).wrapping_mul(0x01000193);
     }
     hash
 }
 
+/// 64-bit XXHash with seed 0, used for `WadEntryLink` game paths.
 pub fn xxhash(string: &str) -> u64 {
     let str_len = string.len() as u64;
     let mut str_cursor = Cursor::new(string);
@@ -38,7 +53,7 @@ pub fn xxhash(string: &str) -> u64 {
             v4 = xxh_rotl64(v4, 31);
             v4 = v4.wrapping_mul(PRIME1);
 
-            if str_cursor.position() <= str_limit {
+            if str_cursor.position() > str_limit {
                 break;
             }
         }
@@ -107,6 +122,88 @@ pub fn xxhash(string: &str) -> u64 {
     h64
 }
 
+/// Loads the 8 hex digit (FNV-1a) lines of a `hash name` dictionary file into
+/// `hash_map`, returning how many new hashes were added.
+pub fn load_hashes_u32_from_file(path: &Path, hash_map: &mut HashMap<u32, String>) -> u32 {
+    load_hashes_with(path, 8, |key, name| {
+        hash_map.insert(key as u32, name.to_string()).is_none()
+    })
+}
+
+/// Loads the 16 hex digit (XXHash) lines of a `hash name` dictionary file into
+/// `hash_map`, returning how many new hashes were added.
+pub fn load_hashes_u64_from_file(path: &Path, hash_map: &mut HashMap<u64, String>) -> u32 {
+    load_hashes_with(path, 16, |key, name| {
+        hash_map.insert(key, name.to_string()).is_none()
+    })
+}
+
+fn load_hashes_with<F>(path: &Path, key_len: usize, mut insert: F) -> u32
+where
+    F: FnMut(u64, &str) -> bool,
+{
+    let path_str = path.display();
+
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) => {
+            eprintln!("Could not open hash file: {} error: {}", path_str, error);
+            return 0;
+        }
+    };
+
+    let mut lines = 0;
+    let mut reader = BufReader::new(file);
+    let mut line = String::with_capacity(1024);
+
+    let msg = |error| {
+        eprintln!(
+            "Could not read line hash file: {} error: {}",
+            path_str, error
+        );
+        0
+    };
+
+    while reader.read_line(&mut line).unwrap_or_else(msg) != 0 {
+        let mut line_split = line.split(' ');
+
+        if line_split.clone().count() == 2 {
+            let key_str = line_split.next().unwrap();
+
+            if key_str.len() != key_len {
+                line.clear();
+                continue;
+            }
+
+            let key = u64::from_str_radix(key_str, 16).unwrap_or_else(|_| {
+                eprintln!("Invalid hex: {}", key_str);
+                0
+            });
+
+            if key == 0 {
+                line.clear();
+                continue;
+            }
+
+            let name = line_split.next().unwrap().trim_end_matches(['\n', '\r']);
+            lines += insert(key, name) as u32;
+        }
+
+        line.clear();
+    }
+
+    eprintln!("File: {} loaded: {} lines", path_str, lines);
+
+    lines
+}
+
+/// Inserts the FNV-1a of every name into `hash_map`.
+pub fn add_to_hash_map(hashes_to_insert: &[&str], hash_map: &mut HashMap<u32, String>) {
+    for hash_name in hashes_to_insert {
+        hash_map.insert(fnv1a(hash_name), hash_name.to_string());
+    }
+}
+
 fn xxh_read8(cursor: &mut Cursor<&str>) -> u8 {
     cursor.read_u8().expect("Could not read u8 XXHash")
 }
@@ -124,7 +221,7 @@ fn xxh_read64(cursor: &mut Cursor<&str>) -> u64 {
 }
 
 fn xxh_rotl64(x: u64, r: u64) -> u64 {
-    (x << r) | (x >> (64 - r))
+    x.rotate_left(r as u32)
 }
 
 const PRIME1: u64 = 0x9E3779B185EBCA87;
@@ -132,3 +229,21 @@ const PRIME2: u64 = 0xC2B2AE3D27D4EB4F;
 const PRIME3: u64 = 0x165667B19E3779F9;
 const PRIME4: u64 = 0x85EBCA77C2B2AE63;
 const PRIME5: u64 = 0x27D4EB2F165667C5;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xxhash_matches_xxh64() {
+        assert_eq!(xxhash(""), 0xEF46DB3751D8E999);
+        assert_eq!(xxhash("a"), 0xD24EC4F1A98C6E5B);
+        assert_eq!(xxhash("abc"), 0x44BC2CF5AD770999);
+        assert_eq!(xxhash(&"a".repeat(32)), 0x856E843298F99AD7);
+        assert_eq!(
+            xxhash("data/characters/ahri/skins/skin0.bin"),
+            0x49E643F9C8A74BC7
+        );
+        assert_eq!(xxhash(&"0123456789".repeat(10)), 0xF80E7B96315AFFFA);
+    }
+}
@@ -1,26 +1,39 @@
+use error::BinError;
 use hashes;
+use json_writer::is_object_key;
+use path::PathSegment;
+use schema::{FieldType, Schema};
 use structs::*;
 
 use json::JsonValue;
 
-fn string_to_hash_u32(value: &str) -> u32 {
-    if let Some(hex) = hex_or_decimal_from_string_u32(value) {
-        hex
+/// Parses a hex or decimal hash, or hashes a name with FNV-1a.
+pub fn string_to_hash_u32(value: &str) -> Result<u32, BinError> {
+    if check_valid_hex_or_decimal(value) {
+        hex_or_decimal_from_string_u32(value)
+            .ok_or_else(|| BinError::json(format!("Hash {} does not fit in 32 bits", value)))
     } else {
-        hashes::fnv1a(value)
+        Ok(hashes::fnv1a(value))
     }
 }
 
-fn string_to_hash_u64(value: &str) -> u64 {
-    if let Some(hex) = hex_or_decimal_from_string_u64(value) {
-        hex
+/// Parses a hex or decimal hash, or hashes a game path with XXHash.
+pub fn string_to_hash_u64(value: &str) -> Result<u64, BinError> {
+    if check_valid_hex_or_decimal(value) {
+        hex_or_decimal_from_string_u64(value)
+            .ok_or_else(|| BinError::json(format!("Hash {} does not fit in 64 bits", value)))
     } else {
-        hashes::xxhash(value)
+        Ok(hashes::xxhash(value))
     }
 }
 
-fn deserialize_bintype(bin_type: &JsonValue) -> BinType {
-    match bin_type.as_str().expect("Expected String") {
+fn expect<T>(value: Option<T>, message: &str) -> Result<T, BinError> {
+    value.ok_or_else(|| BinError::json(message))
+}
+
+/// Parses a bin type written by `json_writer`, like `"UInt32"`.
+pub fn deserialize_bintype(bin_type: &JsonValue) -> Result<BinType, BinError> {
+    let bin_type = match expect(bin_type.as_str(), "Expected bin type as String")? {
         "None" => BinType::None,
         "Bool" => BinType::Bool,
         "SInt8" => BinType::SInt8,
@@ -48,236 +61,406 @@ fn deserialize_bintype(bin_type: &JsonValue) -> BinType {
         "Link" => BinType::Link,
         "Map" => BinType::Map,
         "Flag" => BinType::Flag,
-        _ => panic!("Unknown bin type: {}", bin_type),
-    }
+        _ => return Err(BinError::json(format!("Unknown bin type: {}", bin_type))),
+    };
+    Ok(bin_type)
 }
 
-fn deserialize_bindata(value: &JsonValue, bin_type: &BinType) -> BinData {
-    match *bin_type {
-        BinType::None => BinData::None,
-        BinType::Bool => BinData::Bool(value.as_bool().expect("Expected Bool")),
-        BinType::SInt8 => BinData::SInt8(value.as_i8().expect("Expected SInt8")),
-        BinType::UInt8 => BinData::UInt8(value.as_u8().expect("Expected UInt8")),
-        BinType::SInt16 => BinData::SInt16(value.as_i16().expect("Expected SInt16")),
-        BinType::UInt16 => BinData::UInt16(value.as_u16().expect("Expected UInt16")),
-        BinType::SInt32 => BinData::SInt32(value.as_i32().expect("Expected SInt32")),
-        BinType::UInt32 => BinData::UInt32(value.as_u32().expect("Expected UInt32")),
-        BinType::SInt64 => BinData::SInt64(value.as_i64().expect("Expected SInt64")),
-        BinType::UInt64 => BinData::UInt64(value.as_u64().expect("Expected UInt64")),
-        BinType::Float32 => BinData::Float32(value.as_f32().expect("Expected Float32")),
-        BinType::Vector2 => {
-            if let JsonValue::Array(vec2_array) = value {
-                assert_eq!(vec2_array.len(), 2);
-                let mut vec2: Vec<f32> = Vec::with_capacity(2);
-                for value in vec2_array {
-                    vec2.push(value.as_f32().expect("Expected Vector2 as Float32"));
-                }
-                BinData::Vector2(vec2)
-            } else {
-                panic!("Expected Vector2 as Array");
-            }
-        }
-        BinType::Vector3 => {
-            if let JsonValue::Array(vec3_array) = value {
-                assert_eq!(vec3_array.len(), 3);
-                let mut vec3: Vec<f32> = Vec::with_capacity(3);
-                for value in vec3_array {
-                    vec3.push(value.as_f32().expect("Expected Vector3 as Float32"));
-                }
-                BinData::Vector3(vec3)
-            } else {
-                panic!("Expected Vector3 as Array");
-            }
-        }
-        BinType::Vector4 => {
-            if let JsonValue::Array(vec4_array) = value {
-                assert_eq!(vec4_array.len(), 4);
-                let mut vec4: Vec<f32> = Vec::with_capacity(4);
-                for value in vec4_array {
-                    vec4.push(value.as_f32().expect("Expected Vector4 as Float32"));
-                }
-                BinData::Vector4(vec4)
-            } else {
-                panic!("Expected Vector4 as Array");
-            }
+fn deserialize_floats(value: &JsonValue, count: usize, what: &str) -> Result<Vec<f32>, BinError> {
+    if let JsonValue::Array(array) = value {
+        if array.len() != count {
+            return Err(BinError::json(format!(
+                "Expected {} as Array of {} but found {}",
+                what,
+                count,
+                array.len()
+            )));
         }
-        BinType::Matrix4x4 => {
-            if let JsonValue::Array(mtx44_array) = value {
-                assert_eq!(mtx44_array.len(), 16);
-                let mut mtx44: Vec<f32> = Vec::with_capacity(16);
-                for value in mtx44_array {
-                    mtx44.push(value.as_f32().expect("Expected Matrix4x4 as Float32"));
-                }
-                BinData::Matrix4x4(mtx44)
-            } else {
-                panic!("Expected Matrix4x4 as Array");
-            }
+        let mut floats: Vec<f32> = Vec::with_capacity(count);
+        for (index, value) in array.iter().enumerate() {
+            floats.push(
+                expect(value.as_f32(), &format!("Expected {} as Float32", what))
+                    .map_err(|e| e.in_path(PathSegment::Index(index)))?,
+            );
         }
+        Ok(floats)
+    } else {
+        Err(BinError::json(format!("Expected {} as Array", what)))
+    }
+}
+
+fn deserialize_bindata(
+    value: &JsonValue,
+    bin_type: &BinType,
+    schema: Option<&Schema>,
+) -> Result<BinData, BinError> {
+    let bin_data = match *bin_type {
+        BinType::None => BinData::None,
+        BinType::Bool => BinData::Bool(expect(value.as_bool(), "Expected Bool")?),
+        BinType::SInt8 => BinData::SInt8(expect(value.as_i8(), "Expected SInt8")?),
+        BinType::UInt8 => BinData::UInt8(expect(value.as_u8(), "Expected UInt8")?),
+        BinType::SInt16 => BinData::SInt16(expect(value.as_i16(), "Expected SInt16")?),
+        BinType::UInt16 => BinData::UInt16(expect(value.as_u16(), "Expected UInt16")?),
+        BinType::SInt32 => BinData::SInt32(expect(value.as_i32(), "Expected SInt32")?),
+        BinType::UInt32 => BinData::UInt32(expect(value.as_u32(), "Expected UInt32")?),
+        BinType::SInt64 => BinData::SInt64(expect(value.as_i64(), "Expected SInt64")?),
+        BinType::UInt64 => BinData::UInt64(expect(value.as_u64(), "Expected UInt64")?),
+        BinType::Float32 => BinData::Float32(expect(value.as_f32(), "Expected Float32")?),
+        BinType::Vector2 => BinData::Vector2(deserialize_floats(value, 2, "Vector2")?),
+        BinType::Vector3 => BinData::Vector3(deserialize_floats(value, 3, "Vector3")?),
+        BinType::Vector4 => BinData::Vector4(deserialize_floats(value, 4, "Vector4")?),
+        BinType::Matrix4x4 => BinData::Matrix4x4(deserialize_floats(value, 16, "Matrix4x4")?),
         BinType::Rgba => {
             if let JsonValue::Array(rgba_array) = value {
-                assert_eq!(rgba_array.len(), 4);
+                if rgba_array.len() != 4 {
+                    return Err(BinError::json(format!(
+                        "Expected Rgba as Array of 4 but found {}",
+                        rgba_array.len()
+                    )));
+                }
                 let mut rgba: Vec<u8> = Vec::with_capacity(4);
-                for value in rgba_array {
-                    rgba.push(value.as_u8().expect("Expected Rgba as UInt8"));
+                for (index, value) in rgba_array.iter().enumerate() {
+                    rgba.push(
+                        expect(value.as_u8(), "Expected Rgba as UInt8")
+                            .map_err(|e| e.in_path(PathSegment::Index(index)))?,
+                    );
                 }
                 BinData::Rgba(rgba)
             } else {
-                panic!("Expected Rgba as Array");
+                return Err(BinError::json("Expected Rgba as Array"));
             }
         }
-        BinType::String => BinData::String(value.as_str().expect("Expected String").to_string()),
-        BinType::Hash => BinData::Hash(string_to_hash_u32(
-            value.as_str().expect("Expected Hash as String"),
-        )),
-        BinType::WadEntryLink => BinData::WadEntryLink(string_to_hash_u64(
-            value.as_str().expect("Expected WadEntryLink as String"),
-        )),
-        BinType::Container | BinType::Struct => deserialize_containerorstruct(value),
-        BinType::Pointer | BinType::Embedded => deserialize_pointerorembedded(value),
-        BinType::Optional => deserialize_optional(value),
-        BinType::Link => BinData::Link(string_to_hash_u32(
-            value.as_str().expect("Expected Link as String"),
-        )),
-        BinType::Map => deserialize_map(value),
-        BinType::Flag => BinData::Flag(value.as_bool().expect("Expected Flag as Bool")),
-    }
+        BinType::String => BinData::String(expect(value.as_str(), "Expected String")?.to_string()),
+        BinType::Hash => BinData::Hash(string_to_hash_u32(expect(
+            value.as_str(),
+            "Expected Hash as String",
+        )?)?),
+        BinType::WadEntryLink => BinData::WadEntryLink(string_to_hash_u64(expect(
+            value.as_str(),
+            "Expected WadEntryLink as String",
+        )?)?),
+        BinType::Container | BinType::Struct => deserialize_containerorstruct(value, schema)?,
+        BinType::Pointer | BinType::Embedded => deserialize_pointerorembedded(value, schema)?,
+        BinType::Optional => deserialize_optional(value, schema)?,
+        BinType::Link => BinData::Link(string_to_hash_u32(expect(
+            value.as_str(),
+            "Expected Link as String",
+        )?)?),
+        BinType::Map => deserialize_map(value, schema)?,
+        BinType::Flag => BinData::Flag(expect(value.as_bool(), "Expected Flag as Bool")?),
+    };
+    Ok(bin_data)
 }
 
-fn deserialize_containerorstruct(object: &JsonValue) -> BinData {
+fn deserialize_containerorstruct(
+    object: &JsonValue,
+    schema: Option<&Schema>,
+) -> Result<BinData, BinError> {
     let mut object = object.entries();
-    let btype = deserialize_bintype(object.next().expect("Expected container or struct type").1);
-    let data: Vec<BinData> = object
-        .next()
-        .expect("Expected container or struct data")
+    let btype = deserialize_bintype(expect(object.next(), "Expected container or struct type")?.1)?;
+    let data = expect(object.next(), "Expected container or struct data")?
         .1
         .members()
-        .map(|field| deserialize_bindata(field, &btype))
-        .collect();
-    BinData::ContainerOrStruct(ContainerOrStruct::new(btype, data))
+        .enumerate()
+        .map(|(index, field)| {
+            deserialize_bindata(field, &btype, schema)
+                .map_err(|e| e.in_path(PathSegment::Index(index)))
+        })
+        .collect::<Result<Vec<BinData>, BinError>>()?;
+    Ok(BinData::ContainerOrStruct(ContainerOrStruct::new(
+        btype, data,
+    )))
 }
 
-fn deserialize_binfield(object: &JsonValue) -> BinField {
+fn deserialize_binfield(object: &JsonValue, schema: Option<&Schema>) -> Result<BinField, BinError> {
     let mut object = object.entries();
-    let name = string_to_hash_u32(
-        object
-            .next()
-            .expect("Expected bin field name")
-            .1
-            .as_str()
-            .expect("Expected bin field name as string"),
-    );
-    let btype = deserialize_bintype(object.next().expect("Expected bin field type").1);
-    let data = deserialize_bindata(object.next().expect("Expected bin field data").1, &btype);
-    BinField::new(name, btype, data)
+    let name = string_to_hash_u32(expect(
+        expect(object.next(), "Expected bin field name")?.1.as_str(),
+        "Expected bin field name as string",
+    )?)?;
+    let in_field = |e: BinError| e.in_path(PathSegment::Field(name));
+    let btype = deserialize_bintype(
+        expect(object.next(), "Expected bin field type")
+            .map_err(in_field)?
+            .1,
+    )
+    .map_err(in_field)?;
+    let data = deserialize_bindata(
+        expect(object.next(), "Expected bin field data")
+            .map_err(in_field)?
+            .1,
+        &btype,
+        schema,
+    )
+    .map_err(in_field)?;
+    Ok(BinField::new(name, btype, data))
 }
 
-fn deserialize_pointerorembedded(object: &JsonValue) -> BinData {
+fn deserialize_pointerorembedded(
+    object: &JsonValue,
+    schema: Option<&Schema>,
+) -> Result<BinData, BinError> {
     let mut object = object.entries();
-    if object.len() > 0 {
-        let namedata = object.next().expect("Expected pointer or embedded object");
-        let name = string_to_hash_u32(namedata.0);
-        let data: Vec<BinField> = namedata.1.members().map(deserialize_binfield).collect();
-        BinData::PointerOrEmbedded(PointerOrEmbedded::new(name, data))
+    if let Some(namedata) = object.next() {
+        let name = string_to_hash_u32(namedata.0)?;
+        let data = if namedata.1.is_object() {
+            deserialize_compact_fields(namedata.1, name, schema)
+        } else {
+            namedata
+                .1
+                .members()
+                .map(|field| deserialize_binfield(field, schema))
+                .collect::<Result<Vec<BinField>, BinError>>()
+        }
+        .map_err(|e| e.in_class(name))?;
+        Ok(BinData::PointerOrEmbedded(PointerOrEmbedded::new(
+            name, data,
+        )))
     } else {
-        BinData::PointerOrEmbedded(PointerOrEmbedded::new(0, Vec::new()))
+        Ok(BinData::PointerOrEmbedded(PointerOrEmbedded::new(
+            0,
+            Vec::new(),
+        )))
     }
 }
 
-fn deserialize_optional(object: &JsonValue) -> BinData {
+/// Reads the `{"field": value}` object of a class written with a schema,
+/// taking the type of every field from `schema`.
+fn deserialize_compact_fields(
+    object: &JsonValue,
+    class: u32,
+    schema: Option<&Schema>,
+) -> Result<Vec<BinField>, BinError> {
+    let mut fields = Vec::new();
+    for (name, value) in object.entries() {
+        let name = string_to_hash_u32(name)?;
+        let in_field = |e: BinError| e.in_path(PathSegment::Field(name));
+        let schema = expect(
+            schema,
+            "Expected class fields as Array, or a schema for plain fields",
+        )
+        .map_err(in_field)?;
+        let field_type = expect(
+            schema.get_field(class, name),
+            "Field is not in the schema, write its class as an Array of {name, type, data}",
+        )
+        .map_err(in_field)?
+        .field_type();
+        let data = deserialize_compact(value, &field_type, schema).map_err(in_field)?;
+        fields.push(BinField::new(name, field_type.btype, data));
+    }
+    Ok(fields)
+}
+
+fn deserialize_compact(
+    value: &JsonValue,
+    field_type: &FieldType,
+    schema: &Schema,
+) -> Result<BinData, BinError> {
+    let valuetype = field_type.valuetype.unwrap_or(BinType::None);
+    let bin_data = match field_type.btype {
+        BinType::Container | BinType::Struct => {
+            if !value.is_array() {
+                return Err(BinError::json(format!(
+                    "Expected {:?} as Array",
+                    field_type.btype
+                )));
+            }
+            let items = value
+                .members()
+                .enumerate()
+                .map(|(index, item)| {
+                    deserialize_bindata(item, &valuetype, Some(schema))
+                        .map_err(|e| e.in_path(PathSegment::Index(index)))
+                })
+                .collect::<Result<Vec<BinData>, BinError>>()?;
+            BinData::ContainerOrStruct(ContainerOrStruct::new(valuetype, items))
+        }
+        BinType::Optional => {
+            let item = if value.is_null() {
+                None
+            } else {
+                Some(deserialize_bindata(value, &valuetype, Some(schema))?)
+            };
+            BinData::Optional(Optional::new(valuetype, item))
+        }
+        BinType::Map => {
+            let keytype = field_type.keytype.unwrap_or(BinType::None);
+            let mut items = Vec::new();
+            if is_object_key(keytype) {
+                if !value.is_object() {
+                    return Err(BinError::json("Expected Map as Object"));
+                }
+                for (index, (key, value)) in value.entries().enumerate() {
+                    let keydata =
+                        deserialize_bindata(&JsonValue::String(key.to_string()), &keytype, None)
+                            .map_err(|e| e.in_path(PathSegment::Index(index)))?;
+                    let segment = PathSegment::from_map_key(&keydata, index);
+                    let valuedata = deserialize_bindata(value, &valuetype, Some(schema))
+                        .map_err(|e| e.in_path(segment))?;
+                    items.push(MapPair::new(keydata, valuedata));
+                }
+            } else {
+                if !value.is_array() {
+                    return Err(BinError::json("Expected Map as Array of [key, value]"));
+                }
+                for (index, pair) in value.members().enumerate() {
+                    let in_index = |e: BinError| e.in_path(PathSegment::Index(index));
+                    if !pair.is_array() || pair.len() != 2 {
+                        return Err(in_index(BinError::json("Expected [key, value] in Map")));
+                    }
+                    let keydata =
+                        deserialize_bindata(&pair[0], &keytype, Some(schema)).map_err(in_index)?;
+                    let valuedata = deserialize_bindata(&pair[1], &valuetype, Some(schema))
+                        .map_err(in_index)?;
+                    items.push(MapPair::new(keydata, valuedata));
+                }
+            }
+            BinData::Map(Map::new(keytype, valuetype, items))
+        }
+        btype => deserialize_bindata(value, &btype, Some(schema))?,
+    };
+    Ok(bin_data)
+}
+
+fn deserialize_optional(object: &JsonValue, schema: Option<&Schema>) -> Result<BinData, BinError> {
     let mut object = object.entries();
-    let btype = deserialize_bintype(object.next().expect("Expected optional type").1);
-    let mut data = object.next().expect("Expected optional data").1.members();
-    let item = if data.len() > 0 {
-        let value = data.next().expect("Expected optional data value");
-        Some(deserialize_bindata(value, &btype))
-    } else {
-        None
+    let btype = deserialize_bintype(expect(object.next(), "Expected optional type")?.1)?;
+    let item = match expect(object.next(), "Expected optional data")?
+        .1
+        .members()
+        .next()
+    {
+        Some(value) => Some(deserialize_bindata(value, &btype, schema)?),
+        None => None,
     };
-    BinData::Optional(Optional::new(btype, item))
+    Ok(BinData::Optional(Optional::new(btype, item)))
 }
 
-fn deserialize_mappair(object: &JsonValue, keytype: &BinType, valuetype: &BinType) -> MapPair {
+fn deserialize_mappair(
+    object: &JsonValue,
+    keytype: &BinType,
+    valuetype: &BinType,
+    index: usize,
+    schema: Option<&Schema>,
+) -> Result<MapPair, BinError> {
     let mut object = object.entries();
+    let in_index = |e: BinError| e.in_path(PathSegment::Index(index));
     match keytype {
         BinType::WadEntryLink | BinType::Hash | BinType::Link => {
-            let keyvalue = object.next().expect("Expected object in MapPair");
-            let keydata = deserialize_bindata(&JsonValue::String(keyvalue.0.to_string()), keytype);
-            let valuedata = deserialize_bindata(keyvalue.1, valuetype);
-            MapPair::new(keydata, valuedata)
+            let keyvalue = expect(object.next(), "Expected object in MapPair").map_err(in_index)?;
+            let keydata =
+                deserialize_bindata(&JsonValue::String(keyvalue.0.to_string()), keytype, None)
+                    .map_err(in_index)?;
+            let segment = PathSegment::from_map_key(&keydata, index);
+            let valuedata = deserialize_bindata(keyvalue.1, valuetype, schema)
+                .map_err(|e| e.in_path(segment))?;
+            Ok(MapPair::new(keydata, valuedata))
         }
         _ => {
-            let key = object.next().expect("Expected keydata object in MapPair");
-            let keydata = deserialize_bindata(key.1, keytype);
-            let value = object.next().expect("Expected valuedata object in MapPair");
-            let valuedata = deserialize_bindata(value.1, valuetype);
-            MapPair::new(keydata, valuedata)
+            let key =
+                expect(object.next(), "Expected keydata object in MapPair").map_err(in_index)?;
+            let keydata = deserialize_bindata(key.1, keytype, schema).map_err(in_index)?;
+            let value =
+                expect(object.next(), "Expected valuedata object in MapPair").map_err(in_index)?;
+            let valuedata = deserialize_bindata(value.1, valuetype, schema).map_err(in_index)?;
+            Ok(MapPair::new(keydata, valuedata))
         }
     }
 }
 
-fn deserialize_map(object: &JsonValue) -> BinData {
-    let mut object = object.entries();
-    let keytype = deserialize_bintype(object.next().expect("Expected map keytype").1);
-    let valuetype = deserialize_bintype(object.next().expect("Expected map valuetype").1);
-    let data: Vec<MapPair> = object
-        .next()
-        .expect("Expected map data")
-        .1
+fn deserialize_mappairs(
+    array: &JsonValue,
+    keytype: &BinType,
+    valuetype: &BinType,
+    schema: Option<&Schema>,
+) -> Result<Vec<MapPair>, BinError> {
+    array
         .members()
-        .map(|field| deserialize_mappair(field, &keytype, &valuetype))
-        .collect();
-    BinData::Map(Map::new(keytype, valuetype, data))
+        .enumerate()
+        .map(|(index, field)| deserialize_mappair(field, keytype, valuetype, index, schema))
+        .collect()
+}
+
+fn deserialize_map(object: &JsonValue, schema: Option<&Schema>) -> Result<BinData, BinError> {
+    let mut object = object.entries();
+    let keytype = deserialize_bintype(expect(object.next(), "Expected map keytype")?.1)?;
+    let valuetype = deserialize_bintype(expect(object.next(), "Expected map valuetype")?.1)?;
+    let data = deserialize_mappairs(
+        expect(object.next(), "Expected map data")?.1,
+        &keytype,
+        &valuetype,
+        schema,
+    )?;
+    Ok(BinData::Map(Map::new(keytype, valuetype, data)))
 }
 
-pub fn convert_json_to_bin(contents: &str) -> BinFile {
-    println!("Converting JSON to bin");
+/// Parses JSON produced by `convert_bin_to_json` back into a bin file.
+pub fn convert_json_to_bin(contents: &str) -> Result<BinFile, BinError> {
+    convert_json_to_bin_with_schema(contents, None)
+}
 
-    let root = json::parse(contents).expect("Could not parse json");
+/// Parses JSON back into a bin file, taking the types of classes written as
+/// plain `{"field": value}` objects from `schema`.
+pub fn convert_json_to_bin_with_schema(
+    contents: &str,
+    schema: Option<&Schema>,
+) -> Result<BinFile, BinError> {
+    let root = json::parse(contents)?;
 
-    let is_patch = root["IsPatch"].as_bool().expect("Expected bool in IsPatch");
+    let is_patch = expect(root["IsPatch"].as_bool(), "Expected bool in IsPatch")
+        .map_err(|e| e.in_path(PathSegment::Root("IsPatch")))?;
 
     let unknown = if is_patch {
-        Some(root["Unknown"].as_u64().expect("Expected u64 in Unknown"))
+        Some(
+            expect(root["Unknown"].as_u64(), "Expected u64 in Unknown")
+                .map_err(|e| e.in_path(PathSegment::Root("Unknown")))?,
+        )
     } else {
         None
     };
 
-    let version = root["Version"].as_u32().expect("Expected u32 in Version");
-    let linked_list: Vec<String> = root["LinkedList"]
+    let version = expect(root["Version"].as_u32(), "Expected u32 in Version")
+        .map_err(|e| e.in_path(PathSegment::Root("Version")))?;
+    let linked_list = root["LinkedList"]
         .members()
-        .map(|linked| {
-            linked
-                .as_str()
-                .expect("Expected string in LinkedList")
-                .to_string()
+        .enumerate()
+        .map(|(index, linked)| {
+            expect(linked.as_str(), "Expected string in LinkedList")
+                .map(|linked| linked.to_string())
+                .map_err(|e| {
+                    e.in_path(PathSegment::Index(index))
+                        .in_path(PathSegment::Root("LinkedList"))
+                })
         })
-        .collect();
+        .collect::<Result<Vec<String>, BinError>>()?;
 
     let entries = Map::new(
         BinType::Hash,
         BinType::Embedded,
-        root["Entries"]
-            .members()
-            .map(|entry| deserialize_mappair(entry, &BinType::Hash, &BinType::Embedded))
-            .collect(),
+        deserialize_mappairs(&root["Entries"], &BinType::Hash, &BinType::Embedded, schema)
+            .map_err(|e| e.in_path(PathSegment::Root("Entries")))?,
     );
 
     let patches: Option<Map> = if is_patch {
         Some(Map::new(
             BinType::Hash,
             BinType::Embedded,
-            root["Patches"]
-                .members()
-                .map(|entry| deserialize_mappair(entry, &BinType::Hash, &BinType::Embedded))
-                .collect(),
+            deserialize_mappairs(&root["Patches"], &BinType::Hash, &BinType::Embedded, schema)
+                .map_err(|e| e.in_path(PathSegment::Root("Patches")))?,
         ))
     } else {
         None
     };
 
-    println!("Finished converting JSON to bin");
-
-    BinFile::new(is_patch, unknown, version, linked_list, entries, patches)
+    Ok(BinFile::new(
+        is_patch,
+        unknown,
+        version,
+        linked_list,
+        entries,
+        patches,
+    ))
 }
 
 fn hex_or_decimal_from_string_u32(string: &str) -> Option<u32> {
@@ -302,9 +485,9 @@ fn hex_or_decimal_from_string_u32(string: &str) -> Option<u32> {
     let str_iter_str = str_iter.clone().collect::<String>();
 
     if str_iter_str.starts_with("0x") | str_iter_str.starts_with("0X") {
-        result = u32::from_str_radix(&str_iter_str[2..], 16).unwrap();
+        result = u32::from_str_radix(&str_iter_str[2..], 16).ok()?;
     } else {
-        result = str_iter_str.parse::<u32>().unwrap();
+        result = str_iter_str.parse::<u32>().ok()?;
     }
 
     if negative {
@@ -336,9 +519,9 @@ fn hex_or_decimal_from_string_u64(string: &str) -> Option<u64> {
     let str_iter_str = str_iter.clone().collect::<String>();
 
     if str_iter_str.starts_with("0x") | str_iter_str.starts_with("0X") {
-        result = u64::from_str_radix(&str_iter_str[2..], 16).unwrap();
+        result = u64::from_str_radix(&str_iter_str[2..], 16).ok()?;
     } else {
-        result = str_iter_str.parse::<u64>().unwrap();
+        result = str_iter_str.parse::<u64>().ok()?;
     }
 
     if negative {
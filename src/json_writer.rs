@@ -1,31 +1,19 @@
+use hash_database::HashDatabase;
+use schema::{FieldType, Schema};
 use structs::*;
 
 use json::{codegen::Generator, JsonValue};
-use std::collections::HashMap;
-
-fn hash_u32_to_string(value: u32, hash_map: &mut HashMap<u64, String>) -> String {
-    let str_value = hash_map.get(&(value as u64));
-    if let Some(str_value) = str_value {
-        str_value.clone()
-    } else {
-        format!("0x{:08X}", value)
-    }
-}
-
-fn hash_u64_to_string(value: u64, hash_map: &mut HashMap<u64, String>) -> String {
-    let str_value = hash_map.get(&value);
-    if let Some(str_value) = str_value {
-        str_value.clone()
-    } else {
-        format!("0x{:016X}", value)
-    }
-}
+use std::collections::HashSet;
 
 fn serialize_bintype(bintype: &BinType) -> JsonValue {
     JsonValue::String(format!("{:?}", bintype))
 }
 
-fn serialize_bindata(bindata: &BinData, hash_map: &mut HashMap<u64, String>) -> JsonValue {
+fn serialize_bindata(
+    bindata: &BinData,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
+) -> JsonValue {
     match bindata {
         BinData::None => JsonValue::Null,
         BinData::Bool(bool) => JsonValue::Boolean(*bool),
@@ -79,26 +67,29 @@ fn serialize_bindata(bindata: &BinData, hash_map: &mut HashMap<u64, String>) ->
             JsonValue::Array(rgba_array)
         }
         BinData::String(string) => JsonValue::String(string.clone()),
-        BinData::Hash(hash) => JsonValue::String(hash_u32_to_string(*hash, hash_map)),
+        BinData::Hash(hash) => JsonValue::String(hash_database.hash_to_string(*hash)),
         BinData::WadEntryLink(wadentrylink) => {
-            JsonValue::String(hash_u64_to_string(*wadentrylink, hash_map))
+            JsonValue::String(hash_database.game_to_string(*wadentrylink))
         }
-        BinData::ContainerOrStruct(cs) => serialize_containerorstruct(cs, hash_map),
-        BinData::PointerOrEmbedded(pe) => serialize_pointerorembedded(pe, hash_map),
-        BinData::Optional(optional) => serialize_optional(optional, hash_map),
-        BinData::Link(link) => JsonValue::String(hash_u32_to_string(*link, hash_map)),
-        BinData::Map(map) => serialize_map(map, hash_map),
+        BinData::ContainerOrStruct(cs) => serialize_containerorstruct(cs, hash_database, schema),
+        BinData::PointerOrEmbedded(pe) => serialize_pointerorembedded(pe, hash_database, schema),
+        BinData::Optional(optional) => serialize_optional(optional, hash_database, schema),
+        BinData::Link(link) => JsonValue::String(hash_database.hash_to_string(*link)),
+        BinData::Map(map) => serialize_map(map, hash_database, schema),
         BinData::Flag(flag) => JsonValue::Boolean(*flag),
     }
 }
 
 fn serialize_containerorstruct(
     cs: &ContainerOrStruct,
-    hash_map: &mut HashMap<u64, String>,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
 ) -> JsonValue {
     let mut array = JsonValue::new_array();
     for bindata in &cs.items {
-        array.push(serialize_bindata(bindata, hash_map)).unwrap();
+        array
+            .push(serialize_bindata(bindata, hash_database, schema))
+            .unwrap();
     }
     let mut object = JsonValue::new_object();
     object.insert("type", serialize_bintype(&cs.btype)).unwrap();
@@ -106,45 +97,178 @@ fn serialize_containerorstruct(
     object
 }
 
-fn serialize_binfield(binfield: &BinField, hash_map: &mut HashMap<u64, String>) -> JsonValue {
+fn serialize_binfield(
+    binfield: &BinField,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
+) -> JsonValue {
     let mut object = JsonValue::new_object();
     object
         .insert(
             "name",
-            JsonValue::String(hash_u32_to_string(binfield.name, hash_map)),
+            JsonValue::String(hash_database.field_to_string(binfield.name)),
         )
         .unwrap();
     object
         .insert("type", serialize_bintype(&binfield.btype))
         .unwrap();
     object
-        .insert("data", serialize_bindata(&binfield.data, hash_map))
+        .insert(
+            "data",
+            serialize_bindata(&binfield.data, hash_database, schema),
+        )
         .unwrap();
     object
 }
 
 fn serialize_pointerorembedded(
     pe: &PointerOrEmbedded,
-    hash_map: &mut HashMap<u64, String>,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
 ) -> JsonValue {
+    let mut object = JsonValue::new_object();
+    let name = hash_database.type_to_string(pe.name);
+    if let Some(schema) = schema.filter(|schema| is_compact(pe, schema)) {
+        let mut fields = JsonValue::new_object();
+        for binfield in &pe.items {
+            fields
+                .insert(
+                    hash_database.field_to_string(binfield.name).as_str(),
+                    serialize_compact(&binfield.data, hash_database, schema),
+                )
+                .unwrap();
+        }
+        object.insert(name.as_str(), fields).unwrap();
+        return object;
+    }
+
     let mut array = JsonValue::new_array();
     for binfield in &pe.items {
-        array.push(serialize_binfield(binfield, hash_map)).unwrap();
+        array
+            .push(serialize_binfield(binfield, hash_database, schema))
+            .unwrap();
     }
-    let mut object = JsonValue::new_object();
-    object
-        .insert(hash_u32_to_string(pe.name, hash_map).as_str(), array)
-        .unwrap();
+    object.insert(name.as_str(), array).unwrap();
     object
 }
 
-fn serialize_optional(optional: &Optional, hash_map: &mut HashMap<u64, String>) -> JsonValue {
+/// Whether every field of `pe` has the type `schema` gives it, so the class
+/// can be written as an object of plain values. Nested containers, maps and
+/// optionals need their own type tags, and maps with keys of another type
+/// than their `keytype` cannot be objects, so these keep the class verbose.
+fn is_compact(pe: &PointerOrEmbedded, schema: &Schema) -> bool {
+    let is_nested = |btype: Option<BinType>| {
+        matches!(
+            btype,
+            Some(BinType::None)
+                | Some(BinType::Container)
+                | Some(BinType::Struct)
+                | Some(BinType::Optional)
+                | Some(BinType::Map)
+        )
+    };
+    let mut names = HashSet::new();
+    pe.name != 0
+        && pe.items.iter().all(|binfield| {
+            let field_type = FieldType::of(binfield);
+            names.insert(binfield.name)
+                && schema
+                    .get_field(pe.name, binfield.name)
+                    .is_some_and(|field| field.field_type() == field_type)
+                && !is_nested(field_type.keytype)
+                && !is_nested(field_type.valuetype)
+                && has_object_keys(&binfield.data)
+        })
+}
+
+/// Whether the keys of a map written as a JSON object all have its `keytype`.
+fn has_object_keys(bindata: &BinData) -> bool {
+    match bindata {
+        BinData::Map(map) if is_object_key(map.keytype) => map.items.iter().all(|mappair| {
+            matches!(
+                (map.keytype, &*mappair.keydata),
+                (BinType::Hash, BinData::Hash(_))
+                    | (BinType::Link, BinData::Link(_))
+                    | (BinType::WadEntryLink, BinData::WadEntryLink(_))
+                    | (BinType::String, BinData::String(_))
+            )
+        }),
+        _ => true,
+    }
+}
+
+/// Writes a field value without type tags: containers as plain arrays,
+/// optionals as their value or null, and maps keyed by hashes or strings as
+/// objects, other maps as arrays of `[key, value]` pairs.
+fn serialize_compact(
+    bindata: &BinData,
+    hash_database: &HashDatabase,
+    schema: &Schema,
+) -> JsonValue {
+    match bindata {
+        BinData::ContainerOrStruct(cs) => JsonValue::Array(
+            cs.items
+                .iter()
+                .map(|item| serialize_bindata(item, hash_database, Some(schema)))
+                .collect(),
+        ),
+        BinData::Optional(optional) => match &optional.data {
+            Some(item) => serialize_bindata(item, hash_database, Some(schema)),
+            None => JsonValue::Null,
+        },
+        BinData::Map(map) if is_object_key(map.keytype) => {
+            let mut object = JsonValue::new_object();
+            for mappair in &map.items {
+                let key = match *mappair.keydata {
+                    BinData::Hash(key) | BinData::Link(key) => hash_database.hash_to_string(key),
+                    BinData::WadEntryLink(key) => hash_database.game_to_string(key),
+                    BinData::String(ref string) => string.clone(),
+                    // `is_compact` leaves maps with other keys verbose.
+                    _ => unreachable!(),
+                };
+                object
+                    .insert(
+                        key.as_str(),
+                        serialize_bindata(&mappair.valuedata, hash_database, Some(schema)),
+                    )
+                    .unwrap();
+            }
+            object
+        }
+        BinData::Map(map) => JsonValue::Array(
+            map.items
+                .iter()
+                .map(|mappair| {
+                    JsonValue::Array(vec![
+                        serialize_bindata(&mappair.keydata, hash_database, Some(schema)),
+                        serialize_bindata(&mappair.valuedata, hash_database, Some(schema)),
+                    ])
+                })
+                .collect(),
+        ),
+        bindata => serialize_bindata(bindata, hash_database, Some(schema)),
+    }
+}
+
+/// Whether compact maps with keys of `keytype` are written as JSON objects.
+pub fn is_object_key(keytype: BinType) -> bool {
+    matches!(
+        keytype,
+        BinType::String | BinType::Hash | BinType::Link | BinType::WadEntryLink
+    )
+}
+
+fn serialize_optional(
+    optional: &Optional,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
+) -> JsonValue {
     let mut object = JsonValue::new_object();
     object
         .insert("type", serialize_bintype(&optional.btype))
         .unwrap();
     if let Some(bindata) = &optional.data {
-        let item = serialize_bindata(bindata, hash_map);
+        let item = serialize_bindata(bindata, hash_database, schema);
         object
             .insert("data", JsonValue::Array([item].to_vec()))
             .unwrap();
@@ -154,14 +278,18 @@ fn serialize_optional(optional: &Optional, hash_map: &mut HashMap<u64, String>)
     object
 }
 
-fn serialize_mappair(mappair: &MapPair, hash_map: &mut HashMap<u64, String>) -> JsonValue {
+fn serialize_mappair(
+    mappair: &MapPair,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
+) -> JsonValue {
     match *mappair.keydata {
         BinData::Hash(key) | BinData::Link(key) => {
             let mut object = JsonValue::new_object();
             object
                 .insert(
-                    hash_u32_to_string(key, hash_map).as_str(),
-                    serialize_bindata(&mappair.valuedata, hash_map),
+                    hash_database.hash_to_string(key).as_str(),
+                    serialize_bindata(&mappair.valuedata, hash_database, schema),
                 )
                 .unwrap();
             object
@@ -170,8 +298,8 @@ fn serialize_mappair(mappair: &MapPair, hash_map: &mut HashMap<u64, String>) ->
             let mut object = JsonValue::new_object();
             object
                 .insert(
-                    hash_u64_to_string(key, hash_map).as_str(),
-                    serialize_bindata(&mappair.valuedata, hash_map),
+                    hash_database.game_to_string(key).as_str(),
+                    serialize_bindata(&mappair.valuedata, hash_database, schema),
                 )
                 .unwrap();
             object
@@ -179,20 +307,28 @@ fn serialize_mappair(mappair: &MapPair, hash_map: &mut HashMap<u64, String>) ->
         _ => {
             let mut object = JsonValue::new_object();
             object
-                .insert("keydata", serialize_bindata(&mappair.keydata, hash_map))
+                .insert(
+                    "keydata",
+                    serialize_bindata(&mappair.keydata, hash_database, schema),
+                )
                 .unwrap();
             object
-                .insert("valuedata", serialize_bindata(&mappair.valuedata, hash_map))
+                .insert(
+                    "valuedata",
+                    serialize_bindata(&mappair.valuedata, hash_database, schema),
+                )
                 .unwrap();
             object
         }
     }
 }
 
-fn serialize_map(map: &Map, hash_map: &mut HashMap<u64, String>) -> JsonValue {
+fn serialize_map(map: &Map, hash_database: &HashDatabase, schema: Option<&Schema>) -> JsonValue {
     let mut array = JsonValue::new_array();
     for mappair in &map.items {
-        array.push(serialize_mappair(mappair, hash_map)).unwrap();
+        array
+            .push(serialize_mappair(mappair, hash_database, schema))
+            .unwrap();
     }
     let mut object = JsonValue::new_object();
     object
@@ -205,9 +341,23 @@ fn serialize_map(map: &Map, hash_map: &mut HashMap<u64, String>) -> JsonValue {
     object
 }
 
-pub fn convert_bin_to_json(bin_file: &BinFile, hash_map: &mut HashMap<u64, String>) -> String {
-    println!("Converting bin to JSON");
+/// Converts one value to JSON the way it is written inside a bin file.
+pub fn bindata_to_json(bindata: &BinData, hash_database: &HashDatabase) -> JsonValue {
+    serialize_bindata(bindata, hash_database, None)
+}
 
+/// Converts a bin file to pretty printed JSON, resolving hashes through `hash_database`.
+pub fn convert_bin_to_json(bin_file: &BinFile, hash_database: &HashDatabase) -> String {
+    convert_bin_to_json_with_schema(bin_file, hash_database, None)
+}
+
+/// Converts a bin file to JSON, writing the classes whose fields match
+/// `schema` as plain `{"field": value}` objects without type tags.
+pub fn convert_bin_to_json_with_schema(
+    bin_file: &BinFile,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
+) -> String {
     let mut root = JsonValue::new_object();
 
     root.insert("IsPatch", JsonValue::Boolean(bin_file.is_patch))
@@ -230,7 +380,9 @@ pub fn convert_bin_to_json(bin_file: &BinFile, hash_map: &mut HashMap<u64, Strin
     let mut entries = JsonValue::new_array();
 
     for entry in &bin_file.entries.items {
-        entries.push(serialize_mappair(entry, hash_map)).unwrap();
+        entries
+            .push(serialize_mappair(entry, hash_database, schema))
+            .unwrap();
     }
 
     root.insert("Entries", entries).unwrap();
@@ -240,7 +392,7 @@ pub fn convert_bin_to_json(bin_file: &BinFile, hash_map: &mut HashMap<u64, Strin
 
         for patch in &patches.items {
             patches_array
-                .push(serialize_mappair(patch, hash_map))
+                .push(serialize_mappair(patch, hash_database, schema))
                 .unwrap();
         }
 
@@ -250,11 +402,10 @@ pub fn convert_bin_to_json(bin_file: &BinFile, hash_map: &mut HashMap<u64, Strin
     let mut gen = MyPrettyGenerator::new();
     gen.write_json(&root).expect("Can't write json");
 
-    println!("Finished converting bin to JSON");
-
     gen.consume()
 }
 
+/// Tab indented JSON generator that keeps number arrays on a single line.
 pub struct MyPrettyGenerator {
     buf: Vec<u8>,
     dent: u16,
@@ -273,6 +424,12 @@ impl MyPrettyGenerator {
     }
 }
 
+impl Default for MyPrettyGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl json::codegen::Generator for MyPrettyGenerator {
     type T = Vec<u8>;
 
@@ -408,3 +565,65 @@ fn from_f32(float: f32) -> JsonValue {
         json::number::Number::from_parts_unchecked(false, float.to_bits() as u64, -1)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use json_reader::convert_json_to_bin_with_schema;
+    use text_reader::convert_text_to_bin;
+
+    fn bin_file() -> BinFile {
+        convert_text_to_bin(
+            "entries: map[hash,embed] = {
+                \"Skin0\" = SkinCharacterDataProperties {
+                    name: string = \"Ahri\"
+                    scale: f32 = 1.5
+                    position: vec3 = { 1, 2, 3 }
+                    items: list[u8] = { 1 2 3 }
+                    tags: map[hash,u8] = { \"a\" = 1 \"b\" = 2 }
+                    counts: map[u32,string] = { 1 = \"one\" }
+                    extra: option[f32] = { 3 }
+                    none: option[f32] = { }
+                    target: link = \"Skin1\"
+                    unit: pointer = Unit { name: string = \"a\" }
+                }
+            }",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trips_compact_json() {
+        let bin_file = bin_file();
+        let mut schema = Schema::new();
+        schema.record(&bin_file);
+        let hash_database = HashDatabase::new();
+
+        let json = convert_bin_to_json_with_schema(&bin_file, &hash_database, Some(&schema));
+        let verbose = convert_bin_to_json_with_schema(&bin_file, &hash_database, None);
+        assert!(json.len() < verbose.len());
+        assert!(!json.contains("\"type\""), "{}", json);
+
+        let read = convert_json_to_bin_with_schema(&json, Some(&schema)).unwrap();
+        assert_eq!(read, bin_file);
+    }
+
+    #[test]
+    fn keeps_maps_with_mismatched_keys_verbose() {
+        let mut bin_file = bin_file();
+        let mut schema = Schema::new();
+        schema.record(&bin_file);
+        if let BinData::PointerOrEmbedded(pe) = &mut *bin_file.entries.items[0].valuedata {
+            for field in &mut pe.items {
+                if let BinData::Map(map) = &mut *field.data {
+                    if map.keytype == BinType::Hash {
+                        *map.items[0].keydata = BinData::String("a".to_string());
+                    }
+                }
+            }
+        }
+
+        let json = convert_bin_to_json_with_schema(&bin_file, &HashDatabase::new(), Some(&schema));
+        assert!(json.contains("\"type\""), "{}", json);
+    }
+}
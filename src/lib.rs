@@ -0,0 +1,73 @@
+//! League Of Legends Bin Reader And Writter
+//!
+//! Reads and writes the `PROP`/`PTCH` bin format used by League Of Legends,
+//! and converts it to and from JSON or ritobin text.
+//!
+//! ```no_run
+//! use binreader_rust::HashDatabase;
+//!
+//! let contents = std::fs::read("skin0.bin")?;
+//! let bin_file = binreader_rust::read_bin(&contents)?;
+//!
+//! let mut hash_database = HashDatabase::new();
+//! hash_database.open_dir("files".as_ref());
+//! let json = binreader_rust::convert_bin_to_json(&bin_file, &hash_database);
+//!
+//! let bin_file = binreader_rust::convert_json_to_bin(&json)?;
+//! let bin = binreader_rust::write_bin(&bin_file)?;
+//! assert_eq!(bin, contents);
+//! # Ok::<(), binreader_rust::BinError>(())
+//! ```
+
+extern crate byteorder;
+extern crate dirs;
+extern crate dtoa;
+extern crate flate2;
+extern crate json;
+extern crate memmap2;
+extern crate rhai;
+extern crate zstd;
+
+pub mod diff;
+pub mod edit;
+pub mod error;
+pub mod graph;
+pub mod guess;
+pub mod harvest;
+pub mod hash_cache;
+pub mod hash_database;
+pub mod hashes;
+pub mod json_reader;
+pub mod json_writer;
+pub mod links;
+pub mod merge;
+pub mod patch;
+pub mod path;
+pub mod query;
+pub mod reader;
+pub mod schema;
+pub mod script;
+pub mod structs;
+pub mod text_reader;
+pub mod text_writer;
+pub mod unresolved;
+pub mod validate;
+pub mod wad;
+pub mod walk;
+pub mod writer;
+
+pub use error::BinError;
+pub use hash_database::HashDatabase;
+pub use hashes::{fnv1a, xxhash};
+pub use json_reader::{convert_json_to_bin, convert_json_to_bin_with_schema};
+pub use json_writer::{convert_bin_to_json, convert_bin_to_json_with_schema};
+pub use path::{BinPath, PathSegment};
+pub use reader::read_bin;
+pub use schema::Schema;
+pub use structs::{
+    BinData, BinField, BinFile, BinType, ContainerOrStruct, Map, MapPair, Optional,
+    PointerOrEmbedded,
+};
+pub use text_reader::convert_text_to_bin;
+pub use text_writer::convert_bin_to_text;
+pub use writer::{check_round_trip, write_bin};
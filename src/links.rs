@@ -0,0 +1,184 @@
+use path::BinPath;
+use structs::*;
+use walk::for_each_value;
+
+use std::collections::{HashMap, HashSet};
+
+/// What link resolution needs of one bin file, without its values.
+#[derive(Debug, Clone)]
+pub struct BinLinks {
+    /// Path of the file, matched against the `linked_list` of other files.
+    pub name: String,
+    pub linked_list: Vec<String>,
+    /// Name hashes of the entries.
+    pub entries: Vec<u32>,
+    /// Every non null `Link` value and where it is.
+    pub links: Vec<(BinPath, u32)>,
+}
+
+impl BinLinks {
+    pub fn of(name: &str, bin_file: &BinFile) -> BinLinks {
+        let entries = bin_file
+            .entries
+            .items
+            .iter()
+            .filter_map(|pair| match *pair.keydata {
+                BinData::Hash(hash) => Some(hash),
+                _ => None,
+            })
+            .collect();
+        let mut links = Vec::new();
+        for_each_value(bin_file, |path, bin_data| {
+            if let BinData::Link(hash) = *bin_data {
+                if hash != 0 {
+                    links.push((path.clone(), hash));
+                }
+            }
+        });
+        BinLinks {
+            name: name.to_string(),
+            linked_list: bin_file.linked_list.clone(),
+            entries,
+            links,
+        }
+    }
+}
+
+/// Where the entry a link points to was found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkStatus {
+    /// In the file itself or one it links through `linked_list`, by index.
+    Resolved(usize),
+    /// Only in files the linking file does not reach through `linked_list`.
+    NotLinked(Vec<usize>),
+    /// In none of the files.
+    Dangling,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedLink {
+    /// Index of the file the link is in.
+    pub file: usize,
+    pub path: BinPath,
+    pub target: u32,
+    pub status: LinkStatus,
+}
+
+/// A `linked_list` path that matches none of the files.
+#[derive(Debug, Clone)]
+pub struct MissingLinkedFile {
+    pub file: usize,
+    pub linked: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkResolution {
+    pub links: Vec<ResolvedLink>,
+    pub missing_files: Vec<MissingLinkedFile>,
+}
+
+impl LinkResolution {
+    pub fn dangling(&self) -> usize {
+        self.count(|status| *status == LinkStatus::Dangling)
+    }
+
+    pub fn not_linked(&self) -> usize {
+        self.count(|status| matches!(status, LinkStatus::NotLinked(_)))
+    }
+
+    fn count<F: Fn(&LinkStatus) -> bool>(&self, f: F) -> usize {
+        self.links.iter().filter(|link| f(&link.status)).count()
+    }
+}
+
+/// Resolves the links of every file against the entries of all `bins`.
+///
+/// A file sees its own entries and those of the files its `linked_list`
+/// names, transitively, as `linked_files` matches them.
+pub fn resolve_links(bins: &[BinLinks]) -> LinkResolution {
+    let mut entry_files: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, bin) in bins.iter().enumerate() {
+        for entry in &bin.entries {
+            entry_files.entry(*entry).or_default().push(index);
+        }
+    }
+
+    let (linked_files, missing_files) = linked_files(bins);
+    let mut resolution = LinkResolution {
+        links: Vec::new(),
+        missing_files,
+    };
+    for (index, bin) in bins.iter().enumerate() {
+        let scope = reachable(index, &linked_files);
+        for (path, target) in &bin.links {
+            let files = entry_files.get(target).map_or(&[][..], |files| &files[..]);
+            let status = if files.contains(&index) {
+                LinkStatus::Resolved(index)
+            } else if let Some(file) = files.iter().find(|file| scope.contains(file)) {
+                LinkStatus::Resolved(*file)
+            } else if files.is_empty() {
+                LinkStatus::Dangling
+            } else {
+                LinkStatus::NotLinked(files.to_vec())
+            };
+            resolution.links.push(ResolvedLink {
+                file: index,
+                path: path.clone(),
+                target: *target,
+                status,
+            });
+        }
+    }
+    resolution
+}
+
+/// Indices of the files each file names in its `linked_list`, and the names
+/// that match none of them.
+///
+/// `linked_list` paths match files whose path ends with them, ignoring case,
+/// slashes and the extension, so JSON and text files stand in for the bins
+/// they were decoded from.
+pub fn linked_files(bins: &[BinLinks]) -> (Vec<Vec<usize>>, Vec<MissingLinkedFile>) {
+    let names: Vec<String> = bins.iter().map(|bin| normalize(&bin.name)).collect();
+    let mut missing_files = Vec::new();
+    let mut linked_files = Vec::with_capacity(bins.len());
+    for (index, bin) in bins.iter().enumerate() {
+        let mut files = Vec::new();
+        for linked in &bin.linked_list {
+            let linked_name = normalize(linked);
+            let found = names.iter().position(|name| {
+                name == &linked_name || name.ends_with(&format!("/{}", linked_name))
+            });
+            match found {
+                Some(found) => files.push(found),
+                None => missing_files.push(MissingLinkedFile {
+                    file: index,
+                    linked: linked.clone(),
+                }),
+            }
+        }
+        linked_files.push(files);
+    }
+    (linked_files, missing_files)
+}
+
+/// The file and every file it links through `linked_list`, transitively.
+fn reachable(file: usize, linked_files: &[Vec<usize>]) -> HashSet<usize> {
+    let mut seen = HashSet::new();
+    let mut pending = vec![file];
+    while let Some(file) = pending.pop() {
+        if seen.insert(file) {
+            pending.extend(&linked_files[file]);
+        }
+    }
+    seen
+}
+
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/").to_lowercase();
+    let file_start = path.rfind('/').map_or(0, |slash| slash + 1);
+    match path[file_start..].rfind('.') {
+        Some(dot) => path[..file_start + dot].to_string(),
+        None => path,
+    }
+}
@@ -1,23 +1,38 @@
-extern crate byteorder;
+extern crate binreader_rust;
 extern crate clap;
-extern crate dtoa;
+extern crate dirs;
 extern crate glob;
 extern crate json;
 
+use binreader_rust::{
+    diff, edit,
+    graph::Graph,
+    guess, harvest,
+    hash_database::{HashDatabase, HASH_FILES},
+    hashes, json_reader, json_writer, links, merge, patch,
+    query::Query,
+    reader,
+    schema::Schema,
+    script::Script,
+    text_reader, text_writer,
+    unresolved::UnresolvedHashes,
+    validate,
+    wad::{Wad, WadCompression, WadEntry},
+    writer, BinError, BinFile,
+};
+use json::{codegen::Generator, JsonValue};
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader, Read, Write},
-    path::Path,
+    cell::OnceCell,
+    collections::{HashMap, HashSet},
+    env,
+    fs::{self, File},
+    io::{self, BufRead, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    thread,
 };
 
-mod hashes;
-mod json_reader;
-mod json_writer;
-mod reader;
-mod structs;
-mod writer;
-
 fn main() {
     let matches = clap::Command::new("BinReader-Rust")
         .version(env!("CARGO_PKG_VERSION"))
@@ -25,6 +40,13 @@ fn main() {
         .about("League Of Legends Bin Reader And Writter")
         .arg_required_else_help(true)
         .subcommand_required(true)
+        .arg(
+            clap::Arg::new("hashes")
+                .long("hashes")
+                .value_name("DIR")
+                .help("Sets the directory with the hashes.*.txt dictionaries")
+                .global(true),
+        )
         .subcommand(
             clap::Command::new("decode")
                 .about("Decodes the given file")
@@ -39,6 +61,27 @@ fn main() {
                         .help("Sets the output file to use")
                         .required(false)
                         .index(2),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .help("Sets the format to decode to, json or ritobin text")
+                        .value_parser(["json", "text"])
+                        .default_value("json"),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Writes classes matching the given schema as plain JSON objects"),
+                )
+                .arg(
+                    clap::Arg::new("unresolved")
+                        .long("unresolved")
+                        .value_name("FILE")
+                        .help(
+                            "Writes every hash without a name to the given file, grouped by role",
+                        ),
                 ),
         )
         .subcommand(
@@ -55,182 +98,1924 @@ fn main() {
                         .help("Sets the output file to use")
                         .required(false)
                         .index(2),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .help("Sets the format to encode from, json or ritobin text")
+                        .value_parser(["json", "text"])
+                        .default_value("json"),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads the field types of plain JSON objects from the given schema"),
                 ),
         )
-        .get_matches();
-
-    match matches.subcommand() {
-        Some(("decode", args)) => {
-            let input = args.get_one::<String>("INPUT").unwrap();
-            let output = args.get_one::<String>("OUTPUT");
-
-            let mut hash_map: HashMap<u64, String> = HashMap::new();
-            add_to_hash_map(&["path", "patch", "value"], &mut hash_map);
-
-            println!("Loading hashes");
-            let mut lines =
-                load_hashes_from_file(Path::new("files/hashes.bintypes.txt"), &mut hash_map);
-            lines += load_hashes_from_file(Path::new("files/hashes.binfields.txt"), &mut hash_map);
-            lines += load_hashes_from_file(Path::new("files/hashes.binhashes.txt"), &mut hash_map);
-            lines += load_hashes_from_file(Path::new("files/hashes.binentries.txt"), &mut hash_map);
-            lines += load_hashes_from_file(Path::new("files/hashes.lcu.txt"), &mut hash_map);
-            lines += load_hashes_from_file(Path::new("files/hashes.game.txt"), &mut hash_map);
-            println!("Loaded total of hashes: {lines}");
-            println!("Finished loading hashes.\n");
-
-            match output {
-                Some(output) => {
-                    let contents = read_to_u8(Path::new(input));
-                    let bin_file = reader::read_bin(&contents);
-                    let jsonstr = json_writer::convert_bin_to_json(&bin_file, &mut hash_map);
-                    write_u8(Path::new(output), jsonstr.as_bytes());
-                }
-                None => {
-                    let input_paths = glob::glob(input)
-                        .expect("Failed to read glob pattern")
-                        .filter_map(Result::ok);
-
-                    for mut input_path in input_paths {
-                        let contents = read_to_u8(&input_path);
-                        let bin_file = reader::read_bin(&contents);
-                        let jsonstr = json_writer::convert_bin_to_json(&bin_file, &mut hash_map);
-                        input_path.set_extension("json");
-                        write_u8(&input_path, jsonstr.as_bytes());
-                        println!();
-                    }
-                }
-            }
-        }
-        Some(("encode", args)) => {
-            let input = args.get_one::<String>("INPUT").unwrap();
-            let output = args.get_one::<String>("OUTPUT");
-
-            match output {
-                Some(output) => {
-                    let contents = read_string(Path::new(input));
-                    let bin_file = json_reader::convert_json_to_bin(&contents);
-                    let bin = writer::write_bin(&bin_file);
-                    write_u8(Path::new(output), &bin);
-                }
-                None => {
-                    let input_paths = glob::glob(input)
-                        .expect("Failed to read glob pattern")
-                        .filter_map(Result::ok);
-
-                    for mut input_path in input_paths {
-                        let contents = read_string(&input_path);
-                        let bin_file = json_reader::convert_json_to_bin(&contents);
-                        let bin = writer::write_bin(&bin_file);
-                        input_path.set_extension("bin");
-                        write_u8(&input_path, &bin);
-                        println!();
-                    }
-                }
-            }
-        }
-        _ => {}
-    }
-}
-
-fn load_hashes_from_file(path: &Path, hash_map: &mut HashMap<u64, String>) -> u32 {
-    let path_str = path.to_str().unwrap();
-
-    let file = match File::open(path) {
-        Ok(file) => file,
-        Err(error) => {
-            println!("Could not open hash file: {} error: {}", path_str, error);
-            return 0;
-        }
-    };
-
-    let mut lines = 0;
-    let mut reader = BufReader::new(file);
-    let mut line = String::with_capacity(1024);
-
-    let msg = |error| {
-        println!(
-            "Could not read line hash file: {} error: {}",
-            path_str, error
-        );
-        0
-    };
-
-    while reader.read_line(&mut line).unwrap_or_else(msg) != 0 {
-        let mut line_split = line.split(' ');
-
-        if line_split.clone().count() == 2 {
-            let key_str = line_split.next().unwrap();
-
-            let key = match key_str.len() {
-                8 => u32::from_str_radix(key_str, 16).unwrap_or_else(|_| {
-                    println!("Invalid hex: {}", key_str);
-                    0
-                }) as u64,
-                16 => u64::from_str_radix(key_str, 16).unwrap_or_else(|_| {
-                    println!("Invalid hex: {}", key_str);
-                    0
-                }),
-                _ => {
-                    line.clear();
-                    continue;
-                }
-            };
-
-            if key == 0 {
-                line.clear();
-                continue;
-            }
-
-            lines += hash_map
-                .insert(
-                    key,
-                    line_split
-                        .next()
-                        .unwrap()
-                        .trim_end_matches(&['\n', '\r'])
-                        .to_string(),
+        .subcommand(
+            clap::Command::new("verify")
+                .about("Checks that the given bin survives decoding and encoding unchanged")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the input file to use")
+                        .required(true)
+                        .index(1),
                 )
-                .is_none() as u32;
-        }
-
-        line.clear();
-    }
-
-    println!("File: {} loaded: {} lines", path_str, lines);
-
-    lines
-}
-
-fn add_to_hash_map(hashes_to_insert: &[&str], hash_map: &mut HashMap<u64, String>) {
-    for hash_name in hashes_to_insert {
-        hash_map.insert(hashes::fnv1a(hash_name) as u64, hash_name.to_string());
-        hash_map.insert(hashes::xxhash(hash_name), hash_name.to_string());
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .help("Sets the format to round trip through, json or ritobin text")
+                        .value_parser(["json", "text"])
+                        .default_value("json"),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Round trips through JSON written with the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("harvest")
+                .about("Learns names of unresolved hashes from the strings inside the given bins")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the input files to use")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Prints the learned names without adding them to the dictionaries")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("names")
+                        .long("names")
+                        .help("Also learns class and field names, which are prone to collisions")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("guess")
+                .about("Brute-forces field and class names for unknown FNV-1a hashes")
+                .arg(
+                    clap::Arg::new("HASH")
+                        .help("Sets the hashes to find names for")
+                        .num_args(0..),
+                )
+                .arg(
+                    clap::Arg::new("targets")
+                        .long("targets")
+                        .value_name("FILE")
+                        .help("Reads hashes to find from a list or an --unresolved export"),
+                )
+                .arg(
+                    clap::Arg::new("wordlist")
+                        .long("wordlist")
+                        .value_name("FILE")
+                        .help("Adds the words of the given file as tokens")
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(
+                    clap::Arg::new("prefixes")
+                        .long("prefixes")
+                        .help("Sets the comma separated prefixes to try besides none")
+                        .default_value("m,b,f"),
+                )
+                .arg(
+                    clap::Arg::new("no-dictionary")
+                        .long("no-dictionary")
+                        .help("Does not split the known field and class names into tokens")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("max-words")
+                        .long("max-words")
+                        .help("Sets the most tokens joined into one name")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("3"),
+                )
+                .arg(
+                    clap::Arg::new("budget")
+                        .long("budget")
+                        .help("Sets how many candidates to hash at most")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("1000000000"),
+                )
+                .arg(
+                    clap::Arg::new("threads")
+                        .long("threads")
+                        .help("Sets the number of threads, all cores by default")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("infer-schema")
+                .about("Writes the fields and field types of every class seen in the given bins")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the input files to use")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("OUTPUT")
+                        .help("Sets the schema file to write")
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("validate")
+                .about("Checks bins or JSON files against a class schema, failing on any issue")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the input files to use, .json files are read as JSON")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Sets the schema written by infer-schema to check against")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("query")
+                .about("Prints the values a query selects in bins or JSON files")
+                .arg(
+                    clap::Arg::new("QUERY")
+                        .help("Sets the query, like Entries[*:SkinCharacterDataProperties]..bankUnits[*].name")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the input files to use, .json files are read as JSON")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads the field types of plain JSON objects from the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("set")
+                .about("Replaces the values a query selects, keeping their types")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the files to edit in place, .json files are read as JSON")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("QUERY")
+                        .help("Sets the query selecting the values, like Entries[*].skinScale")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("VALUE")
+                        .help("Sets the new value as ritobin text, like 1.2 or \"name\"")
+                        .required(true)
+                        .index(3),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads and writes plain JSON objects with the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("delete")
+                .about("Removes the fields, items, map pairs or entries a query selects")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the files to edit in place, .json files are read as JSON")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("QUERY")
+                        .help("Sets the query selecting the values, like Entries[*].skinScale")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads and writes plain JSON objects with the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("insert")
+                .about("Adds a field, item or map pair to the values a query selects")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the files to edit in place, .json files are read as JSON")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("QUERY")
+                        .help("Sets the query selecting the values, like Entries[*].skinScale")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("VALUE")
+                        .help("Sets the ritobin text to add, like \"name: f32 = 1.2\" for embeds or \"key = value\" for maps")
+                        .required(true)
+                        .index(3),
+                )
+                .arg(
+                    clap::Arg::new("at")
+                        .long("at")
+                        .value_name("INDEX")
+                        .help("Inserts list items at the given index instead of the end")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads and writes plain JSON objects with the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("script")
+                .about("Runs a Rhai script on bins or JSON files, writing back the ones it changed")
+                .arg(
+                    clap::Arg::new("SCRIPT")
+                        .help("Sets the .rhai script to run, which sees the file as bin")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the files to run the script on, .json files are read as JSON")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Runs the script without writing the changed files")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads and writes plain JSON objects with the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("links")
+                .about("Resolves the links of a directory of bins, failing on links to missing entries")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the directory of bins, or a glob of the files to use")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("all")
+                        .long("all")
+                        .help("Also prints the links that resolved and where to")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads the field types of plain JSON objects from the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("graph")
+                .about("Writes the files and entries of bins with their includes and links as a graph")
+                .arg(
+                    clap::Arg::new("INPUT")
+                        .help("Sets the directory of bins, or a glob of the files to use")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .help("Sets the graph format, Graphviz DOT or JSON")
+                        .value_parser(["dot", "json"])
+                        .default_value("dot"),
+                )
+                .arg(
+                    clap::Arg::new("files-only")
+                        .long("files-only")
+                        .help("Leaves out entries, linking the files that have them instead")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Writes the graph to the given file instead of stdout"),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads the field types of plain JSON objects from the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("wad-list")
+                .about("Lists the entries of a WAD archive with their game paths and compression")
+                .arg(
+                    clap::Arg::new("WAD")
+                        .help("Sets the .wad.client archive to read")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("filter")
+                        .long("filter")
+                        .value_name("PATTERN")
+                        .help("Only uses entries whose game path matches the glob pattern, like data/characters/ahri/**"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("wad-extract")
+                .about("Writes the decompressed entries of a WAD archive to a directory")
+                .arg(
+                    clap::Arg::new("WAD")
+                        .help("Sets the .wad.client archive to read")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("OUTPUT")
+                        .help("Sets the directory to write the entries to")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("filter")
+                        .long("filter")
+                        .value_name("PATTERN")
+                        .help("Only uses entries whose game path matches the glob pattern, like data/characters/ahri/**"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("wad-decode")
+                .about("Decodes the bins inside a WAD archive to JSON or ritobin text")
+                .arg(
+                    clap::Arg::new("WAD")
+                        .help("Sets the .wad.client archive to read")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("OUTPUT")
+                        .help("Sets the directory to write the decoded bins to")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("filter")
+                        .long("filter")
+                        .value_name("PATTERN")
+                        .help("Only uses entries whose game path matches the glob pattern, like data/characters/ahri/**"),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .help("Sets the format to decode to, json or ritobin text")
+                        .value_parser(["json", "text"])
+                        .default_value("json"),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Writes classes matching the given schema as plain JSON objects"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("diff")
+                .about("Lists the values added, removed and changed between two bins or JSON files")
+                .arg(
+                    clap::Arg::new("OLD")
+                        .help("Sets the original file")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("NEW")
+                        .help("Sets the changed file")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("json")
+                        .long("json")
+                        .help("Writes the changes as JSON")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Writes the changes to the given file instead of printing them"),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads the field types of plain JSON objects from the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("merge")
+                .about("Merges the changes two files made to a common base")
+                .arg(
+                    clap::Arg::new("BASE")
+                        .help("Sets the file both sides started from")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("OURS")
+                        .help("Sets our changed file")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("THEIRS")
+                        .help("Sets their changed file")
+                        .required(true)
+                        .index(3),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Sets the merged file to write, as JSON or ritobin text by its extension")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads and writes plain JSON objects with the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("apply-patch")
+                .about("Applies the patches of a PTCH file to a base bin")
+                .arg(
+                    clap::Arg::new("BASE")
+                        .help("Sets the bin to patch")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("PATCH")
+                        .help("Sets the PTCH file with the patches")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Sets the patched file to write, as JSON or ritobin text by its extension")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads and writes plain JSON objects with the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("make-patch")
+                .about("Writes a PTCH file with the changes between two bins")
+                .arg(
+                    clap::Arg::new("ORIGINAL")
+                        .help("Sets the original file")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    clap::Arg::new("MODIFIED")
+                        .help("Sets the modified file")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Sets the PTCH file to write, as JSON or ritobin text by its extension")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("schema")
+                        .long("schema")
+                        .value_name("FILE")
+                        .help("Reads and writes plain JSON objects with the given schema"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("hash")
+                .about("Hashes names or looks hashes up in the dictionaries")
+                .subcommand_required(true)
+                .subcommand(
+                    clap::Command::new("fnv1a")
+                        .about("Prints the FNV-1a hash of bin class, field and entry names")
+                        .arg(
+                            clap::Arg::new("VALUE")
+                                .help("Sets the names to hash, read from stdin when none or - is given")
+                                .num_args(0..),
+                        )
+                        .arg(
+                            clap::Arg::new("new")
+                                .long("new")
+                                .help("Only prints names whose hash has no name in the dictionaries")
+                                .action(clap::ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(
+                    clap::Command::new("xxh64")
+                        .about("Prints the XXHash of game file paths, lowercased")
+                        .arg(
+                            clap::Arg::new("VALUE")
+                                .help("Sets the paths to hash, read from stdin when none or - is given")
+                                .num_args(0..),
+                        )
+                        .arg(
+                            clap::Arg::new("new")
+                                .long("new")
+                                .help("Only prints paths whose hash has no name in the dictionaries")
+                                .action(clap::ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(
+                    clap::Command::new("lookup")
+                        .about("Prints the dictionary names of hex hashes, 8 digits for bin hashes and 16 for game paths")
+                        .arg(
+                            clap::Arg::new("VALUE")
+                                .help("Sets the hashes to look up, read from stdin when none or - is given")
+                                .num_args(0..),
+                        ),
+                ),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        Some(("decode", args)) => {
+            let input = args.get_one::<String>("INPUT").unwrap();
+            let output = args.get_one::<String>("OUTPUT");
+            let format = Format::from_arg(args);
+
+            let unresolved_path = args.get_one::<String>("unresolved");
+            let schema = load_schema(args);
+
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+            let mut unresolved = UnresolvedHashes::new();
+
+            let failed = run_batch(
+                input,
+                output,
+                format.extension(),
+                |input_path, output_path| {
+                    let contents = read_to_u8(input_path)?;
+                    let collect = unresolved_path.map(|_| &mut unresolved);
+                    let string =
+                        decode_bin(&contents, format, &hash_database, schema.as_ref(), collect)?;
+                    write_u8(output_path, string.as_bytes())?;
+                    Ok(())
+                },
+                |error| error.to_string_with(&hash_database),
+            );
+
+            if let Some(unresolved_path) = unresolved_path {
+                let list = unresolved.to_string_with(&hash_database);
+                match write_u8(Path::new(unresolved_path), list.as_bytes()) {
+                    Ok(()) => eprintln!("Unresolved hashes: {}", unresolved.hashes.len()),
+                    Err(error) => {
+                        eprintln!("Error: {}: {}", unresolved_path, error);
+                        process::exit(1);
+                    }
+                }
+            }
+            exit_on_failure(failed);
+        }
+        Some(("encode", args)) => {
+            let input = args.get_one::<String>("INPUT").unwrap();
+            let output = args.get_one::<String>("OUTPUT");
+            let format = Format::from_arg(args);
+
+            let schema = load_schema(args);
+
+            // Hashes are only needed to name the path of an error.
+            let hashes_dir = args.get_one::<String>("hashes");
+            let hash_database = OnceCell::new();
+
+            let failed = run_batch(
+                input,
+                output,
+                "bin",
+                |input_path, output_path| {
+                    let contents = read_string(input_path)?;
+                    let bin = encode(&contents, format, schema.as_ref())?;
+                    write_u8(output_path, &bin)?;
+                    Ok(())
+                },
+                |error| {
+                    error.to_string_with(
+                        hash_database.get_or_init(|| load_hash_database(hashes_dir)),
+                    )
+                },
+            );
+            exit_on_failure(failed);
+        }
+        Some(("verify", args)) => {
+            let input = args.get_one::<String>("INPUT").unwrap();
+            let format = Format::from_arg(args);
+            let schema = load_schema(args);
+
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+
+            let failed = run_batch(
+                input,
+                None,
+                "bin",
+                |input_path, _| {
+                    let contents = read_to_u8(input_path)?;
+                    verify_bin(&contents, format, &hash_database, schema.as_ref())?;
+                    eprintln!("Round trip is identical: {}", input_path.display());
+                    Ok(())
+                },
+                |error| error.to_string_with(&hash_database),
+            );
+            exit_on_failure(failed);
+        }
+        Some(("harvest", args)) => {
+            let input = args.get_one::<String>("INPUT").unwrap();
+            let hashes_arg = args.get_one::<String>("hashes");
+
+            let hash_database = load_hash_database(hashes_arg);
+            let mut unresolved = UnresolvedHashes::new();
+            let mut strings = HashSet::new();
+
+            let failed = run_batch(
+                input,
+                None,
+                "bin",
+                |input_path, _| {
+                    let contents = read_to_u8(input_path)?;
+                    let bin_file = reader::read_bin(&contents)?;
+                    unresolved.collect(&bin_file, &hash_database);
+                    harvest::collect_strings(&bin_file, &mut strings);
+                    Ok(())
+                },
+                |error| error.to_string_with(&hash_database),
+            );
+
+            println!(
+                "Hashing {} strings against {} unresolved hashes",
+                strings.len(),
+                unresolved.hashes.len()
+            );
+            let learned = harvest::harvest(&strings, &unresolved, args.get_flag("names"));
+            for learned in &learned {
+                println!(
+                    "{}: {:x} {}",
+                    learned.role.name(),
+                    learned.hash,
+                    learned.name
+                );
+            }
+            println!("Learned {} names", learned.len());
+
+            if !learned.is_empty() && !args.get_flag("dry-run") {
+                let candidates = hash_dir_candidates(hashes_arg);
+                let hash_dir = find_hash_dir(&candidates).unwrap_or(&candidates[0]);
+                match harvest::append_to_dictionaries(hash_dir, &learned) {
+                    Ok(lines) => println!("Added {} lines to {}", lines, hash_dir.display()),
+                    Err(error) => {
+                        println!("Error: {}: {}", hash_dir.display(), error);
+                        process::exit(1);
+                    }
+                }
+            }
+            exit_on_failure(failed);
+        }
+        Some(("guess", args)) => {
+            let mut targets = HashSet::new();
+            for hash in args.get_many::<String>("HASH").into_iter().flatten() {
+                targets.extend(guess::parse_targets(hash));
+            }
+            if let Some(targets_path) = args.get_one::<String>("targets") {
+                match read_string(Path::new(targets_path)) {
+                    Ok(contents) => targets.extend(guess::parse_targets(&contents)),
+                    Err(error) => {
+                        println!("Error: {}: {}", targets_path, error);
+                        process::exit(1);
+                    }
+                }
+            }
+            if targets.is_empty() {
+                println!("Error: no hashes to guess, give them as arguments or with --targets");
+                process::exit(1);
+            }
+
+            let mut tokens = Vec::new();
+            for wordlist in args.get_many::<String>("wordlist").into_iter().flatten() {
+                match read_string(Path::new(wordlist)) {
+                    Ok(contents) => tokens.extend(guess::wordlist_tokens(&contents)),
+                    Err(error) => {
+                        println!("Error: {}: {}", wordlist, error);
+                        process::exit(1);
+                    }
+                }
+            }
+            if !args.get_flag("no-dictionary") {
+                let candidates = hash_dir_candidates(args.get_one::<String>("hashes"));
+                if let Some(hash_dir) = find_hash_dir(&candidates) {
+                    let mut names = HashMap::new();
+                    for file in ["hashes.binfields.txt", "hashes.bintypes.txt"].iter() {
+                        let path = hash_dir.join(file);
+                        if path.is_file() {
+                            hashes::load_hashes_u32_from_file(&path, &mut names);
+                        }
+                    }
+                    tokens.extend(guess::camel_case_tokens(names.values().map(String::as_str)));
+                }
+            }
+            let mut seen = HashSet::new();
+            tokens.retain(|token| seen.insert(token.to_lowercase()));
+
+            let mut prefixes = vec![String::new()];
+            for prefix in args.get_one::<String>("prefixes").unwrap().split(',') {
+                if !prefix.is_empty() && !prefixes.iter().any(|known| known == prefix) {
+                    prefixes.push(prefix.to_string());
+                }
+            }
+
+            let options = guess::GuessOptions {
+                prefixes,
+                tokens,
+                max_words: *args.get_one::<usize>("max-words").unwrap(),
+                budget: *args.get_one::<u64>("budget").unwrap(),
+                threads: args
+                    .get_one::<usize>("threads")
+                    .cloned()
+                    .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+                    .unwrap_or(1),
+            };
+            println!(
+                "Guessing {} hashes from {} tokens and {} prefixes on {} threads",
+                targets.len(),
+                options.tokens.len(),
+                options.prefixes.len(),
+                options.threads
+            );
+
+            let result = guess::guess(&targets, &options);
+            for (hash, name) in &result.matches {
+                println!("{:08x} {}", hash, name);
+            }
+            println!(
+                "Found {} names for {} hashes after {} candidates{}",
+                result.matches.len(),
+                result
+                    .matches
+                    .iter()
+                    .map(|(hash, _)| hash)
+                    .collect::<HashSet<_>>()
+                    .len(),
+                result.tried,
+                if result.stopped { ", budget spent" } else { "" }
+            );
+        }
+        Some(("infer-schema", args)) => {
+            let input = args.get_one::<String>("INPUT").unwrap();
+            let output = args.get_one::<String>("OUTPUT").unwrap();
+
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+            let mut schema = Schema::new();
+
+            let failed = run_batch(
+                input,
+                None,
+                "bin",
+                |input_path, _| {
+                    let contents = read_to_u8(input_path)?;
+                    schema.record(&reader::read_bin(&contents)?);
+                    Ok(())
+                },
+                |error| error.to_string_with(&hash_database),
+            );
+
+            let conflicts = schema.conflicts();
+            for (class, field, field_schema) in &conflicts {
+                let types: Vec<String> = field_schema
+                    .types
+                    .iter()
+                    .map(|(field_type, count)| format!("{} ({})", field_type, count))
+                    .collect();
+                println!(
+                    "Conflict: {}.{} seen as {}",
+                    hash_database.type_to_string(*class),
+                    hash_database.field_to_string(*field),
+                    types.join(", ")
+                );
+            }
+            println!(
+                "Found {} classes, {} conflicting fields",
+                schema.classes.len(),
+                conflicts.len()
+            );
+
+            if let Err(error) =
+                write_u8(Path::new(output), schema.to_json(&hash_database).as_bytes())
+            {
+                println!("Error: {}: {}", output, error);
+                process::exit(1);
+            }
+            exit_on_failure(failed);
+        }
+        Some(("validate", args)) => {
+            let input = args.get_one::<String>("INPUT").unwrap();
+            let schema = load_schema(args).unwrap();
+
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+            let mut issues = 0;
+
+            let failed = run_batch(
+                input,
+                None,
+                "bin",
+                |input_path, _| {
+                    let bin_file = read_bin_file(input_path, Some(&schema))?;
+                    let file_issues = validate::validate(&bin_file, &schema);
+                    for issue in &file_issues {
+                        println!(
+                            "{}: {}",
+                            input_path.display(),
+                            issue.to_string_with(&hash_database)
+                        );
+                    }
+                    println!("{} issue(s) in {}", file_issues.len(), input_path.display());
+                    issues += file_issues.len();
+                    Ok(())
+                },
+                |error| error.to_string_with(&hash_database),
+            );
+
+            if issues > 0 {
+                println!("Found {} issue(s)", issues);
+                process::exit(1);
+            }
+            exit_on_failure(failed);
+        }
+        Some(("query", args)) => {
+            let input = args.get_one::<String>("INPUT").unwrap();
+            let schema = load_schema(args);
+            let query = match Query::parse(args.get_one::<String>("QUERY").unwrap()) {
+                Ok(query) => query,
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    process::exit(1);
+                }
+            };
+
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+            let mut matches = 0;
+            let mut matched_files = 0;
+
+            let failed = run_batch(
+                input,
+                None,
+                "bin",
+                |input_path, _| {
+                    let bin_file = read_bin_file(input_path, schema.as_ref())?;
+                    let file_matches = query.run(&bin_file);
+                    for file_match in &file_matches {
+                        println!(
+                            "{}: {}",
+                            input_path.display(),
+                            file_match.to_string_with(&hash_database)
+                        );
+                    }
+                    if !file_matches.is_empty() {
+                        matched_files += 1;
+                    }
+                    matches += file_matches.len();
+                    Ok(())
+                },
+                |error| error.to_string_with(&hash_database),
+            );
+
+            eprintln!("{} match(es) in {} file(s)", matches, matched_files);
+            exit_on_failure(failed);
+        }
+        Some(("set", args)) => {
+            let value = args.get_one::<String>("VALUE").unwrap();
+            edit_files(args, "Set", |bin_file, query| {
+                edit::set_values(bin_file, query, value)
+            });
+        }
+        Some(("delete", args)) => {
+            edit_files(args, "Deleted", edit::delete_values);
+        }
+        Some(("insert", args)) => {
+            let value = args.get_one::<String>("VALUE").unwrap();
+            let at = args.get_one::<usize>("at").cloned();
+            edit_files(args, "Inserted", |bin_file, query| {
+                edit::insert_values(bin_file, query, value, at)
+            });
+        }
+        Some(("script", args)) => {
+            let input = args.get_one::<String>("INPUT").unwrap();
+            let schema = load_schema(args);
+            let dry_run = args.get_flag("dry-run");
+
+            let hash_database = Rc::new(load_hash_database(args.get_one::<String>("hashes")));
+            let script_path = args.get_one::<String>("SCRIPT").unwrap();
+            let script = match read_string(Path::new(script_path))
+                .map_err(BinError::from)
+                .and_then(|source| Script::compile(&source, hash_database.clone()))
+            {
+                Ok(script) => script,
+                Err(error) => {
+                    eprintln!("Error: {}: {}", script_path, error);
+                    process::exit(1);
+                }
+            };
+            let mut changed = 0;
+
+            let failed = run_batch(
+                input,
+                None,
+                "bin",
+                |input_path, _| {
+                    let mut bin_file = read_bin_file(input_path, schema.as_ref())?;
+                    if script.run(&mut bin_file, &input_path.display().to_string())? {
+                        if !dry_run {
+                            write_bin_file(input_path, &bin_file, &hash_database, schema.as_ref())?;
+                        }
+                        println!("Changed {}", input_path.display());
+                        changed += 1;
+                    }
+                    Ok(())
+                },
+                |error| error.to_string_with(&hash_database),
+            );
+
+            println!("Changed {} file(s)", changed);
+            exit_on_failure(failed);
+        }
+        Some(("links", args)) => {
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+            let (bins, failed) = read_bin_links(args, &hash_database);
+
+            let resolution = links::resolve_links(&bins);
+            for missing in &resolution.missing_files {
+                println!(
+                    "Missing linked file: {} links {}, which is not in the files",
+                    bins[missing.file].name, missing.linked
+                );
+            }
+            for link in &resolution.links {
+                let from = format!(
+                    "{}: {} -> {}",
+                    bins[link.file].name,
+                    link.path.to_string_with(&hash_database),
+                    hash_database.hash_to_string(link.target)
+                );
+                match &link.status {
+                    links::LinkStatus::Resolved(file) if args.get_flag("all") => {
+                        println!("{} in {}", from, bins[*file].name)
+                    }
+                    links::LinkStatus::Resolved(_) => {}
+                    links::LinkStatus::NotLinked(files) => {
+                        let names: Vec<&str> =
+                            files.iter().map(|file| bins[*file].name.as_str()).collect();
+                        println!("Not linked: {} is only in {}", from, names.join(", "));
+                    }
+                    links::LinkStatus::Dangling => println!("Dangling link: {}", from),
+                }
+            }
+            println!(
+                "{} link(s) in {} file(s), {} dangling, {} not linked, {} missing linked file(s)",
+                resolution.links.len(),
+                bins.len(),
+                resolution.dangling(),
+                resolution.not_linked(),
+                resolution.missing_files.len()
+            );
+
+            if resolution.dangling() > 0 {
+                process::exit(1);
+            }
+            exit_on_failure(failed);
+        }
+        Some(("graph", args)) => {
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+            let (bins, failed) = read_bin_links(args, &hash_database);
+
+            let graph = Graph::build(&bins, &hash_database, args.get_flag("files-only"));
+            let output = match args.get_one::<String>("format").unwrap().as_str() {
+                "json" => {
+                    let mut gen = json_writer::MyPrettyGenerator::new();
+                    gen.write_json(&graph.to_json()).expect("Can't write json");
+                    gen.consume()
+                }
+                _ => graph.to_dot(),
+            };
+
+            match args.get_one::<String>("output") {
+                Some(output_path) => {
+                    if let Err(error) = write_u8(Path::new(output_path), output.as_bytes()) {
+                        eprintln!("Error: {}: {}", output_path, error);
+                        process::exit(1);
+                    }
+                    eprintln!(
+                        "Wrote {} node(s) and {} edge(s) to {}",
+                        graph.nodes.len(),
+                        graph.edges.len(),
+                        output_path
+                    );
+                }
+                None if output.ends_with('\n') => print!("{}", output),
+                None => println!("{}", output),
+            }
+            exit_on_failure(failed);
+        }
+        Some(("wad-list", args)) => {
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+            let contents = read_wad_file(args);
+            let wad = open_wad(args, &contents);
+
+            let entries =
+                filter_wad_entries(&wad, args.get_one::<String>("filter"), &hash_database);
+            for (entry, name) in &entries {
+                println!(
+                    "{} {} {} -> {} bytes{}",
+                    name,
+                    entry.compression.name(),
+                    entry.compressed_size,
+                    entry.size,
+                    if entry.duplicate { " (duplicate)" } else { "" }
+                );
+            }
+            eprintln!(
+                "{} of {} entries in WAD {}.{}",
+                entries.len(),
+                wad.entries.len(),
+                wad.major,
+                wad.minor
+            );
+        }
+        Some(("wad-extract", args)) => {
+            let output = Path::new(args.get_one::<String>("OUTPUT").unwrap());
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+            let contents = read_wad_file(args);
+            let wad = open_wad(args, &contents);
+            let mut extracted = 0;
+            let mut failed = 0;
+
+            let entries =
+                filter_wad_entries(&wad, args.get_one::<String>("filter"), &hash_database);
+            for (entry, name) in &entries {
+                if entry.compression == WadCompression::Satellite {
+                    eprintln!("Skipping {}: its data is in another WAD", name);
+                    continue;
+                }
+                let result = wad.extract(entry).and_then(|data| {
+                    let output_path = wad_output_path(output, entry, &hash_database, &data, None);
+                    write_u8_creating_dirs(&output_path, &data)?;
+                    extracted += 1;
+                    Ok(())
+                });
+                if let Err(error) = result {
+                    eprintln!("Error: {}: {}", name, error);
+                    failed += 1;
+                }
+            }
+            eprintln!("Extracted {} entries", extracted);
+            exit_on_failure(failed);
+        }
+        Some(("wad-decode", args)) => {
+            let output = Path::new(args.get_one::<String>("OUTPUT").unwrap());
+            let format = Format::from_arg(args);
+            let schema = load_schema(args);
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+            let contents = read_wad_file(args);
+            let wad = open_wad(args, &contents);
+            let mut decoded = 0;
+            let mut failed = 0;
+
+            let entries =
+                filter_wad_entries(&wad, args.get_one::<String>("filter"), &hash_database);
+            for (entry, name) in &entries {
+                if entry.compression == WadCompression::Satellite {
+                    eprintln!("Skipping {}: its data is in another WAD", name);
+                    continue;
+                }
+                let result = wad.extract(entry).and_then(|data| {
+                    if !data.starts_with(b"PROP") && !data.starts_with(b"PTCH") {
+                        return Ok(());
+                    }
+                    eprintln!("Decoding {}", name);
+                    let string = decode_bin(&data, format, &hash_database, schema.as_ref(), None)?;
+                    let output_path = wad_output_path(
+                        output,
+                        entry,
+                        &hash_database,
+                        &data,
+                        Some(format.extension()),
+                    );
+                    write_u8_creating_dirs(&output_path, string.as_bytes())?;
+                    decoded += 1;
+                    Ok(())
+                });
+                if let Err(error) = result {
+                    eprintln!("Error: {}: {}", name, error.to_string_with(&hash_database));
+                    failed += 1;
+                }
+            }
+            eprintln!("Decoded {} bins", decoded);
+            exit_on_failure(failed);
+        }
+        Some(("diff", args)) => {
+            let schema = load_schema(args);
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+
+            let mut bin_files = Vec::new();
+            for name in ["OLD", "NEW"].iter() {
+                let path = args.get_one::<String>(name).unwrap();
+                match read_bin_file(Path::new(path), schema.as_ref()) {
+                    Ok(bin_file) => bin_files.push(bin_file),
+                    Err(error) => {
+                        eprintln!("Error: {}: {}", path, error.to_string_with(&hash_database));
+                        process::exit(1);
+                    }
+                }
+            }
+
+            let changes = diff::diff_bins(&bin_files[0], &bin_files[1]);
+            let output = if args.get_flag("json") {
+                let mut array = JsonValue::new_array();
+                for change in &changes {
+                    array.push(change.to_json(&hash_database)).unwrap();
+                }
+                let mut root = JsonValue::new_object();
+                root.insert("Changes", array).unwrap();
+                let mut gen = json_writer::MyPrettyGenerator::new();
+                gen.write_json(&root).expect("Can't write json");
+                gen.consume()
+            } else {
+                let mut lines = String::new();
+                for change in &changes {
+                    lines.push_str(&change.to_string_with(&hash_database));
+                    lines.push('\n');
+                }
+                lines
+            };
+
+            match args.get_one::<String>("output") {
+                Some(output_path) => {
+                    if let Err(error) = write_u8(Path::new(output_path), output.as_bytes()) {
+                        eprintln!("Error: {}: {}", output_path, error);
+                        process::exit(1);
+                    }
+                }
+                None if output.ends_with('\n') => print!("{}", output),
+                None => println!("{}", output),
+            }
+            let count = |kind| {
+                changes
+                    .iter()
+                    .filter(|change| change.kind() == kind)
+                    .count()
+            };
+            eprintln!(
+                "{} added, {} removed, {} changed",
+                count("added"),
+                count("removed"),
+                count("changed")
+            );
+        }
+        Some(("merge", args)) => {
+            let schema = load_schema(args);
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+
+            let mut bin_files = Vec::new();
+            for name in ["BASE", "OURS", "THEIRS"].iter() {
+                let path = args.get_one::<String>(name).unwrap();
+                match read_bin_file(Path::new(path), schema.as_ref()) {
+                    Ok(bin_file) => bin_files.push(bin_file),
+                    Err(error) => {
+                        eprintln!("Error: {}: {}", path, error.to_string_with(&hash_database));
+                        process::exit(1);
+                    }
+                }
+            }
+
+            let result = merge::merge_bins(&bin_files[0], &bin_files[1], &bin_files[2]);
+            let output = args.get_one::<String>("output").unwrap();
+            if let Err(error) = write_bin_file(
+                Path::new(output),
+                &result.bin_file,
+                &hash_database,
+                schema.as_ref(),
+            ) {
+                eprintln!(
+                    "Error: {}: {}",
+                    output,
+                    error.to_string_with(&hash_database)
+                );
+                process::exit(1);
+            }
+
+            for conflict in &result.conflicts {
+                eprintln!("Conflict at {}", conflict.to_string_with(&hash_database));
+            }
+            if !result.conflicts.is_empty() {
+                eprintln!(
+                    "{} conflict(s), replace each {} in {} with the value to keep",
+                    result.conflicts.len(),
+                    merge::CONFLICT_CLASS,
+                    output
+                );
+                process::exit(1);
+            }
+            println!("Merged without conflicts");
+        }
+        Some(("apply-patch", args)) => {
+            let schema = load_schema(args);
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+
+            let mut bin_files = Vec::new();
+            for name in ["BASE", "PATCH"].iter() {
+                let path = args.get_one::<String>(name).unwrap();
+                match read_bin_file(Path::new(path), schema.as_ref()) {
+                    Ok(bin_file) => bin_files.push(bin_file),
+                    Err(error) => {
+                        eprintln!("Error: {}: {}", path, error.to_string_with(&hash_database));
+                        process::exit(1);
+                    }
+                }
+            }
+
+            let patched = match patch::apply_patches(&bin_files[0], &bin_files[1]) {
+                Ok(patched) => patched,
+                Err(error) => {
+                    eprintln!("Error: {}", error.to_string_with(&hash_database));
+                    process::exit(1);
+                }
+            };
+            let output = args.get_one::<String>("output").unwrap();
+            if let Err(error) =
+                write_bin_file(Path::new(output), &patched, &hash_database, schema.as_ref())
+            {
+                eprintln!(
+                    "Error: {}: {}",
+                    output,
+                    error.to_string_with(&hash_database)
+                );
+                process::exit(1);
+            }
+            let count = bin_files[1].patches.as_ref().map_or(0, |p| p.items.len());
+            println!("Applied {} patch(es)", count);
+        }
+        Some(("make-patch", args)) => {
+            let schema = load_schema(args);
+            let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+
+            let mut bin_files = Vec::new();
+            for name in ["ORIGINAL", "MODIFIED"].iter() {
+                let path = args.get_one::<String>(name).unwrap();
+                match read_bin_file(Path::new(path), schema.as_ref()) {
+                    Ok(bin_file) => bin_files.push(bin_file),
+                    Err(error) => {
+                        eprintln!("Error: {}: {}", path, error.to_string_with(&hash_database));
+                        process::exit(1);
+                    }
+                }
+            }
+
+            let generated = patch::make_patch(&bin_files[0], &bin_files[1], &hash_database);
+            let output = args.get_one::<String>("output").unwrap();
+            if let Err(error) = write_bin_file(
+                Path::new(output),
+                &generated.bin_file,
+                &hash_database,
+                schema.as_ref(),
+            ) {
+                eprintln!(
+                    "Error: {}: {}",
+                    output,
+                    error.to_string_with(&hash_database)
+                );
+                process::exit(1);
+            }
+
+            for removed in &generated.removed {
+                eprintln!(
+                    "Warning: {} was removed, which a patch cannot express",
+                    removed.to_string_with(&hash_database)
+                );
+            }
+            let patch_count = generated
+                .bin_file
+                .patches
+                .as_ref()
+                .map_or(0, |p| p.items.len());
+            println!(
+                "Wrote {} patch(es) and {} whole entries",
+                patch_count,
+                generated.bin_file.entries.items.len()
+            );
+        }
+        Some(("hash", args)) => {
+            // Only hashes and names go to stdout, so the output can be piped;
+            // dictionary loading and errors are written to stderr.
+            let mut hash_database = HashDatabase::new();
+            let candidates = hash_dir_candidates(args.get_one::<String>("hashes"));
+            if let Some(hash_dir) = find_hash_dir(&candidates) {
+                hash_database.open_dir(hash_dir);
+            }
+
+            let mut failed = 0;
+            match args.subcommand() {
+                Some(("fnv1a", args)) => {
+                    for name in read_values(args) {
+                        let hash = hashes::fnv1a(&name);
+                        let known = hash_database.get_type(hash).is_some()
+                            || hash_database.get_field(hash).is_some()
+                            || hash_database.get_hash(hash).is_some();
+                        if !(known && args.get_flag("new")) {
+                            println!("{:08x} {}", hash, name);
+                        }
+                    }
+                }
+                Some(("xxh64", args)) => {
+                    for path in read_values(args) {
+                        let path = path.to_lowercase();
+                        let hash = hashes::xxhash(&path);
+                        let known = hash_database.get_game(hash).is_some();
+                        if !(known && args.get_flag("new")) {
+                            println!("{:016x} {}", hash, path);
+                        }
+                    }
+                }
+                Some(("lookup", args)) => {
+                    for value in read_values(args) {
+                        let hex = value.trim_start_matches("0x").trim_start_matches("0X");
+                        let names = if hex.len() <= 8 {
+                            u32::from_str_radix(hex, 16).ok().map(|hash| {
+                                let mut names = vec![
+                                    hash_database.get_type(hash),
+                                    hash_database.get_field(hash),
+                                    hash_database.get_hash(hash),
+                                ];
+                                names.dedup();
+                                (format!("{:08x}", hash), names)
+                            })
+                        } else if hex.len() <= 16 {
+                            u64::from_str_radix(hex, 16).ok().map(|hash| {
+                                (format!("{:016x}", hash), vec![hash_database.get_game(hash)])
+                            })
+                        } else {
+                            None
+                        };
+                        match names {
+                            Some((hash, names)) => {
+                                let names: Vec<&str> = names.into_iter().flatten().collect();
+                                if names.is_empty() {
+                                    println!("{}", hash);
+                                }
+                                for name in names {
+                                    println!("{} {}", hash, name);
+                                }
+                            }
+                            None => {
+                                eprintln!("Error: not a hex hash: {}", value);
+                                failed += 1;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if failed > 0 {
+                process::exit(1);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Text format a bin is decoded to or encoded from.
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Text,
+}
+
+impl Format {
+    fn from_arg(args: &clap::ArgMatches) -> Format {
+        match args.get_one::<String>("format").map(String::as_str) {
+            Some("text") => Format::Text,
+            _ => Format::Json,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Text => "text",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Text => "py",
+        }
+    }
+}
+
+const HASHES_ENV: &str = "BINREADER_HASHES";
+
+/// Directories searched for hash dictionaries, in order.
+///
+/// `--hashes` or the `BINREADER_HASHES` environment variable replace the
+/// default search of `files/` in the working directory, next to the
+/// executable and in the per-user data directory.
+fn hash_dir_candidates(hashes_arg: Option<&String>) -> Vec<PathBuf> {
+    if let Some(dir) = hashes_arg {
+        return vec![PathBuf::from(dir)];
+    }
+    if let Some(dir) = env::var_os(HASHES_ENV) {
+        return vec![PathBuf::from(dir)];
+    }
+
+    let mut candidates = vec![PathBuf::from("files")];
+    if let Some(exe_dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        candidates.push(exe_dir.join("files"));
+        candidates.push(exe_dir);
+    }
+    if let Some(data_dir) = dirs::data_dir() {
+        candidates.push(data_dir.join("binreader-rust"));
+    }
+    candidates
+}
+
+fn find_hash_dir(candidates: &[PathBuf]) -> Option<&PathBuf> {
+    candidates
+        .iter()
+        .find(|dir| HASH_FILES.iter().any(|file| dir.join(file).is_file()))
+}
+
+fn load_hash_database(hashes_arg: Option<&String>) -> HashDatabase {
+    let mut hash_database = HashDatabase::new();
+
+    let candidates = hash_dir_candidates(hashes_arg);
+    let hash_dir = match find_hash_dir(&candidates) {
+        Some(hash_dir) => hash_dir,
+        None => {
+            let searched: Vec<String> = candidates
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect();
+            eprintln!(
+                "Warning: no hash dictionaries found in {}, names will be written as hex. \
+                Use --hashes <DIR> or set {}.\n",
+                searched.join(", "),
+                HASHES_ENV
+            );
+            return hash_database;
+        }
+    };
+
+    eprintln!("Using hashes from {}\n", hash_dir.display());
+    hash_database.open_dir(hash_dir);
+
+    hash_database
+}
+
+/// Runs `convert` on the given input/output pair, or on every file matched by
+/// the glob when no output is given, reporting failures without stopping.
+fn run_batch<F, D>(
+    input: &str,
+    output: Option<&String>,
+    extension: &str,
+    mut convert: F,
+    describe: D,
+) -> usize
+where
+    F: FnMut(&Path, &Path) -> Result<(), BinError>,
+    D: Fn(&BinError) -> String,
+{
+    let mut failed = 0;
+
+    match output {
+        Some(output) => {
+            if let Err(error) = convert(Path::new(input), Path::new(output)) {
+                eprintln!("Error: {}: {}", input, describe(&error));
+                failed += 1;
+            }
+        }
+        None => {
+            let input_paths = match glob::glob(input) {
+                Ok(input_paths) => input_paths.filter_map(Result::ok),
+                Err(error) => {
+                    eprintln!("Failed to read glob pattern: {}", error);
+                    return 1;
+                }
+            };
+
+            for input_path in input_paths {
+                let output_path = input_path.with_extension(extension);
+                if let Err(error) = convert(&input_path, &output_path) {
+                    eprintln!("Error: {}: {}", input_path.display(), describe(&error));
+                    failed += 1;
+                }
+                eprintln!();
+            }
+        }
+    }
+
+    failed
+}
+
+fn exit_on_failure(failed: usize) {
+    if failed > 0 {
+        eprintln!("{} file(s) failed", failed);
+        process::exit(1);
+    }
+}
+
+fn decode_bin(
+    contents: &[u8],
+    format: Format,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
+    unresolved: Option<&mut UnresolvedHashes>,
+) -> Result<String, BinError> {
+    eprintln!("Reading bin file");
+    let bin_file = reader::read_bin(contents)?;
+    eprintln!("Finished reading bin file");
+
+    if let Some(unresolved) = unresolved {
+        unresolved.collect(&bin_file, hash_database);
+    }
+
+    eprintln!("Converting bin to {}", format.name());
+    let string = match format {
+        Format::Json => {
+            json_writer::convert_bin_to_json_with_schema(&bin_file, hash_database, schema)
+        }
+        Format::Text => text_writer::convert_bin_to_text(&bin_file, hash_database),
+    };
+    eprintln!("Finished converting bin to {}", format.name());
+
+    Ok(string)
+}
+
+fn encode(contents: &str, format: Format, schema: Option<&Schema>) -> Result<Vec<u8>, BinError> {
+    eprintln!("Converting {} to bin", format.name());
+    let bin_file = match format {
+        Format::Json => json_reader::convert_json_to_bin_with_schema(contents, schema)?,
+        Format::Text => text_reader::convert_text_to_bin(contents)?,
+    };
+    eprintln!("Finished converting {} to bin", format.name());
+
+    eprintln!("Writing bin file");
+    let bin = writer::write_bin(&bin_file)?;
+    eprintln!("Finished writing bin file");
+
+    Ok(bin)
+}
+
+/// Decodes and encodes the bin in memory and compares it with the original.
+fn verify_bin(
+    contents: &[u8],
+    format: Format,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
+) -> Result<(), BinError> {
+    let bin_file = reader::read_bin(contents)?;
+
+    eprintln!("Round tripping bin through {}", format.name());
+    let rewritten_file = match format {
+        Format::Json => json_reader::convert_json_to_bin_with_schema(
+            &json_writer::convert_bin_to_json_with_schema(&bin_file, hash_database, schema),
+            schema,
+        )?,
+        Format::Text => text_reader::convert_text_to_bin(&text_writer::convert_bin_to_text(
+            &bin_file,
+            hash_database,
+        ))?,
+    };
+    let rewritten = writer::write_bin(&rewritten_file)?;
+
+    writer::check_round_trip(&bin_file, contents, &rewritten)
+}
+
+/// Reads a bin, or JSON or ritobin text by the `.json` and `.py` extensions.
+fn read_bin_file(path: &Path, schema: Option<&Schema>) -> Result<BinFile, BinError> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => json_reader::convert_json_to_bin_with_schema(&read_string(path)?, schema),
+        Some("py") => text_reader::convert_text_to_bin(&read_string(path)?),
+        _ => reader::read_bin(&read_to_u8(path)?),
+    }
+}
+
+/// Runs `edit` with the query on every input file and writes the changed
+/// files back in place, exiting with status 1 when nothing matched.
+fn edit_files<F>(args: &clap::ArgMatches, verb: &str, mut edit: F)
+where
+    F: FnMut(&mut BinFile, &Query) -> Result<usize, BinError>,
+{
+    let input = args.get_one::<String>("INPUT").unwrap();
+    let schema = load_schema(args);
+    let query = match Query::parse(args.get_one::<String>("QUERY").unwrap()) {
+        Ok(query) => query,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            process::exit(1);
+        }
+    };
+
+    let hash_database = load_hash_database(args.get_one::<String>("hashes"));
+    let mut edited = 0;
+
+    let failed = run_batch(
+        input,
+        None,
+        "bin",
+        |input_path, _| {
+            let mut bin_file = read_bin_file(input_path, schema.as_ref())?;
+            let count = edit(&mut bin_file, &query)?;
+            if count > 0 {
+                write_bin_file(input_path, &bin_file, &hash_database, schema.as_ref())?;
+            }
+            eprintln!("{} {} value(s) in {}", verb, count, input_path.display());
+            edited += count;
+            Ok(())
+        },
+        |error| error.to_string_with(&hash_database),
+    );
+
+    exit_on_failure(failed);
+    if edited == 0 {
+        eprintln!("No values match the query");
+        process::exit(1);
+    }
+}
+
+/// Writes a bin, or JSON or ritobin text by the `.json` and `.py` extensions.
+fn write_bin_file(
+    path: &Path,
+    bin_file: &BinFile,
+    hash_database: &HashDatabase,
+    schema: Option<&Schema>,
+) -> Result<(), BinError> {
+    let contents = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => {
+            json_writer::convert_bin_to_json_with_schema(bin_file, hash_database, schema)
+                .into_bytes()
+        }
+        Some("py") => text_writer::convert_bin_to_text(bin_file, hash_database).into_bytes(),
+        _ => writer::write_bin(bin_file)?,
+    };
+    write_u8(path, &contents)?;
+    Ok(())
+}
+
+/// Reads the link facts of the bins under the `INPUT` directory, or matching
+/// the `INPUT` glob. Returns them with the number of files that failed.
+fn read_bin_links(
+    args: &clap::ArgMatches,
+    hash_database: &HashDatabase,
+) -> (Vec<links::BinLinks>, usize) {
+    let input = args.get_one::<String>("INPUT").unwrap();
+    let input = if Path::new(input).is_dir() {
+        Path::new(input)
+            .join("**")
+            .join("*.bin")
+            .display()
+            .to_string()
+    } else {
+        input.clone()
+    };
+    let schema = load_schema(args);
+    let mut bins = Vec::new();
+
+    let failed = run_batch(
+        &input,
+        None,
+        "bin",
+        |input_path, _| {
+            let bin_file = read_bin_file(input_path, schema.as_ref())?;
+            bins.push(links::BinLinks::of(
+                &input_path.display().to_string(),
+                &bin_file,
+            ));
+            Ok(())
+        },
+        |error| error.to_string_with(hash_database),
+    );
+    (bins, failed)
+}
+
+fn read_wad_file(args: &clap::ArgMatches) -> Vec<u8> {
+    let path = args.get_one::<String>("WAD").unwrap();
+    match read_to_u8(Path::new(path)) {
+        Ok(contents) => contents,
+        Err(error) => {
+            eprintln!("Error: {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
+
+fn open_wad<'a>(args: &clap::ArgMatches, contents: &'a [u8]) -> Wad<'a> {
+    match Wad::read(contents) {
+        Ok(wad) => wad,
+        Err(error) => {
+            eprintln!(
+                "Error: {}: {}",
+                args.get_one::<String>("WAD").unwrap(),
+                error
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Entries of `wad` with their game path, or hash when it has no name, that
+/// match the `--filter` pattern.
+fn filter_wad_entries<'a>(
+    wad: &'a Wad,
+    filter: Option<&String>,
+    hash_database: &HashDatabase,
+) -> Vec<(&'a WadEntry, String)> {
+    let pattern = filter.map(|filter| match glob::Pattern::new(filter) {
+        Ok(pattern) => pattern,
+        Err(error) => {
+            eprintln!("Failed to read filter pattern: {}", error);
+            process::exit(1);
+        }
+    });
+    let options = glob::MatchOptions {
+        case_sensitive: false,
+        ..Default::default()
+    };
+    wad.entries
+        .iter()
+        .map(|entry| (entry, hash_database.game_to_string(entry.path_hash)))
+        .filter(|(_, name)| {
+            pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.matches_with(name, options))
+        })
+        .collect()
+}
+
+/// Where a WAD entry goes below `output`: its game path, or its hash with an
+/// extension guessed from the data. `extension` replaces the one of the path.
+fn wad_output_path(
+    output: &Path,
+    entry: &WadEntry,
+    hash_database: &HashDatabase,
+    data: &[u8],
+    extension: Option<&str>,
+) -> PathBuf {
+    let mut path = output.to_path_buf();
+    match hash_database.get_game(entry.path_hash) {
+        Some(name) => {
+            // Game paths stay below the output directory.
+            for part in name.split(&['/', '\\'][..]) {
+                if !part.is_empty() && part != "." && part != ".." {
+                    path.push(part);
+                }
+            }
+        }
+        None => {
+            let guessed = if data.starts_with(b"PROP") || data.starts_with(b"PTCH") {
+                "bin"
+            } else if data.starts_with(b"DDS ") {
+                "dds"
+            } else if data.starts_with(b"TEX\0") {
+                "tex"
+            } else {
+                "unknown"
+            };
+            path.push(format!("{:016x}.{}", entry.path_hash, guessed));
+        }
+    }
+    match extension {
+        Some(extension) => path.with_extension(extension),
+        None => path,
+    }
+}
+
+fn write_u8_creating_dirs(path: &Path, v: &[u8]) -> Result<(), BinError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_u8(path, v)?;
+    Ok(())
+}
+
+/// Reads the schema given with `--schema`, exiting when it cannot be read.
+fn load_schema(args: &clap::ArgMatches) -> Option<Schema> {
+    let path = args.get_one::<String>("schema")?;
+    let schema = read_string(Path::new(path))
+        .map_err(BinError::from)
+        .and_then(|contents| Schema::from_json(&contents));
+    match schema {
+        Ok(schema) => Some(schema),
+        Err(error) => {
+            eprintln!("Error: {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
+
+/// Values given as arguments, or the non-empty lines of stdin when there are
+/// none or one of them is `-`.
+fn read_values(args: &clap::ArgMatches) -> Vec<String> {
+    let given: Vec<&String> = args
+        .get_many::<String>("VALUE")
+        .into_iter()
+        .flatten()
+        .collect();
+    let mut values: Vec<String> = given
+        .iter()
+        .filter(|value| value.as_str() != "-")
+        .map(|value| value.to_string())
+        .collect();
+    if given.is_empty() || given.len() != values.len() {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            let line = line.trim();
+            if !line.is_empty() {
+                values.push(line.to_string());
+            }
+        }
     }
+    values
 }
 
-fn read_to_u8(path: &Path) -> Vec<u8> {
-    let mut file = File::open(path).expect("Could not open file");
+fn read_to_u8(path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
     let mut contents: Vec<u8> = Vec::new();
-    println!("Reading file: {}", path.to_str().unwrap());
-    file.read_to_end(&mut contents)
-        .expect("Could not read file");
-    println!("Finished reading file");
-    contents
+    eprintln!("Reading file: {}", path.display());
+    file.read_to_end(&mut contents)?;
+    eprintln!("Finished reading file");
+    Ok(contents)
 }
 
-fn write_u8(path: &Path, v: &[u8]) {
-    let mut file = File::create(path).expect("Could not create file");
-    println!("Writing to file: {}", path.to_str().unwrap());
-    file.write_all(v).expect("Could not write to file");
-    println!("Finished writing to file");
+fn write_u8(path: &Path, v: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    eprintln!("Writing to file: {}", path.display());
+    file.write_all(v)?;
+    eprintln!("Finished writing to file");
+    Ok(())
 }
 
-fn read_string(path: &Path) -> String {
-    let mut file = File::open(path).expect("Could not open file");
+fn read_string(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
     let mut contents = String::new();
-    println!("Reading file: {}", path.to_str().unwrap());
-    file.read_to_string(&mut contents)
-        .expect("Could not read file");
-    println!("Finished reading file");
-    contents
+    eprintln!("Reading file: {}", path.display());
+    file.read_to_string(&mut contents)?;
+    eprintln!("Finished reading file");
+    Ok(contents)
 }
@@ -0,0 +1,321 @@
+use diff::TypedData;
+use hashes::fnv1a;
+use path::{BinPath, PathSegment};
+use structs::*;
+
+/// Class of the value written where ours and theirs changed the same value
+/// differently, with `base`, `ours` and `theirs` fields for the sides that
+/// have it. Replace it with the value to keep to resolve the conflict.
+pub const CONFLICT_CLASS: &str = "MergeConflict";
+
+type Side<'a> = Option<(BinType, &'a BinData)>;
+
+enum Merged {
+    Value(Option<TypedData>),
+    /// Both sides changed the value and it cannot be merged inside.
+    Conflict,
+}
+
+/// A merged bin file and the paths of the conflicts written into it.
+#[derive(Debug)]
+pub struct MergeResult {
+    pub bin_file: BinFile,
+    pub conflicts: Vec<BinPath>,
+}
+
+/// Merges the changes `ours` and `theirs` made to `base`.
+///
+/// Changes to different entries, fields and map pairs are combined. Where
+/// both sides changed the same value differently, the nearest field or
+/// embed holding it becomes a `MergeConflict` with all three versions, since
+/// items of containers and other maps cannot hold a value of another type.
+pub fn merge_bins(base: &BinFile, ours: &BinFile, theirs: &BinFile) -> MergeResult {
+    let mut merger = Merger {
+        conflicts: Vec::new(),
+    };
+    let mut path = BinPath::new();
+
+    let version = if ours.version == base.version {
+        theirs.version
+    } else {
+        if theirs.version != base.version && theirs.version != ours.version {
+            merger
+                .conflicts
+                .push(path.with(PathSegment::Root("Version")));
+        }
+        ours.version
+    };
+
+    let mut linked_list: Vec<String> = ours
+        .linked_list
+        .iter()
+        .filter(|linked| !base.linked_list.contains(linked) || theirs.linked_list.contains(linked))
+        .cloned()
+        .collect();
+    for linked in &theirs.linked_list {
+        if !base.linked_list.contains(linked) && !linked_list.contains(linked) {
+            linked_list.push(linked.clone());
+        }
+    }
+
+    // Entries and patches hold embeds, so their conflicts always have a place.
+    let empty = Map::new(BinType::Hash, BinType::Embedded, Vec::new());
+    path.push(PathSegment::Root("Entries"));
+    let entries = merger
+        .merge_map(
+            &mut path,
+            Some(&base.entries),
+            &ours.entries,
+            &theirs.entries,
+        )
+        .unwrap_or_else(|| empty.clone());
+    path.pop();
+
+    let is_patch = ours.is_patch || theirs.is_patch;
+    let patches = if is_patch {
+        path.push(PathSegment::Root("Patches"));
+        let patches = merger
+            .merge_map(
+                &mut path,
+                base.patches.as_ref(),
+                ours.patches.as_ref().unwrap_or(&empty),
+                theirs.patches.as_ref().unwrap_or(&empty),
+            )
+            .unwrap_or_else(|| empty.clone());
+        path.pop();
+        Some(patches)
+    } else {
+        None
+    };
+
+    let bin_file = BinFile::new(
+        is_patch,
+        ours.unknown.or(theirs.unknown),
+        version,
+        linked_list,
+        entries,
+        patches,
+    );
+    MergeResult {
+        bin_file,
+        conflicts: merger.conflicts,
+    }
+}
+
+struct Merger {
+    conflicts: Vec<BinPath>,
+}
+
+impl Merger {
+    fn merge(&mut self, path: &mut BinPath, base: Side, ours: Side, theirs: Side) -> Merged {
+        let owned = |side: Side| side.map(|(btype, data)| (btype, data.clone()));
+        if ours == theirs || base == theirs {
+            return Merged::Value(owned(ours));
+        }
+        if base == ours {
+            return Merged::Value(owned(theirs));
+        }
+
+        let (base, ours, theirs) = match (base, ours, theirs) {
+            (Some(base), Some(ours), Some(theirs)) if base.0 == ours.0 && ours.0 == theirs.0 => {
+                (base, ours, theirs)
+            }
+            _ => return Merged::Conflict,
+        };
+        // Conflicts placed inside a value that conflicts as a whole are dropped.
+        let recorded = self.conflicts.len();
+        match self.merge_inside(path, base.1, ours.1, theirs.1) {
+            Some(merged) => Merged::Value(Some((ours.0, merged))),
+            None => {
+                self.conflicts.truncate(recorded);
+                Merged::Conflict
+            }
+        }
+    }
+
+    /// Merges the insides of three values of the same type, or gives `None`
+    /// when they are not all the same class, shape or kind of value.
+    fn merge_inside(
+        &mut self,
+        path: &mut BinPath,
+        base: &BinData,
+        ours: &BinData,
+        theirs: &BinData,
+    ) -> Option<BinData> {
+        let merged = match (base, ours, theirs) {
+            (
+                BinData::PointerOrEmbedded(base_pe),
+                BinData::PointerOrEmbedded(ours_pe),
+                BinData::PointerOrEmbedded(theirs_pe),
+            ) if base_pe.name == ours_pe.name && ours_pe.name == theirs_pe.name => {
+                BinData::PointerOrEmbedded(self.merge_fields(path, base_pe, ours_pe, theirs_pe))
+            }
+            (
+                BinData::ContainerOrStruct(base_cs),
+                BinData::ContainerOrStruct(ours_cs),
+                BinData::ContainerOrStruct(theirs_cs),
+            ) if base_cs.btype == ours_cs.btype
+                && ours_cs.btype == theirs_cs.btype
+                && base_cs.items.len() == ours_cs.items.len()
+                && ours_cs.items.len() == theirs_cs.items.len() =>
+            {
+                let item_type = ours_cs.btype;
+                let mut items = Vec::with_capacity(ours_cs.items.len());
+                for index in 0..ours_cs.items.len() {
+                    path.push(PathSegment::Index(index));
+                    let merged = self.merge(
+                        path,
+                        Some((item_type, &base_cs.items[index])),
+                        Some((item_type, &ours_cs.items[index])),
+                        Some((item_type, &theirs_cs.items[index])),
+                    );
+                    path.pop();
+                    match merged {
+                        Merged::Value(Some((_, item))) => items.push(item),
+                        _ => return None,
+                    }
+                }
+                BinData::ContainerOrStruct(ContainerOrStruct::new(item_type, items))
+            }
+            (BinData::Map(base_map), BinData::Map(ours_map), BinData::Map(theirs_map))
+                if base_map.keytype == ours_map.keytype
+                    && ours_map.keytype == theirs_map.keytype
+                    && base_map.valuetype == ours_map.valuetype
+                    && ours_map.valuetype == theirs_map.valuetype =>
+            {
+                BinData::Map(self.merge_map(path, Some(base_map), ours_map, theirs_map)?)
+            }
+            (
+                BinData::Optional(base_optional),
+                BinData::Optional(ours_optional),
+                BinData::Optional(theirs_optional),
+            ) if base_optional.btype == ours_optional.btype
+                && ours_optional.btype == theirs_optional.btype =>
+            {
+                let item_type = ours_optional.btype;
+                match self.merge(
+                    path,
+                    optional_side(base_optional),
+                    optional_side(ours_optional),
+                    optional_side(theirs_optional),
+                ) {
+                    Merged::Value(item) => {
+                        BinData::Optional(Optional::new(item_type, item.map(|(_, item)| item)))
+                    }
+                    Merged::Conflict => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(merged)
+    }
+
+    fn merge_fields(
+        &mut self,
+        path: &mut BinPath,
+        base: &PointerOrEmbedded,
+        ours: &PointerOrEmbedded,
+        theirs: &PointerOrEmbedded,
+    ) -> PointerOrEmbedded {
+        let mut names: Vec<u32> = ours.items.iter().map(|field| field.name).collect();
+        for field in &theirs.items {
+            if !names.contains(&field.name) {
+                names.push(field.name);
+            }
+        }
+
+        let mut fields = Vec::with_capacity(names.len());
+        for name in names {
+            path.push(PathSegment::Field(name));
+            let (base, ours, theirs) = (
+                field_side(base, name),
+                field_side(ours, name),
+                field_side(theirs, name),
+            );
+            match self.merge(path, base, ours, theirs) {
+                Merged::Value(Some((btype, data))) => fields.push(BinField::new(name, btype, data)),
+                Merged::Value(None) => {}
+                Merged::Conflict => {
+                    self.conflicts.push(path.clone());
+                    fields.push(BinField::new(
+                        name,
+                        BinType::Embedded,
+                        conflict_data(base, ours, theirs),
+                    ));
+                }
+            }
+            path.pop();
+        }
+        PointerOrEmbedded::new(ours.name, fields)
+    }
+
+    /// Merges map pairs by key, or gives `None` when a pair conflicts and
+    /// the values of the map cannot hold a `MergeConflict`.
+    fn merge_map(
+        &mut self,
+        path: &mut BinPath,
+        base: Option<&Map>,
+        ours: &Map,
+        theirs: &Map,
+    ) -> Option<Map> {
+        let valuetype = ours.valuetype;
+        let mut keys: Vec<&BinData> = ours.items.iter().map(|pair| &*pair.keydata).collect();
+        for pair in &theirs.items {
+            if !keys.contains(&&*pair.keydata) {
+                keys.push(&pair.keydata);
+            }
+        }
+
+        let mut items = Vec::with_capacity(keys.len());
+        for (index, key) in keys.into_iter().enumerate() {
+            path.push(PathSegment::from_map_key(key, index));
+            let base_side = base.and_then(|base| pair_side(base, key));
+            let (ours_side, theirs_side) = (pair_side(ours, key), pair_side(theirs, key));
+            match self.merge(path, base_side, ours_side, theirs_side) {
+                Merged::Value(Some((_, value))) => items.push(MapPair::new(key.clone(), value)),
+                Merged::Value(None) => {}
+                Merged::Conflict => {
+                    if valuetype != BinType::Embedded && valuetype != BinType::Pointer {
+                        path.pop();
+                        return None;
+                    }
+                    self.conflicts.push(path.clone());
+                    items.push(MapPair::new(
+                        key.clone(),
+                        conflict_data(base_side, ours_side, theirs_side),
+                    ));
+                }
+            }
+            path.pop();
+        }
+        Some(Map::new(ours.keytype, valuetype, items))
+    }
+}
+
+fn field_side(pe: &PointerOrEmbedded, name: u32) -> Side<'_> {
+    pe.items
+        .iter()
+        .find(|field| field.name == name)
+        .map(|field| (field.btype, &*field.data))
+}
+
+fn pair_side<'a>(map: &'a Map, key: &BinData) -> Side<'a> {
+    map.items
+        .iter()
+        .find(|pair| *pair.keydata == *key)
+        .map(|pair| (map.valuetype, &*pair.valuedata))
+}
+
+fn optional_side(optional: &Optional) -> Side<'_> {
+    optional.data.as_ref().map(|data| (optional.btype, &**data))
+}
+
+fn conflict_data(base: Side, ours: Side, theirs: Side) -> BinData {
+    let mut fields = Vec::new();
+    for (name, side) in [("base", base), ("ours", ours), ("theirs", theirs)].iter() {
+        if let Some((btype, data)) = side {
+            fields.push(BinField::new(fnv1a(name), *btype, (*data).clone()));
+        }
+    }
+    BinData::PointerOrEmbedded(PointerOrEmbedded::new(fnv1a(CONFLICT_CLASS), fields))
+}
@@ -0,0 +1,421 @@
+use diff::diff_bins;
+use error::BinError;
+use hash_database::HashDatabase;
+use hashes::fnv1a;
+use path::{BinPath, PathSegment};
+use schema::FieldType;
+use structs::*;
+
+/// Class and field names of the embeds `reader` reads PTCH patches into.
+const PATCH_CLASS: u32 = 0xF9100AA9;
+const PATH_FIELD: u32 = 0x84874D36;
+const VALUE_FIELD: u32 = 0x425ED3CA;
+
+/// Parses a patch path like `mSpell.mEffects[3].value` into segments below
+/// the patched entry.
+///
+/// Names and `0x` hex hashes are fields, numbers in brackets are items of
+/// containers, structs and maps by position, and other names or hashes in
+/// brackets are keys of maps keyed by `Hash` or `Link`.
+pub fn parse_patch_path(string: &str) -> Result<Vec<PathSegment>, BinError> {
+    let bad_path = || BinError::patch(format!("Invalid patch path {:?}", string));
+    let mut segments = Vec::new();
+    for part in string.split('.') {
+        let (name, mut rest) = match part.find('[') {
+            Some(start) => part.split_at(start),
+            None => (part, ""),
+        };
+        if name.is_empty() {
+            return Err(bad_path());
+        }
+        segments.push(PathSegment::Field(name_to_hash(name).ok_or_else(bad_path)?));
+
+        while !rest.is_empty() {
+            let end = match (rest.starts_with('['), rest.find(']')) {
+                (true, Some(end)) if end > 1 => end,
+                _ => return Err(bad_path()),
+            };
+            let key = &rest[1..end];
+            segments.push(match key.parse::<usize>() {
+                Ok(index) => PathSegment::Index(index),
+                Err(_) => PathSegment::Hash(name_to_hash(key).ok_or_else(bad_path)?),
+            });
+            rest = &rest[end + 1..];
+        }
+    }
+    Ok(segments)
+}
+
+/// Formats segments below an entry as `parse_patch_path` reads them, using
+/// the field and key names in `hash_database` where they are known.
+///
+/// Gives `None` for paths a patch cannot express, like ones not starting with
+/// a field or with `WadEntryLink` keys.
+pub fn patch_path_to_string(
+    segments: &[PathSegment],
+    hash_database: &HashDatabase,
+) -> Option<String> {
+    let name = |hash: u32, name: Option<&str>| match name {
+        Some(name) if fnv1a(name) == hash && !name.contains(&['.', '[', ']'][..]) => {
+            name.to_string()
+        }
+        _ => format!("0x{:08x}", hash),
+    };
+    let mut string = String::new();
+    for segment in segments {
+        match *segment {
+            PathSegment::Field(hash) => {
+                if !string.is_empty() {
+                    string.push('.');
+                }
+                string.push_str(&name(hash, hash_database.get_field(hash)));
+            }
+            PathSegment::Index(index) if !string.is_empty() => {
+                string.push_str(&format!("[{}]", index))
+            }
+            PathSegment::Hash(hash) if !string.is_empty() => {
+                string.push_str(&format!("[{}]", name(hash, hash_database.get_hash(hash))))
+            }
+            _ => return None,
+        }
+    }
+    Some(string)
+}
+
+fn name_to_hash(name: &str) -> Option<u32> {
+    match name.strip_prefix("0x").or_else(|| name.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => Some(fnv1a(name)),
+    }
+}
+
+/// Applies the patches of `patch` to the entries of `base`, giving the bin
+/// the game would load.
+///
+/// Entries of `patch` replace the base entries of the same name or are added.
+/// Each patch is keyed by the entry it changes and replaces the value at its
+/// path, which must already exist with the same type as the patch value.
+pub fn apply_patches(base: &BinFile, patch: &BinFile) -> Result<BinFile, BinError> {
+    let mut patched = base.clone();
+    patched.is_patch = false;
+    patched.unknown = None;
+    patched.patches = None;
+
+    for pair in &patch.entries.items {
+        match patched
+            .entries
+            .items
+            .iter_mut()
+            .find(|base_pair| base_pair.keydata == pair.keydata)
+        {
+            Some(base_pair) => *base_pair = pair.clone(),
+            None => patched.entries.items.push(pair.clone()),
+        }
+    }
+    for linked in &patch.linked_list {
+        if !patched.linked_list.contains(linked) {
+            patched.linked_list.push(linked.clone());
+        }
+    }
+
+    let patches = match &patch.patches {
+        Some(patches) => patches,
+        None => return Ok(patched),
+    };
+    for (index, pair) in patches.items.iter().enumerate() {
+        apply_patch(&mut patched.entries, &pair.keydata, &pair.valuedata).map_err(|e| {
+            e.in_path(PathSegment::from_map_key(&pair.keydata, index))
+                .in_path(PathSegment::Root("Patches"))
+        })?;
+    }
+    Ok(patched)
+}
+
+fn apply_patch(entries: &mut Map, entry: &BinData, patch: &BinData) -> Result<(), BinError> {
+    let fields = match patch {
+        BinData::PointerOrEmbedded(pe) => &pe.items,
+        _ => return Err(BinError::patch("Patch is not an embed")),
+    };
+    let field = |name: u32| {
+        fields
+            .iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| {
+                BinError::patch("Patch is missing a field").in_path(PathSegment::Field(name))
+            })
+    };
+    let path = match &*field(PATH_FIELD)?.data {
+        BinData::String(path) => parse_patch_path(path)?,
+        _ => {
+            return Err(BinError::patch("Patch path is not a string")
+                .in_path(PathSegment::Field(PATH_FIELD)))
+        }
+    };
+    let value = field(VALUE_FIELD)?;
+
+    let entry_data = entries
+        .items
+        .iter_mut()
+        .find(|pair| *pair.keydata == *entry)
+        .map(|pair| &mut *pair.valuedata)
+        .ok_or_else(|| BinError::patch("Patched entry is not in the base bin"))?;
+
+    let (btype, data) = resolve(BinType::Embedded, entry_data, &path)?;
+    let expected = FieldType::of_data(btype, data);
+    let found = FieldType::of(value);
+    if expected != found {
+        let error = BinError::patch(format!(
+            "Patch value is {} but the patched value is {}",
+            found, expected
+        ));
+        return Err(path
+            .into_iter()
+            .rev()
+            .fold(error, |error, segment| error.in_path(segment)));
+    }
+    *data = (*value.data).clone();
+    Ok(())
+}
+
+/// Finds the value at `segments` below `data`, with the type it is stored as.
+fn resolve<'a>(
+    btype: BinType,
+    data: &'a mut BinData,
+    segments: &[PathSegment],
+) -> Result<(BinType, &'a mut BinData), BinError> {
+    let segment = match segments.first() {
+        Some(segment) => segment.clone(),
+        None => return Ok((btype, data)),
+    };
+    let not_found =
+        || BinError::patch("Patched value is not in the base bin").in_path(segment.clone());
+    let (btype, data) = match (&segment, data) {
+        (PathSegment::Field(name), BinData::PointerOrEmbedded(pe)) => {
+            let field = pe
+                .items
+                .iter_mut()
+                .find(|field| field.name == *name)
+                .ok_or_else(not_found)?;
+            (field.btype, &mut *field.data)
+        }
+        (PathSegment::Index(index), BinData::ContainerOrStruct(cs)) => {
+            let item_type = cs.btype;
+            (item_type, cs.items.get_mut(*index).ok_or_else(not_found)?)
+        }
+        (PathSegment::Index(index), BinData::Map(map)) => {
+            let valuetype = map.valuetype;
+            let pair = map.items.get_mut(*index).ok_or_else(not_found)?;
+            (valuetype, &mut *pair.valuedata)
+        }
+        (PathSegment::Hash(hash), BinData::Map(map)) => {
+            let valuetype = map.valuetype;
+            let pair = map
+                .items
+                .iter_mut()
+                .find(|pair| match *pair.keydata {
+                    BinData::Hash(key) | BinData::Link(key) => key == *hash,
+                    _ => false,
+                })
+                .ok_or_else(not_found)?;
+            (valuetype, &mut *pair.valuedata)
+        }
+        _ => {
+            return Err(BinError::patch(format!(
+                "Patch path goes into a {:?} value, which has no {}",
+                btype,
+                match segment {
+                    PathSegment::Field(_) => "fields",
+                    PathSegment::Index(_) => "items",
+                    _ => "keys",
+                }
+            ))
+            .in_path(segment))
+        }
+    };
+    resolve(btype, data, &segments[1..]).map_err(|e| e.in_path(segment))
+}
+
+/// A generated PTCH file and the entries it cannot express.
+#[derive(Debug)]
+pub struct GeneratedPatch {
+    pub bin_file: BinFile,
+    /// Entries of the original that the modified bin no longer has.
+    pub removed: Vec<BinPath>,
+}
+
+/// Makes a PTCH file whose patches turn the entries of `original` into those
+/// of `modified`, naming paths through `hash_database`.
+///
+/// Each changed value becomes one patch. Added entries, and entries with
+/// changes patches cannot express like added or removed fields, are written
+/// whole to the entries of the PTCH instead.
+pub fn make_patch(
+    original: &BinFile,
+    modified: &BinFile,
+    hash_database: &HashDatabase,
+) -> GeneratedPatch {
+    let changes = diff_bins(original, modified);
+    let mut entries = Map::new(BinType::Hash, BinType::Embedded, Vec::new());
+    let mut patches = Map::new(BinType::Hash, BinType::Embedded, Vec::new());
+
+    for (index, pair) in modified.entries.items.iter().enumerate() {
+        let original_pair = original
+            .entries
+            .items
+            .iter()
+            .find(|original_pair| original_pair.keydata == pair.keydata);
+        let original_pair = match original_pair {
+            Some(original_pair) => original_pair,
+            None => {
+                entries.items.push(pair.clone());
+                continue;
+            }
+        };
+        let entry_segment = PathSegment::from_map_key(&pair.keydata, index);
+        let entry_changes: Vec<_> = changes
+            .iter()
+            .filter(|change| {
+                change.path.segments.len() > 2
+                    && change.path.segments[0] == PathSegment::Root("Entries")
+                    && change.path.segments[1] == entry_segment
+            })
+            .collect();
+        if entry_changes.is_empty() && original_pair.valuedata == pair.valuedata {
+            continue;
+        }
+
+        let mut entry_patches = Vec::with_capacity(entry_changes.len());
+        for change in entry_changes {
+            let path = patch_path_to_string(&change.path.segments[2..], hash_database);
+            if let (Some(path), Some(_), Some((btype, data))) = (path, &change.old, &change.new) {
+                entry_patches.push(MapPair::new(
+                    (*pair.keydata).clone(),
+                    patch_data(path, *btype, data.clone()),
+                ));
+            }
+        }
+
+        // Patches are kept only when applying them gives back the modified entry.
+        let mut check = Map::new(
+            original.entries.keytype,
+            original.entries.valuetype,
+            vec![original_pair.clone()],
+        );
+        let applies = entry_patches
+            .iter()
+            .all(|patch| apply_patch(&mut check, &patch.keydata, &patch.valuedata).is_ok());
+        if applies && check.items[0] == *pair {
+            patches.items.extend(entry_patches);
+        } else {
+            entries.items.push(pair.clone());
+        }
+    }
+
+    let removed = original
+        .entries
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, pair)| {
+            !modified
+                .entries
+                .items
+                .iter()
+                .any(|modified_pair| modified_pair.keydata == pair.keydata)
+        })
+        .map(|(index, pair)| {
+            let mut path = BinPath::new();
+            path.push(PathSegment::Root("Entries"));
+            path.with(PathSegment::from_map_key(&pair.keydata, index))
+        })
+        .collect();
+
+    let linked_list = modified
+        .linked_list
+        .iter()
+        .filter(|linked| !original.linked_list.contains(linked))
+        .cloned()
+        .collect();
+    GeneratedPatch {
+        bin_file: BinFile::new(
+            true,
+            Some(1),
+            modified.version,
+            linked_list,
+            entries,
+            Some(patches),
+        ),
+        removed,
+    }
+}
+
+fn patch_data(path: String, btype: BinType, data: BinData) -> BinData {
+    BinData::PointerOrEmbedded(PointerOrEmbedded::new(
+        PATCH_CLASS,
+        vec![
+            BinField::new(PATH_FIELD, BinType::String, BinData::String(path)),
+            BinField::new(VALUE_FIELD, btype, data),
+        ],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_paths() {
+        assert_eq!(
+            parse_patch_path("mSpell.mEffects[3].0x0000000A[Skin0]").unwrap(),
+            vec![
+                PathSegment::Field(fnv1a("mSpell")),
+                PathSegment::Field(fnv1a("mEffects")),
+                PathSegment::Index(3),
+                PathSegment::Field(0xA),
+                PathSegment::Hash(fnv1a("Skin0")),
+            ]
+        );
+        assert_eq!(
+            parse_patch_path("a[0][1]").unwrap(),
+            vec![
+                PathSegment::Field(fnv1a("a")),
+                PathSegment::Index(0),
+                PathSegment::Index(1),
+            ]
+        );
+        for path in &["", "a..b", ".a", "a[]", "a[0", "a[0]b", "[0]", "0xZZ"] {
+            match parse_patch_path(path) {
+                Err(BinError::Patch { message }) => {
+                    assert_eq!(message, format!("Invalid patch path {:?}", path))
+                }
+                result => panic!("Expected {:?} to be invalid but got {:?}", path, result),
+            }
+        }
+    }
+
+    #[test]
+    fn formats_paths_it_parses() {
+        let hash_database = HashDatabase::new();
+        let segments = parse_patch_path("mSpell.mEffects[3].0x0000000a[Skin0]").unwrap();
+        let string = patch_path_to_string(&segments, &hash_database).unwrap();
+        assert_eq!(
+            string,
+            format!(
+                "0x{:08x}.0x{:08x}[3].0x0000000a[0x{:08x}]",
+                fnv1a("mSpell"),
+                fnv1a("mEffects"),
+                fnv1a("Skin0")
+            )
+        );
+        assert_eq!(parse_patch_path(&string).unwrap(), segments);
+
+        assert_eq!(
+            patch_path_to_string(&[PathSegment::Index(0)], &hash_database),
+            None
+        );
+        assert_eq!(
+            patch_path_to_string(&[PathSegment::Hash(1)], &hash_database),
+            None
+        );
+    }
+}
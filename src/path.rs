@@ -0,0 +1,97 @@
+use hash_database::HashDatabase;
+use structs::*;
+
+use std::fmt;
+
+/// One step from the root of a bin file down to a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    /// A top level section, `Entries`, `Patches` or `LinkedList`.
+    Root(&'static str),
+    /// A field of a Pointer or Embedded, by name hash.
+    Field(u32),
+    /// An item of a Container, Struct or LinkedList, or a map pair without a hash key.
+    Index(usize),
+    /// A map pair keyed by a `Hash` or `Link`, including entries and patches.
+    Hash(u32),
+    /// A map pair keyed by a `WadEntryLink`.
+    WadEntryLink(u64),
+}
+
+/// Logical location of a value, like `Entries[0x1234ABCD].mSpellData.mEffects[3].value`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BinPath {
+    pub segments: Vec<PathSegment>,
+}
+
+impl PathSegment {
+    /// Segment of a map pair, using the key when it is a hash.
+    pub fn from_map_key(keydata: &BinData, index: usize) -> PathSegment {
+        match *keydata {
+            BinData::Hash(hash) | BinData::Link(hash) => PathSegment::Hash(hash),
+            BinData::WadEntryLink(hash) => PathSegment::WadEntryLink(hash),
+            _ => PathSegment::Index(index),
+        }
+    }
+}
+
+impl BinPath {
+    pub fn new() -> BinPath {
+        BinPath {
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, segment: PathSegment) {
+        self.segments.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<PathSegment> {
+        self.segments.pop()
+    }
+
+    pub fn with(&self, segment: PathSegment) -> BinPath {
+        let mut path = self.clone();
+        path.push(segment);
+        path
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Formats the path, resolving field names and hash keys through `hash_database`.
+    pub fn to_string_with(&self, hash_database: &HashDatabase) -> String {
+        let mut string = String::new();
+        for segment in &self.segments {
+            match *segment {
+                PathSegment::Root(name) => {
+                    if !string.is_empty() {
+                        string.push('.');
+                    }
+                    string.push_str(name);
+                }
+                PathSegment::Field(name) => {
+                    if !string.is_empty() {
+                        string.push('.');
+                    }
+                    string.push_str(&hash_database.field_to_string(name));
+                }
+                PathSegment::Index(index) => string.push_str(&format!("[{}]", index)),
+                PathSegment::Hash(hash) => {
+                    string.push_str(&format!("[{}]", hash_database.hash_to_string(hash)))
+                }
+                PathSegment::WadEntryLink(hash) => {
+                    string.push_str(&format!("[{}]", hash_database.game_to_string(hash)))
+                }
+            }
+        }
+        string
+    }
+}
+
+impl fmt::Display for BinPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string_with(&HashDatabase::default()))
+    }
+}
@@ -0,0 +1,708 @@
+use error::BinError;
+use hash_database::HashDatabase;
+use json_reader::{string_to_hash_u32, string_to_hash_u64};
+use path::{BinPath, PathSegment};
+use structs::*;
+use text_writer::typed_value_to_text;
+
+use std::cmp::Ordering;
+
+/// Which children of a value a step selects.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    /// `*`, every child.
+    Any,
+    /// An item of a container, struct or map by position, from an unquoted
+    /// number.
+    Index(usize),
+    /// A field name or map key, as a name or hex hash. Quoted, it can also be
+    /// a number, like a decimal hash or an integer key.
+    Name(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Number(f64),
+    String(String),
+    Bool(bool),
+}
+
+/// Condition of a `[?field op value]` filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    /// Field of each item to test, or `None` for `@`, the item itself.
+    pub field: Option<u32>,
+    /// `None` only requires the field to exist.
+    pub comparison: Option<(Comparison, Literal)>,
+}
+
+/// One step of a query. Steps with a class only keep embeds and pointers of
+/// that class.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// `.name`, fields of an embed or pointer.
+    Field(Selector, Option<u32>),
+    /// `..name`, fields at any depth below.
+    Descendant(Selector, Option<u32>),
+    /// `[key]`, items of a container, struct or map, or entries.
+    Item(Selector, Option<u32>),
+    /// `[?field op value]`, the items whose field compares true.
+    Filter(Predicate),
+}
+
+/// A parsed query like `Entries[*:SkinCharacterDataProperties]..bankUnits[*].name`.
+///
+/// A query starts at `Entries`, `Patches`, or both when it starts with a
+/// step. Optionals are looked through, so their value is selected directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub root: Option<&'static str>,
+    pub steps: Vec<Step>,
+}
+
+/// A value selected by a query, with the type it is stored as.
+#[derive(Debug, Clone)]
+pub struct Match<'a> {
+    pub path: BinPath,
+    pub btype: BinType,
+    pub data: &'a BinData,
+}
+
+impl<'a> Match<'a> {
+    /// Formats the match as its path and ritobin `type = value`.
+    pub fn to_string_with(&self, hash_database: &HashDatabase) -> String {
+        format!(
+            "{}: {}",
+            self.path.to_string_with(hash_database),
+            typed_value_to_text(&self.btype, self.data, hash_database)
+        )
+    }
+}
+
+/// A value the next step starts from, or a whole section before the first step.
+enum Node<'a> {
+    Section(BinPath, &'a Map),
+    Value(BinPath, BinType, &'a BinData),
+}
+
+/// How a child hangs off its parent, for matching selectors.
+enum ChildKey<'a> {
+    Field(u32),
+    Item(usize),
+    Pair(usize, &'a BinData),
+}
+
+struct Child<'a> {
+    key: ChildKey<'a>,
+    path: BinPath,
+    btype: BinType,
+    data: &'a BinData,
+}
+
+impl Query {
+    /// Parses a query, giving the column of the first character it could not read.
+    pub fn parse(query: &str) -> Result<Query, BinError> {
+        QueryParser {
+            chars: query.chars().collect(),
+            position: 0,
+        }
+        .parse()
+    }
+
+    /// Runs the query over the entries and patches of `bin_file`.
+    pub fn run<'a>(&self, bin_file: &'a BinFile) -> Vec<Match<'a>> {
+        let sections = [
+            ("Entries", Some(&bin_file.entries)),
+            ("Patches", bin_file.patches.as_ref()),
+        ];
+        let mut nodes = Vec::new();
+        for (name, map) in sections.iter() {
+            if let Some(map) = map {
+                if self.root.is_none() || self.root == Some(*name) {
+                    let mut path = BinPath::new();
+                    path.push(PathSegment::Root(name));
+                    nodes.push(Node::Section(path, map));
+                }
+            }
+        }
+
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for node in &nodes {
+                apply_step(step, node, &mut next);
+            }
+            nodes = next;
+        }
+
+        nodes
+            .into_iter()
+            .filter_map(|node| match node {
+                Node::Value(path, btype, data) => Some(Match { path, btype, data }),
+                Node::Section(..) => None,
+            })
+            .collect()
+    }
+}
+
+fn apply_step<'a>(step: &Step, node: &Node<'a>, next: &mut Vec<Node<'a>>) {
+    let mut keep = |child: Child<'a>, class: Option<u32>| {
+        if class.is_none_or(|class| class_of(child.data) == Some(class)) {
+            next.push(Node::Value(child.path, child.btype, child.data));
+        }
+    };
+    match step {
+        Step::Field(selector, class) => {
+            for child in children(node) {
+                if let ChildKey::Field(name) = child.key {
+                    if selects_field(selector, name) {
+                        keep(child, *class);
+                    }
+                }
+            }
+        }
+        Step::Descendant(selector, class) => {
+            let mut found = Vec::new();
+            descendants(node, selector, &mut found);
+            for child in found {
+                keep(child, *class);
+            }
+        }
+        Step::Item(selector, class) => {
+            for child in children(node) {
+                let selected = match (selector, &child.key) {
+                    (_, ChildKey::Field(_)) => false,
+                    (Selector::Any, _) => true,
+                    (Selector::Index(index), ChildKey::Item(position))
+                    | (Selector::Index(index), ChildKey::Pair(position, _)) => index == position,
+                    (Selector::Name(name), ChildKey::Pair(_, key)) => key_matches(key, name),
+                    (Selector::Name(_), ChildKey::Item(_)) => false,
+                };
+                if selected {
+                    keep(child, *class);
+                }
+            }
+        }
+        Step::Filter(predicate) => {
+            for child in children(node) {
+                if !matches!(child.key, ChildKey::Field(_)) && predicate.test(child.data) {
+                    keep(child, None);
+                }
+            }
+        }
+    }
+}
+
+/// The fields, items or map values directly below a node.
+fn children<'a>(node: &Node<'a>) -> Vec<Child<'a>> {
+    let (path, map) = match node {
+        Node::Section(path, map) => (path, *map),
+        Node::Value(path, _, data) => match look_through(data) {
+            BinData::PointerOrEmbedded(pe) => {
+                return pe
+                    .items
+                    .iter()
+                    .map(|field| Child {
+                        key: ChildKey::Field(field.name),
+                        path: path.with(PathSegment::Field(field.name)),
+                        btype: field.btype,
+                        data: &field.data,
+                    })
+                    .collect()
+            }
+            BinData::ContainerOrStruct(cs) => {
+                return cs
+                    .items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| Child {
+                        key: ChildKey::Item(index),
+                        path: path.with(PathSegment::Index(index)),
+                        btype: cs.btype,
+                        data: item,
+                    })
+                    .collect()
+            }
+            BinData::Map(map) => (path, map),
+            _ => return Vec::new(),
+        },
+    };
+    map.items
+        .iter()
+        .enumerate()
+        .map(|(index, pair)| Child {
+            key: ChildKey::Pair(index, &pair.keydata),
+            path: path.with(PathSegment::from_map_key(&pair.keydata, index)),
+            btype: map.valuetype,
+            data: &pair.valuedata,
+        })
+        .collect()
+}
+
+/// Collects the fields below `node` at any depth that `selector` selects,
+/// or every value below it for `*`.
+fn descendants<'a>(node: &Node<'a>, selector: &Selector, found: &mut Vec<Child<'a>>) {
+    for child in children(node) {
+        let selected = match (selector, &child.key) {
+            (Selector::Any, _) => true,
+            (_, ChildKey::Field(name)) => selects_field(selector, *name),
+            _ => false,
+        };
+        let next = Node::Value(child.path.clone(), child.btype, child.data);
+        if selected {
+            found.push(child);
+        }
+        descendants(&next, selector, found);
+    }
+}
+
+/// The value inside an optional, or the value itself.
+pub(crate) fn look_through(data: &BinData) -> &BinData {
+    match data {
+        BinData::Optional(Optional {
+            data: Some(data), ..
+        }) => data,
+        data => data,
+    }
+}
+
+fn class_of(data: &BinData) -> Option<u32> {
+    match look_through(data) {
+        BinData::PointerOrEmbedded(pe) if pe.name != 0 => Some(pe.name),
+        _ => None,
+    }
+}
+
+fn selects_field(selector: &Selector, name: u32) -> bool {
+    match selector {
+        Selector::Any => true,
+        Selector::Index(_) => false,
+        Selector::Name(selected) => string_to_hash_u32(selected).ok() == Some(name),
+    }
+}
+
+pub(crate) fn key_matches(key: &BinData, name: &str) -> bool {
+    match key {
+        BinData::Hash(hash) | BinData::Link(hash) => string_to_hash_u32(name).ok() == Some(*hash),
+        BinData::WadEntryLink(hash) => string_to_hash_u64(&name.to_lowercase()).ok() == Some(*hash),
+        BinData::String(string) => string == name,
+        key => {
+            let literal = match name.parse::<f64>() {
+                Ok(number) => Literal::Number(number),
+                Err(_) => Literal::String(name.to_string()),
+            };
+            compare(key, &literal) == Some(Ordering::Equal)
+        }
+    }
+}
+
+impl Predicate {
+    fn test(&self, data: &BinData) -> bool {
+        let value = match self.field {
+            None => look_through(data),
+            Some(name) => match look_through(data) {
+                BinData::PointerOrEmbedded(pe) => {
+                    match pe.items.iter().find(|field| field.name == name) {
+                        Some(field) => look_through(&field.data),
+                        None => return false,
+                    }
+                }
+                _ => return false,
+            },
+        };
+        match &self.comparison {
+            None => true,
+            Some((comparison, literal)) => {
+                let ordering = compare(value, literal);
+                match comparison {
+                    Comparison::Equal => ordering == Some(Ordering::Equal),
+                    Comparison::NotEqual => ordering != Some(Ordering::Equal),
+                    Comparison::Less => ordering == Some(Ordering::Less),
+                    Comparison::LessOrEqual => {
+                        matches!(ordering, Some(Ordering::Less) | Some(Ordering::Equal))
+                    }
+                    Comparison::Greater => ordering == Some(Ordering::Greater),
+                    Comparison::GreaterOrEqual => {
+                        matches!(ordering, Some(Ordering::Greater) | Some(Ordering::Equal))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Orders a value against a literal, or gives `None` when they cannot be
+/// compared. Hashes only compare equal to the names or hashes they match,
+/// and embeds and pointers equal to their class name.
+fn compare(data: &BinData, literal: &Literal) -> Option<Ordering> {
+    let equal_if = |equal: bool| if equal { Some(Ordering::Equal) } else { None };
+    match (data, literal) {
+        (BinData::Bool(value), Literal::Bool(literal))
+        | (BinData::Flag(value), Literal::Bool(literal)) => value.partial_cmp(literal),
+        (BinData::String(value), Literal::String(literal)) => Some(value.as_str().cmp(literal)),
+        (BinData::Hash(_), Literal::String(literal))
+        | (BinData::Link(_), Literal::String(literal))
+        | (BinData::WadEntryLink(_), Literal::String(literal)) => {
+            equal_if(key_matches(data, literal))
+        }
+        (BinData::PointerOrEmbedded(pe), Literal::String(literal)) => {
+            equal_if(string_to_hash_u32(literal).ok() == Some(pe.name))
+        }
+        (data, Literal::Number(literal)) => number_of(data)?.partial_cmp(literal),
+        _ => None,
+    }
+}
+
+fn number_of(data: &BinData) -> Option<f64> {
+    let number = match *data {
+        BinData::SInt8(value) => value as f64,
+        BinData::UInt8(value) => value as f64,
+        BinData::SInt16(value) => value as f64,
+        BinData::UInt16(value) => value as f64,
+        BinData::SInt32(value) => value as f64,
+        BinData::UInt32(value) => value as f64,
+        BinData::SInt64(value) => value as f64,
+        BinData::UInt64(value) => value as f64,
+        BinData::Float32(value) => value as f64,
+        _ => return None,
+    };
+    Some(number)
+}
+
+struct QueryParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl QueryParser {
+    fn error<T, S: Into<String>>(&self, message: S) -> Result<T, BinError> {
+        Err(BinError::Query {
+            column: self.position + 1,
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn eat(&mut self, string: &str) -> bool {
+        let matches = string
+            .chars()
+            .enumerate()
+            .all(|(offset, c)| self.chars.get(self.position + offset) == Some(&c));
+        if matches {
+            self.position += string.chars().count();
+        }
+        matches
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn parse(mut self) -> Result<Query, BinError> {
+        let mut root = None;
+        for name in ["Entries", "Patches"].iter() {
+            if self.eat(name) {
+                root = Some(*name);
+                break;
+            }
+        }
+
+        let mut steps = Vec::new();
+        while let Some(c) = self.peek() {
+            let step = if self.eat("..") {
+                let selector = self.selector("field name")?;
+                Step::Descendant(selector, self.class()?)
+            } else if self.eat(".") {
+                let selector = self.selector("field name")?;
+                Step::Field(selector, self.class()?)
+            } else if self.eat("[") {
+                self.skip_spaces();
+                let step = if self.eat("?") {
+                    Step::Filter(self.predicate()?)
+                } else {
+                    let selector = self.selector("key")?;
+                    Step::Item(selector, self.class()?)
+                };
+                self.skip_spaces();
+                if !self.eat("]") {
+                    return self.error("Expected ]");
+                }
+                step
+            } else {
+                return self.error(format!("Expected . or [ but found {:?}", c));
+            };
+            steps.push(step);
+        }
+        Ok(Query { root, steps })
+    }
+
+    fn selector(&mut self, what: &str) -> Result<Selector, BinError> {
+        if self.eat("*") {
+            return Ok(Selector::Any);
+        }
+        // Quoted names are never positions, so `["123"]` matches the key 123.
+        if let Some(name) = self.quoted()? {
+            return Ok(Selector::Name(name));
+        }
+        let name = self.word();
+        if name.is_empty() {
+            return self.error(format!("Expected {} or *", what));
+        }
+        Ok(match name.parse::<usize>() {
+            Ok(index) => Selector::Index(index),
+            Err(_) => Selector::Name(name),
+        })
+    }
+
+    fn class(&mut self) -> Result<Option<u32>, BinError> {
+        if !self.eat(":") {
+            return Ok(None);
+        }
+        let name = self.word();
+        if name.is_empty() {
+            return self.error("Expected class name");
+        }
+        string_to_hash_u32(&name).map(Some)
+    }
+
+    fn predicate(&mut self) -> Result<Predicate, BinError> {
+        self.skip_spaces();
+        let field = if self.eat("@") {
+            None
+        } else {
+            let name = self.word();
+            if name.is_empty() {
+                return self.error("Expected field name or @");
+            }
+            Some(string_to_hash_u32(&name)?)
+        };
+        self.skip_spaces();
+
+        let operators = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        let comparison = match operators.iter().find(|(operator, _)| self.eat(operator)) {
+            Some((_, comparison)) => *comparison,
+            None => {
+                return Ok(Predicate {
+                    field,
+                    comparison: None,
+                })
+            }
+        };
+        self.skip_spaces();
+
+        let literal = match self.quoted()? {
+            Some(string) => Literal::String(string),
+            None => {
+                let mut word = String::new();
+                while let Some(c) = self.peek() {
+                    if c.is_whitespace() || c == ']' {
+                        break;
+                    }
+                    word.push(c);
+                    self.position += 1;
+                }
+                match word.as_str() {
+                    "" => return self.error("Expected value to compare with"),
+                    "true" => Literal::Bool(true),
+                    "false" => Literal::Bool(false),
+                    _ => match word.parse::<f64>() {
+                        Ok(number) if !word.starts_with("0x") => Literal::Number(number),
+                        _ => Literal::String(word),
+                    },
+                }
+            }
+        };
+        Ok(Predicate {
+            field,
+            comparison: Some((comparison, literal)),
+        })
+    }
+
+    /// Reads a name up to the next character with a meaning in queries.
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || ".[]:?@=!<>\"".contains(c) {
+                break;
+            }
+            word.push(c);
+            self.position += 1;
+        }
+        word
+    }
+
+    fn quoted(&mut self) -> Result<Option<String>, BinError> {
+        if !self.eat("\"") {
+            return Ok(None);
+        }
+        let mut string = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.position += 1;
+                    return Ok(Some(string));
+                }
+                Some('\\') if self.chars.get(self.position + 1).is_some() => {
+                    string.push(self.chars[self.position + 1]);
+                    self.position += 2;
+                }
+                Some(c) => {
+                    string.push(c);
+                    self.position += 1;
+                }
+                None => return self.error("Unterminated string"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashes::fnv1a;
+    use text_reader::convert_text_to_bin;
+
+    fn query_error(query: &str) -> (usize, String) {
+        match Query::parse(query) {
+            Err(BinError::Query { column, message }) => (column, message),
+            result => panic!(
+                "Expected a query error for {:?} but got {:?}",
+                query, result
+            ),
+        }
+    }
+
+    fn bin_file() -> BinFile {
+        convert_text_to_bin(
+            "entries: map[hash,embed] = {
+                \"Skin0\" = SkinCharacterDataProperties {
+                    skinScale: f32 = 1.5
+                    name: string = \"Ahri\"
+                    units: list[embed] = {
+                        Unit { name: string = \"a\" scale: f32 = 1 }
+                        Unit { name: string = \"b\" scale: f32 = 2 }
+                        Other { name: string = \"c\" }
+                    }
+                    counts: map[u32,string] = { 1 = \"one\" 12 = \"twelve\" }
+                }
+                \"Skin1\" = SkinCharacterDataProperties {
+                    skinScale: f32 = 0.5
+                }
+            }",
+        )
+        .unwrap()
+    }
+
+    fn run(query: &str) -> Vec<String> {
+        let bin_file = bin_file();
+        Query::parse(query)
+            .unwrap()
+            .run(&bin_file)
+            .iter()
+            .map(|found| typed_value_to_text(&found.btype, found.data, &HashDatabase::default()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_steps() {
+        let query = Query::parse("Entries[*:SkinCharacterDataProperties]..units[0].name").unwrap();
+        assert_eq!(query.root, Some("Entries"));
+        assert_eq!(
+            query.steps,
+            vec![
+                Step::Item(Selector::Any, Some(fnv1a("SkinCharacterDataProperties"))),
+                Step::Descendant(Selector::Name("units".to_string()), None),
+                Step::Item(Selector::Index(0), None),
+                Step::Field(Selector::Name("name".to_string()), None),
+            ]
+        );
+
+        let query = Query::parse("[?skinScale >= 1.2][?@ != \"a b\"][? name]").unwrap();
+        assert_eq!(query.root, None);
+        assert_eq!(
+            query.steps,
+            vec![
+                Step::Filter(Predicate {
+                    field: Some(fnv1a("skinScale")),
+                    comparison: Some((Comparison::GreaterOrEqual, Literal::Number(1.2))),
+                }),
+                Step::Filter(Predicate {
+                    field: None,
+                    comparison: Some((Comparison::NotEqual, Literal::String("a b".to_string()))),
+                }),
+                Step::Filter(Predicate {
+                    field: Some(fnv1a("name")),
+                    comparison: None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_error_columns() {
+        assert_eq!(query_error("Entries[*"), (10, "Expected ]".to_string()));
+        assert_eq!(
+            query_error("Entries]"),
+            (8, "Expected . or [ but found ']'".to_string())
+        );
+        assert_eq!(
+            query_error("Entries."),
+            (9, "Expected field name or *".to_string())
+        );
+        assert_eq!(query_error("[*:]"), (4, "Expected class name".to_string()));
+        assert_eq!(
+            query_error("[?skinScale > ]"),
+            (15, "Expected value to compare with".to_string())
+        );
+    }
+
+    #[test]
+    fn selects_values() {
+        assert_eq!(run("Entries[*].skinScale"), vec!["f32 = 1.5", "f32 = 0.5"]);
+        assert_eq!(
+            run("..units[*:Unit].name"),
+            vec!["string = \"a\"", "string = \"b\""]
+        );
+        assert_eq!(run("Entries[Skin0].name"), vec!["string = \"Ahri\""]);
+        assert_eq!(run("Entries[Skin1].units"), Vec::<String>::new());
+        assert_eq!(run("..counts[1]"), vec!["string = \"twelve\""]);
+        assert_eq!(run("..counts[\"1\"]"), vec!["string = \"one\""]);
+        assert_eq!(
+            run(&format!("Entries[\"{}\"].skinScale", fnv1a("Skin1"))),
+            vec!["f32 = 0.5"]
+        );
+    }
+
+    #[test]
+    fn filters_items() {
+        assert_eq!(
+            run("Entries[?skinScale > 1].name"),
+            vec!["string = \"Ahri\""]
+        );
+        assert_eq!(run("..units[?scale <= 1].name"), vec!["string = \"a\""]);
+        assert_eq!(run("..units[?scale].name").len(), 2);
+        assert_eq!(run("..units[?name == \"c\"].name"), vec!["string = \"c\""]);
+        assert_eq!(run("Entries[*].skinScale[?@ < 1]"), Vec::<String>::new());
+    }
+}
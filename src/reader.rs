@@ -1,457 +1,432 @@
+use error::BinError;
+use path::PathSegment;
 use structs::*;
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::io::{Cursor, Read};
+use std::io::{self, Cursor, Read};
+
+pub(crate) fn read<T, F>(reader: &mut Cursor<&[u8]>, what: &str, read_fn: F) -> Result<T, BinError>
+where
+    F: FnOnce(&mut Cursor<&[u8]>) -> io::Result<T>,
+{
+    let offset = reader.position();
+    read_fn(reader).map_err(|_| BinError::Truncated {
+        offset,
+        what: what.to_string(),
+    })
+}
+
+fn read_type(reader: &mut Cursor<&[u8]>, what: &str) -> Result<(u8, BinType), BinError> {
+    let offset = reader.position();
+    let ftype = read(reader, what, |r| r.read_u8())?;
+    match u8_to_type(ftype) {
+        Some(bin_type) => Ok((ftype, bin_type)),
+        None => Err(BinError::UnknownType {
+            offset,
+            type_byte: ftype,
+        }),
+    }
+}
+
+fn check_size(what: &str, old_offset: u64, new_offset: u64, length: u32) -> Result<(), BinError> {
+    if old_offset + length as u64 != new_offset {
+        return Err(BinError::SizeMismatch {
+            what: what.to_string(),
+            offset: old_offset,
+            expected: length as u64,
+            actual: new_offset - old_offset,
+        });
+    }
+    Ok(())
+}
 
-fn read_string(reader: &mut Cursor<&Vec<u8>>) -> String {
-    let string_length = reader
-        .read_u16::<LittleEndian>()
-        .expect("Could not read string length");
+/// Capacity for `count` items of at least `item_size` bytes, limited by the
+/// bytes left so a corrupt count fails as truncated instead of allocating.
+fn capacity(reader: &Cursor<&[u8]>, count: usize, item_size: usize) -> usize {
+    let remaining = (reader.get_ref().len() as u64).saturating_sub(reader.position()) as usize;
+    count.min(remaining / item_size)
+}
+
+fn read_string(reader: &mut Cursor<&[u8]>) -> Result<String, BinError> {
+    let string_length = read(reader, "string length", |r| r.read_u16::<LittleEndian>())?;
+    let offset = reader.position();
     let mut string = vec![0u8; string_length as usize];
-    reader
-        .read_exact(&mut string)
-        .expect("Could not read string");
-    String::from_utf8(string).expect("Invalid UTF-8 sequence")
+    read(reader, "string", |r| r.read_exact(&mut string))?;
+    String::from_utf8(string).map_err(|_| BinError::InvalidUtf8 { offset })
 }
 
-pub fn read_bin(contents: &Vec<u8>) -> BinFile {
-    println!("Reading bin file");
+/// Parses a `PROP` or `PTCH` bin file.
+pub fn read_bin(contents: &[u8]) -> Result<BinFile, BinError> {
     let mut reader = Cursor::new(contents);
 
     let mut is_patch = false;
     let mut unknown_data: Option<u64> = None;
 
     let mut signature: Vec<u8> = vec![0u8; 4];
-    reader
-        .read_exact(&mut signature)
-        .expect("Could not read signature");
+    read(&mut reader, "signature", |r| r.read_exact(&mut signature))?;
     if signature == b"PTCH" {
-        unknown_data = Some(
-            reader
-                .read_u64::<LittleEndian>()
-                .expect("Could not read unknown data"),
-        );
-        reader
-            .read_exact(&mut signature)
-            .expect("Could not read second signature");
+        unknown_data = Some(read(&mut reader, "unknown data", |r| {
+            r.read_u64::<LittleEndian>()
+        })?);
+        read(&mut reader, "second signature", |r| {
+            r.read_exact(&mut signature)
+        })?;
         is_patch = true;
     }
     if signature != b"PROP" {
-        panic!("Bin has no valid signature");
+        return Err(BinError::BadSignature {
+            offset: reader.position() - 4,
+            signature,
+        });
     }
 
-    let version = reader
-        .read_u32::<LittleEndian>()
-        .expect("Could not read version");
+    let version = read(&mut reader, "version", |r| r.read_u32::<LittleEndian>())?;
 
     let mut linked_list: Vec<String> = Vec::new();
 
     if version >= 2 {
-        let linked_list_count = reader
-            .read_u32::<LittleEndian>()
-            .expect("Could not read linked list count");
+        let linked_list_count = read(&mut reader, "linked list count", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
         for _ in 0..linked_list_count {
-            linked_list.push(read_string(&mut reader));
+            linked_list.push(read_string(&mut reader)?);
         }
     }
 
-    let entries_count = reader
-        .read_u32::<LittleEndian>()
-        .expect("Could not read entries count");
+    let entries_count = read(&mut reader, "entries count", |r| {
+        r.read_u32::<LittleEndian>()
+    })?;
 
-    let mut entry_types: Vec<u32> = Vec::with_capacity(entries_count as usize);
+    let mut entry_types: Vec<u32> =
+        Vec::with_capacity(capacity(&reader, entries_count as usize, 4));
     for _ in 0..entries_count {
-        entry_types.push(
-            reader
-                .read_u32::<LittleEndian>()
-                .expect("Could not read entry type"),
-        );
+        entry_types.push(read(&mut reader, "entry type", |r| {
+            r.read_u32::<LittleEndian>()
+        })?);
     }
 
     let mut entries_map = Map::new(
         BinType::Hash,
         BinType::Embedded,
-        Vec::with_capacity(entries_count as usize),
+        Vec::with_capacity(capacity(&reader, entry_types.len(), 10)),
     );
 
-    for entry_type in entry_types {
-        let entry_length = reader
-            .read_u32::<LittleEndian>()
-            .expect("Could not read entry length");
+    for (index, entry_type) in entry_types.into_iter().enumerate() {
+        let entry = read_entry(&mut reader, entry_type, index)
+            .map_err(|e| e.in_path(PathSegment::Root("Entries")))?;
+        entries_map.items.push(entry);
+    }
 
-        let old_offset = reader.position();
+    let mut patches_map: Option<Map> = None;
 
-        let entry_name = reader
-            .read_u32::<LittleEndian>()
-            .expect("Could not read entry name");
-        let field_count = reader
-            .read_u16::<LittleEndian>()
-            .expect("Could not read entry field count");
+    if is_patch {
+        let patches_count = read(&mut reader, "patches count", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
 
-        let mut embedded =
-            PointerOrEmbedded::new(entry_type, Vec::with_capacity(field_count as usize));
+        let mut patches = Map::new(
+            BinType::Hash,
+            BinType::Embedded,
+            Vec::with_capacity(capacity(&reader, patches_count as usize, 11)),
+        );
 
-        for _ in 0..field_count as usize {
-            let name = reader
-                .read_u32::<LittleEndian>()
-                .expect("Could not read entry field name");
-            let ftype = reader.read_u8().expect("Could not read entry field type");
+        for index in 0..patches_count as usize {
+            let patch = read_patch(&mut reader, index)
+                .map_err(|e| e.in_path(PathSegment::Root("Patches")))?;
+            patches.items.push(patch);
+        }
 
-            let field = BinField::new(
-                name,
-                u8_to_type(ftype).unwrap_or_else(|| panic!("Unknown entry field type {}", ftype)),
-                read_value_by_type(&mut reader, ftype),
-            );
+        patches_map = Some(patches);
+    }
 
-            embedded.items.push(field);
-        }
+    Ok(BinFile::new(
+        is_patch,
+        unknown_data,
+        version,
+        linked_list,
+        entries_map,
+        patches_map,
+    ))
+}
 
-        entries_map.items.push(MapPair::new(
-            BinData::Hash(entry_name),
-            BinData::PointerOrEmbedded(embedded),
-        ));
+fn read_entry(
+    reader: &mut Cursor<&[u8]>,
+    entry_type: u32,
+    index: usize,
+) -> Result<MapPair, BinError> {
+    let in_index = |e: BinError| e.in_path(PathSegment::Index(index));
 
-        let new_offset = reader.position();
-        if old_offset + entry_length as u64 != new_offset {
-            let diff_offset = new_offset - old_offset;
-            panic!(
-                "Wrong entry {} size from {} to {} in entries. Its {}, but should have been {}",
-                entry_name, old_offset, new_offset, diff_offset, entry_length
-            );
-        }
-    }
+    let entry_length =
+        read(reader, "entry length", |r| r.read_u32::<LittleEndian>()).map_err(in_index)?;
 
-    let mut patches_map: Option<Map> = None;
+    let old_offset = reader.position();
 
-    if is_patch {
-        let patches_count = reader
-            .read_u32::<LittleEndian>()
-            .expect("Could not read patches count");
+    let entry_name =
+        read(reader, "entry name", |r| r.read_u32::<LittleEndian>()).map_err(in_index)?;
 
-        patches_map = Some(Map::new(
-            BinType::Hash,
-            BinType::Embedded,
-            Vec::with_capacity(patches_count as usize),
-        ));
+    let in_entry = |e: BinError| {
+        e.in_class(entry_type)
+            .in_path(PathSegment::Hash(entry_name))
+    };
 
-        for _ in 0..patches_count {
-            let patch_name = reader
-                .read_u32::<LittleEndian>()
-                .expect("Could not read patch name");
-            let patch_length = reader
-                .read_u32::<LittleEndian>()
-                .expect("Could not read patch length");
+    let field_count = read(reader, "entry field count", |r| {
+        r.read_u16::<LittleEndian>()
+    })
+    .map_err(in_entry)?;
 
-            let old_offset = reader.position();
+    let embedded = PointerOrEmbedded::new(
+        entry_type,
+        read_fields(reader, field_count, "entry").map_err(in_entry)?,
+    );
 
-            let ftype = reader.read_u8().expect("Could not read patch type");
+    check_size("entry", old_offset, reader.position(), entry_length).map_err(in_entry)?;
 
-            let string = BinData::String(read_string(&mut reader));
+    Ok(MapPair::new(
+        BinData::Hash(entry_name),
+        BinData::PointerOrEmbedded(embedded),
+    ))
+}
 
-            let mut embedded = PointerOrEmbedded::new(
-                0xF9100AA9, // patch FNV1a
-                Vec::with_capacity(2),
-            );
+fn read_patch(reader: &mut Cursor<&[u8]>, index: usize) -> Result<MapPair, BinError> {
+    let patch_name = read(reader, "patch name", |r| r.read_u32::<LittleEndian>())
+        .map_err(|e| e.in_path(PathSegment::Index(index)))?;
 
-            let first_field = BinField::new(
-                0x84874D36, // path FNV1a
-                BinType::String,
-                string,
-            );
-            embedded.items.push(first_field);
+    let in_patch = |e: BinError| e.in_path(PathSegment::Hash(patch_name));
 
-            let second_field = BinField::new(
-                0x425ED3CA, // value FNV1a
-                u8_to_type(ftype).unwrap_or_else(|| panic!("Unknown patch field type {}", ftype)),
-                read_value_by_type(&mut reader, ftype),
-            );
-            embedded.items.push(second_field);
-
-            patches_map.as_mut().unwrap().items.push(MapPair::new(
-                BinData::Hash(patch_name),
-                BinData::PointerOrEmbedded(embedded),
-            ));
-
-            let new_offset = reader.position();
-            if old_offset + patch_length as u64 != new_offset {
-                let diff_offset = new_offset - old_offset;
-                panic!(
-                    "Wrong patch {} size from {} to {} in patches. Its {}, but should have been {}",
-                    patch_name, old_offset, new_offset, diff_offset, patch_length
-                );
-            }
-        }
-    }
+    let patch_length =
+        read(reader, "patch length", |r| r.read_u32::<LittleEndian>()).map_err(in_patch)?;
 
-    println!("Finished reading bin file");
+    let old_offset = reader.position();
 
-    BinFile::new(
-        is_patch,
-        unknown_data,
-        version,
-        linked_list,
-        entries_map,
-        patches_map,
-    )
+    let (ftype, bin_type) = read_type(reader, "patch type").map_err(in_patch)?;
+
+    let string = BinData::String(
+        read_string(reader).map_err(|e| in_patch(e.in_path(PathSegment::Field(0x84874D36))))?,
+    );
+
+    let mut embedded = PointerOrEmbedded::new(
+        0xF9100AA9, // patch FNV1a
+        Vec::with_capacity(2),
+    );
+
+    let first_field = BinField::new(
+        0x84874D36, // path FNV1a
+        BinType::String,
+        string,
+    );
+    embedded.items.push(first_field);
+
+    let second_field = BinField::new(
+        0x425ED3CA, // value FNV1a
+        bin_type,
+        read_value_by_type(reader, ftype)
+            .map_err(|e| in_patch(e.in_path(PathSegment::Field(0x425ED3CA))))?,
+    );
+    embedded.items.push(second_field);
+
+    check_size("patch", old_offset, reader.position(), patch_length).map_err(in_patch)?;
+
+    Ok(MapPair::new(
+        BinData::Hash(patch_name),
+        BinData::PointerOrEmbedded(embedded),
+    ))
 }
 
-fn read_value_by_type(reader: &mut Cursor<&Vec<u8>>, ftype: u8) -> BinData {
-    let old_offset = reader.position();
+fn read_fields(
+    reader: &mut Cursor<&[u8]>,
+    field_count: u16,
+    what: &str,
+) -> Result<Vec<BinField>, BinError> {
+    let mut fields = Vec::with_capacity(capacity(reader, field_count as usize, 5));
+
+    for index in 0..field_count as usize {
+        let name = read(reader, &format!("{} field name", what), |r| {
+            r.read_u32::<LittleEndian>()
+        })
+        .map_err(|e| e.in_path(PathSegment::Index(index)))?;
+
+        let in_field = |e: BinError| e.in_path(PathSegment::Field(name));
+
+        let (ftype, bin_type) =
+            read_type(reader, &format!("{} field type", what)).map_err(in_field)?;
 
-    let bin_type = u8_to_type(ftype);
-    if bin_type.is_none() {
-        panic!("Unknown bin type {} starting at: {}.", ftype, old_offset);
+        fields.push(BinField::new(
+            name,
+            bin_type,
+            read_value_by_type(reader, ftype).map_err(in_field)?,
+        ));
     }
-    let bin_type = bin_type.unwrap();
 
-    match bin_type {
+    Ok(fields)
+}
+
+fn read_value_by_type(reader: &mut Cursor<&[u8]>, ftype: u8) -> Result<BinData, BinError> {
+    let bin_type = match u8_to_type(ftype) {
+        Some(bin_type) => bin_type,
+        None => {
+            return Err(BinError::UnknownType {
+                offset: reader.position(),
+                type_byte: ftype,
+            })
+        }
+    };
+
+    let bin_data = match bin_type {
         BinType::None => BinData::None,
-        BinType::Bool => BinData::Bool(reader.read_u8().expect("Could not read Bool") != 0),
-        BinType::SInt8 => BinData::SInt8(reader.read_i8().expect("Could not read SInt8")),
-        BinType::UInt8 => BinData::UInt8(reader.read_u8().expect("Could not read UInt8")),
-        BinType::SInt16 => BinData::SInt16(
-            reader
-                .read_i16::<LittleEndian>()
-                .expect("Could not read SInt16"),
-        ),
-        BinType::UInt16 => BinData::UInt16(
-            reader
-                .read_u16::<LittleEndian>()
-                .expect("Could not read UInt16"),
-        ),
-        BinType::SInt32 => BinData::SInt32(
-            reader
-                .read_i32::<LittleEndian>()
-                .expect("Could not read SInt32"),
-        ),
-        BinType::UInt32 => BinData::UInt32(
-            reader
-                .read_u32::<LittleEndian>()
-                .expect("Could not read UInt32"),
-        ),
-        BinType::SInt64 => BinData::SInt64(
-            reader
-                .read_i64::<LittleEndian>()
-                .expect("Could not read SInt64"),
-        ),
-        BinType::UInt64 => BinData::UInt64(
-            reader
-                .read_u64::<LittleEndian>()
-                .expect("Could not read UInt64"),
-        ),
-        BinType::Float32 => BinData::Float32(
-            reader
-                .read_f32::<LittleEndian>()
-                .expect("Could not read Float32"),
-        ),
-        BinType::Vector2 => {
-            let mut vec2: Vec<f32> = Vec::with_capacity(2);
-            for _ in 0..2 {
-                vec2.push(
-                    reader
-                        .read_f32::<LittleEndian>()
-                        .expect("Could not read Vector2"),
-                );
-            }
-            BinData::Vector2(vec2)
+        BinType::Bool => BinData::Bool(read(reader, "Bool", |r| r.read_u8())? != 0),
+        BinType::SInt8 => BinData::SInt8(read(reader, "SInt8", |r| r.read_i8())?),
+        BinType::UInt8 => BinData::UInt8(read(reader, "UInt8", |r| r.read_u8())?),
+        BinType::SInt16 => {
+            BinData::SInt16(read(reader, "SInt16", |r| r.read_i16::<LittleEndian>())?)
         }
-        BinType::Vector3 => {
-            let mut vec3: Vec<f32> = Vec::with_capacity(3);
-            for _ in 0..3 {
-                vec3.push(
-                    reader
-                        .read_f32::<LittleEndian>()
-                        .expect("Could not read Vector3"),
-                );
-            }
-            BinData::Vector3(vec3)
+        BinType::UInt16 => {
+            BinData::UInt16(read(reader, "UInt16", |r| r.read_u16::<LittleEndian>())?)
         }
-        BinType::Vector4 => {
-            let mut vec4: Vec<f32> = Vec::with_capacity(4);
-            for _ in 0..4 {
-                vec4.push(
-                    reader
-                        .read_f32::<LittleEndian>()
-                        .expect("Could not read Vector4"),
-                );
-            }
-            BinData::Vector4(vec4)
+        BinType::SInt32 => {
+            BinData::SInt32(read(reader, "SInt32", |r| r.read_i32::<LittleEndian>())?)
         }
-        BinType::Matrix4x4 => {
-            let mut mtx44: Vec<f32> = Vec::with_capacity(16);
-            for _ in 0..16 {
-                mtx44.push(
-                    reader
-                        .read_f32::<LittleEndian>()
-                        .expect("Could not read Matrix4x4"),
-                );
-            }
-            BinData::Matrix4x4(mtx44)
+        BinType::UInt32 => {
+            BinData::UInt32(read(reader, "UInt32", |r| r.read_u32::<LittleEndian>())?)
+        }
+        BinType::SInt64 => {
+            BinData::SInt64(read(reader, "SInt64", |r| r.read_i64::<LittleEndian>())?)
         }
+        BinType::UInt64 => {
+            BinData::UInt64(read(reader, "UInt64", |r| r.read_u64::<LittleEndian>())?)
+        }
+        BinType::Float32 => {
+            BinData::Float32(read(reader, "Float32", |r| r.read_f32::<LittleEndian>())?)
+        }
+        BinType::Vector2 => BinData::Vector2(read_floats(reader, 2, "Vector2")?),
+        BinType::Vector3 => BinData::Vector3(read_floats(reader, 3, "Vector3")?),
+        BinType::Vector4 => BinData::Vector4(read_floats(reader, 4, "Vector4")?),
+        BinType::Matrix4x4 => BinData::Matrix4x4(read_floats(reader, 16, "Matrix4x4")?),
         BinType::Rgba => {
-            let mut rgba: Vec<u8> = Vec::with_capacity(4);
-            for _ in 0..4 {
-                rgba.push(reader.read_u8().expect("Could not read Rgba"));
-            }
+            let mut rgba: Vec<u8> = vec![0u8; 4];
+            read(reader, "Rgba", |r| r.read_exact(&mut rgba))?;
             BinData::Rgba(rgba)
         }
-        BinType::String => BinData::String(read_string(reader)),
-        BinType::Hash => BinData::Hash(
-            reader
-                .read_u32::<LittleEndian>()
-                .expect("Could not read Hash"),
-        ),
-        BinType::WadEntryLink => BinData::WadEntryLink(
-            reader
-                .read_u64::<LittleEndian>()
-                .expect("Could not read WadEntryLink"),
-        ),
+        BinType::String => BinData::String(read_string(reader)?),
+        BinType::Hash => BinData::Hash(read(reader, "Hash", |r| r.read_u32::<LittleEndian>())?),
+        BinType::WadEntryLink => BinData::WadEntryLink(read(reader, "WadEntryLink", |r| {
+            r.read_u64::<LittleEndian>()
+        })?),
         BinType::Container | BinType::Struct => {
-            let cs_type = reader
-                .read_u8()
-                .unwrap_or_else(|_| panic!("Could not read {:?} type", bin_type));
-            let cs_length = reader
-                .read_u32::<LittleEndian>()
-                .unwrap_or_else(|_| panic!("Could not read {:?} length", bin_type));
+            let what = format!("{:?}", bin_type);
+
+            let (cs_type, cs_bin_type) = read_type(reader, &format!("{} type", what))?;
+            let cs_length = read(reader, &format!("{} length", what), |r| {
+                r.read_u32::<LittleEndian>()
+            })?;
 
             let old_offset = reader.position();
 
-            let field_count = reader
-                .read_u32::<LittleEndian>()
-                .unwrap_or_else(|_| panic!("Could not read {:?} field count", bin_type));
+            let field_count = read(reader, &format!("{} field count", what), |r| {
+                r.read_u32::<LittleEndian>()
+            })?;
 
             let mut cs = ContainerOrStruct::new(
-                u8_to_type(cs_type)
-                    .unwrap_or_else(|| panic!("Unknown {:?} field type {}", bin_type, cs_type)),
-                Vec::with_capacity(field_count as usize),
+                cs_bin_type,
+                Vec::with_capacity(capacity(reader, field_count as usize, 1)),
             );
 
-            for _ in 0..field_count {
-                cs.items.push(read_value_by_type(reader, cs_type));
-            }
-
-            let new_offset = reader.position();
-            if old_offset + cs_length as u64 != new_offset {
-                let diff_offset = new_offset - old_offset;
-                panic!(
-                    "Wrong {:?} size from {} to {}. Its {}, but should have been {}",
-                    bin_type, old_offset, new_offset, diff_offset, cs_length
+            for index in 0..field_count as usize {
+                cs.items.push(
+                    read_value_by_type(reader, cs_type)
+                        .map_err(|e| e.in_path(PathSegment::Index(index)))?,
                 );
             }
 
+            check_size(&what, old_offset, reader.position(), cs_length)?;
+
             BinData::ContainerOrStruct(cs)
         }
         BinType::Pointer | BinType::Embedded => {
-            let name = reader
-                .read_u32::<LittleEndian>()
-                .unwrap_or_else(|_| panic!("Could not read {:?} name", bin_type));
+            let what = format!("{:?}", bin_type);
+
+            let name = read(reader, &format!("{} name", what), |r| {
+                r.read_u32::<LittleEndian>()
+            })?;
             if name == 0 {
-                return BinData::PointerOrEmbedded(PointerOrEmbedded::new(0, Vec::new()));
+                return Ok(BinData::PointerOrEmbedded(PointerOrEmbedded::new(
+                    0,
+                    Vec::new(),
+                )));
             }
 
-            let pe_length = reader
-                .read_u32::<LittleEndian>()
-                .unwrap_or_else(|_| panic!("Could not read {:?} length", bin_type));
+            let pe_length = read(reader, &format!("{} length", what), |r| {
+                r.read_u32::<LittleEndian>()
+            })?;
 
             let old_offset = reader.position();
 
-            let field_count = reader
-                .read_u16::<LittleEndian>()
-                .unwrap_or_else(|_| panic!("Could not read {:?} field count", bin_type));
-
-            let mut pe = PointerOrEmbedded::new(name, Vec::with_capacity(field_count as usize));
-
-            for _ in 0..field_count {
-                let fname = reader
-                    .read_u32::<LittleEndian>()
-                    .unwrap_or_else(|_| panic!("Could not read {:?} field name", bin_type));
-                let ftype = reader
-                    .read_u8()
-                    .unwrap_or_else(|_| panic!("Could not read {:?} field type", bin_type));
-
-                let field = BinField::new(
-                    fname,
-                    u8_to_type(ftype)
-                        .unwrap_or_else(|| panic!("Unknown {:?} field type {}", bin_type, ftype)),
-                    read_value_by_type(reader, ftype),
-                );
+            let field_count = read(reader, &format!("{} field count", what), |r| {
+                r.read_u16::<LittleEndian>()
+            })?;
 
-                pe.items.push(field);
-            }
+            let pe = PointerOrEmbedded::new(
+                name,
+                read_fields(reader, field_count, &what).map_err(|e| e.in_class(name))?,
+            );
 
-            let new_offset = reader.position();
-            if old_offset + pe_length as u64 != new_offset {
-                let diff_offset = new_offset - old_offset;
-                panic!(
-                    "Wrong {:?} size from {} to {}. Its {}, but should have been {}",
-                    bin_type, old_offset, new_offset, diff_offset, pe_length
-                );
-            }
+            check_size(&what, old_offset, reader.position(), pe_length)
+                .map_err(|e| e.in_class(name))?;
 
             BinData::PointerOrEmbedded(pe)
         }
-        BinType::Link => BinData::Link(
-            reader
-                .read_u32::<LittleEndian>()
-                .expect("Could not read Link"),
-        ),
+        BinType::Link => BinData::Link(read(reader, "Link", |r| r.read_u32::<LittleEndian>())?),
         BinType::Optional => {
-            let op_type = reader.read_u8().expect("Could not read Optional type");
-            let is_some = reader.read_u8().expect("Could not read Optional some");
+            let (op_type, op_bin_type) = read_type(reader, "Optional type")?;
+            let is_some = read(reader, "Optional some", |r| r.read_u8())?;
 
             let data = if is_some != 0 {
-                Some(read_value_by_type(reader, op_type))
+                Some(read_value_by_type(reader, op_type)?)
             } else {
                 None
             };
 
-            let option = Optional::new(
-                u8_to_type(op_type).unwrap_or_else(|| panic!("Unknown Option type {}", op_type)),
-                data,
-            );
-
-            BinData::Optional(option)
+            BinData::Optional(Optional::new(op_bin_type, data))
         }
         BinType::Map => {
-            let key_type = reader.read_u8().expect("Could not read Map key type");
-            let value_type = reader.read_u8().expect("Could not read Map value type");
-            let map_length = reader
-                .read_u32::<LittleEndian>()
-                .expect("Could not read Map length");
+            let (key_type, key_bin_type) = read_type(reader, "Map key type")?;
+            let (value_type, value_bin_type) = read_type(reader, "Map value type")?;
+            let map_length = read(reader, "Map length", |r| r.read_u32::<LittleEndian>())?;
 
             let old_offset = reader.position();
 
-            let field_count = reader
-                .read_u32::<LittleEndian>()
-                .expect("Could not read Map field count");
+            let field_count = read(reader, "Map field count", |r| r.read_u32::<LittleEndian>())?;
 
             let mut map = Map::new(
-                u8_to_type(key_type).unwrap_or_else(|| panic!("Unknown Map key type {}", key_type)),
-                u8_to_type(value_type)
-                    .unwrap_or_else(|| panic!("Unknown Map value type {}", value_type)),
-                Vec::with_capacity(field_count as usize),
+                key_bin_type,
+                value_bin_type,
+                Vec::with_capacity(capacity(reader, field_count as usize, 2)),
             );
 
-            for _ in 0..field_count {
-                map.items.push(MapPair::new(
-                    read_value_by_type(reader, key_type),
-                    read_value_by_type(reader, value_type),
-                ));
+            for index in 0..field_count as usize {
+                let keydata = read_value_by_type(reader, key_type)
+                    .map_err(|e| e.in_path(PathSegment::Index(index)))?;
+                let segment = PathSegment::from_map_key(&keydata, index);
+                let valuedata =
+                    read_value_by_type(reader, value_type).map_err(|e| e.in_path(segment))?;
+                map.items.push(MapPair::new(keydata, valuedata));
             }
 
-            let new_offset = reader.position();
-            if old_offset + map_length as u64 != new_offset {
-                let diff_offset = new_offset - old_offset;
-                panic!(
-                    "Wrong Map size from {} to {}. Its {}, but should have been {}",
-                    old_offset, new_offset, diff_offset, map_length
-                );
-            }
+            check_size("Map", old_offset, reader.position(), map_length)?;
 
             BinData::Map(map)
         }
-        BinType::Flag => BinData::Flag(reader.read_u8().expect("Could not read flag") != 0),
-    }
+        BinType::Flag => BinData::Flag(read(reader, "Flag", |r| r.read_u8())? != 0),
+    };
+
+    Ok(bin_data)
+}
+
+fn read_floats(reader: &mut Cursor<&[u8]>, count: usize, what: &str) -> Result<Vec<f32>, BinError> {
+    let mut floats: Vec<f32> = vec![0f32; count];
+    read(reader, what, |r| {
+        r.read_f32_into::<LittleEndian>(&mut floats)
+    })?;
+    Ok(floats)
 }
 
 fn u8_to_type(ftype: u8) -> Option<BinType> {
@@ -490,3 +465,35 @@ fn u8_to_type(ftype: u8) -> Option<BinType> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn truncated(contents: &[u8]) -> (u64, String) {
+        let error = match read_bin(contents) {
+            Err(BinError::At { source, .. }) => *source,
+            Err(error) => error,
+            Ok(_) => panic!("Expected a truncated error"),
+        };
+        match error {
+            BinError::Truncated { offset, what } => (offset, what),
+            error => panic!("Expected a truncated error but got {}", error),
+        }
+    }
+
+    #[test]
+    fn rejects_huge_counts() {
+        let mut contents = b"PROP".to_vec();
+        contents.extend(&[1, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(truncated(&contents), (12, "entry type".to_string()));
+
+        let mut contents = b"PROP".to_vec();
+        contents.extend(&[1, 0, 0, 0, 1, 0, 0, 0]);
+        contents.extend(&[1, 0, 0, 0]); // entry type
+        contents.extend(&[0, 1, 0, 0, 2, 0, 0, 0, 1, 0]); // length, name, one field
+        contents.extend(&[3, 0, 0, 0, 0x80]); // list field
+        contents.extend(&[3, 8, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]); // of UInt8
+        assert_eq!(truncated(&contents), (40, "UInt8".to_string()));
+    }
+}
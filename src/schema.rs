@@ -0,0 +1,259 @@
+use error::BinError;
+use hash_database::HashDatabase;
+use json_reader::{deserialize_bintype, string_to_hash_u32};
+use json_writer::MyPrettyGenerator;
+use path::PathSegment;
+use structs::*;
+use walk::for_each_value;
+
+use json::{codegen::Generator, JsonValue};
+use std::{collections::HashMap, fmt};
+
+/// Type of a field, with the element type of a container, struct or optional
+/// and the key and value types of a map.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldType {
+    pub btype: BinType,
+    pub keytype: Option<BinType>,
+    pub valuetype: Option<BinType>,
+}
+
+impl FieldType {
+    pub fn of(field: &BinField) -> FieldType {
+        FieldType::of_data(field.btype, &field.data)
+    }
+
+    /// Type of a value stored as `btype`, like a container item or map value.
+    pub fn of_data(btype: BinType, data: &BinData) -> FieldType {
+        let (keytype, valuetype) = match data {
+            BinData::ContainerOrStruct(cs) => (None, Some(cs.btype)),
+            BinData::Optional(optional) => (None, Some(optional.btype)),
+            BinData::Map(map) => (Some(map.keytype), Some(map.valuetype)),
+            _ => (None, None),
+        };
+        FieldType {
+            btype,
+            keytype,
+            valuetype,
+        }
+    }
+
+    fn to_json(self) -> JsonValue {
+        let mut object = JsonValue::new_object();
+        object.insert("type", format!("{:?}", self.btype)).unwrap();
+        if let Some(keytype) = self.keytype {
+            object.insert("keytype", format!("{:?}", keytype)).unwrap();
+        }
+        if let Some(valuetype) = self.valuetype {
+            object
+                .insert("valuetype", format!("{:?}", valuetype))
+                .unwrap();
+        }
+        object
+    }
+
+    fn from_json(object: &JsonValue) -> Result<FieldType, BinError> {
+        let optional = |key: &str| match &object[key] {
+            JsonValue::Null => Ok(None),
+            value => deserialize_bintype(value).map(Some),
+        };
+        Ok(FieldType {
+            btype: deserialize_bintype(&object["type"])?,
+            keytype: optional("keytype")?,
+            valuetype: optional("valuetype")?,
+        })
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.keytype, self.valuetype) {
+            (Some(keytype), Some(valuetype)) => {
+                write!(f, "{:?}<{:?}, {:?}>", self.btype, keytype, valuetype)
+            }
+            (None, Some(valuetype)) => write!(f, "{:?}<{:?}>", self.btype, valuetype),
+            _ => write!(f, "{:?}", self.btype),
+        }
+    }
+}
+
+/// Every type a field was seen with and how often, most seen first.
+#[derive(Debug, Clone, Default)]
+pub struct FieldSchema {
+    pub types: Vec<(FieldType, usize)>,
+}
+
+impl FieldSchema {
+    /// The type the field was seen with most.
+    pub fn field_type(&self) -> FieldType {
+        self.types[0].0
+    }
+
+    /// How many instances of the class had the field.
+    pub fn count(&self) -> usize {
+        self.types.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Whether the field was seen with more than one type.
+    pub fn is_conflicting(&self) -> bool {
+        self.types.len() > 1
+    }
+
+    fn record(&mut self, field_type: FieldType) {
+        match self
+            .types
+            .iter_mut()
+            .find(|(known, _)| *known == field_type)
+        {
+            Some((_, count)) => *count += 1,
+            None => self.types.push((field_type, 1)),
+        }
+        self.types.sort_by(|(_, a), (_, b)| b.cmp(a));
+    }
+}
+
+/// Fields of a class, and how many instances of it were seen.
+#[derive(Debug, Clone, Default)]
+pub struct ClassSchema {
+    pub count: usize,
+    pub fields: HashMap<u32, FieldSchema>,
+}
+
+/// Fields and field types of every class seen in a set of bins.
+#[derive(Debug, Default)]
+pub struct Schema {
+    pub classes: HashMap<u32, ClassSchema>,
+}
+
+impl Schema {
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    pub fn get_field(&self, class: u32, field: u32) -> Option<&FieldSchema> {
+        self.classes.get(&class)?.fields.get(&field)
+    }
+
+    /// Records the fields of every Pointer and Embedded of `bin_file`.
+    pub fn record(&mut self, bin_file: &BinFile) {
+        for_each_value(bin_file, |_, bin_data| {
+            if let BinData::PointerOrEmbedded(pe) = bin_data {
+                if pe.name == 0 {
+                    return;
+                }
+                let class = self.classes.entry(pe.name).or_default();
+                class.count += 1;
+                for field in &pe.items {
+                    class
+                        .fields
+                        .entry(field.name)
+                        .or_default()
+                        .record(FieldType::of(field));
+                }
+            }
+        });
+    }
+
+    /// Class, field and schema of every field seen with more than one type.
+    pub fn conflicts(&self) -> Vec<(u32, u32, &FieldSchema)> {
+        let mut conflicts = Vec::new();
+        for (class_name, class) in &self.classes {
+            for (field_name, field) in &class.fields {
+                if field.is_conflicting() {
+                    conflicts.push((*class_name, *field_name, field));
+                }
+            }
+        }
+        conflicts.sort_by_key(|(class, field, _)| (*class, *field));
+        conflicts
+    }
+
+    /// Writes the schema as JSON, with classes and fields sorted by name.
+    ///
+    /// Every field lists its most seen type and count, and the other types
+    /// it was seen with under `conflicts`.
+    pub fn to_json(&self, hash_database: &HashDatabase) -> String {
+        let mut class_names: Vec<(String, &ClassSchema)> = self
+            .classes
+            .iter()
+            .map(|(name, class)| (hash_database.type_to_string(*name), class))
+            .collect();
+        class_names.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut classes = JsonValue::new_object();
+        for (class_name, class) in class_names {
+            let mut field_names: Vec<(String, &FieldSchema)> = class
+                .fields
+                .iter()
+                .map(|(name, field)| (hash_database.field_to_string(*name), field))
+                .collect();
+            field_names.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            let mut fields = JsonValue::new_object();
+            for (field_name, field) in field_names {
+                let mut object = field.field_type().to_json();
+                object.insert("count", field.types[0].1).unwrap();
+                if field.is_conflicting() {
+                    let mut conflicts = JsonValue::new_array();
+                    for (field_type, count) in &field.types[1..] {
+                        let mut conflict = field_type.to_json();
+                        conflict.insert("count", *count).unwrap();
+                        conflicts.push(conflict).unwrap();
+                    }
+                    object.insert("conflicts", conflicts).unwrap();
+                }
+                fields.insert(&field_name, object).unwrap();
+            }
+
+            let mut object = JsonValue::new_object();
+            object.insert("count", class.count).unwrap();
+            object.insert("fields", fields).unwrap();
+            classes.insert(&class_name, object).unwrap();
+        }
+
+        let mut root = JsonValue::new_object();
+        root.insert("Classes", classes).unwrap();
+
+        let mut gen = MyPrettyGenerator::new();
+        gen.write_json(&root).expect("Can't write json");
+        gen.consume()
+    }
+
+    /// Reads a schema written by `to_json`.
+    pub fn from_json(contents: &str) -> Result<Schema, BinError> {
+        let root = json::parse(contents)?;
+        if !root["Classes"].is_object() {
+            return Err(BinError::json("Expected Classes as Object"));
+        }
+
+        let mut schema = Schema::new();
+        for (class_name, object) in root["Classes"].entries() {
+            let name = string_to_hash_u32(class_name)?;
+            let in_class = |e: BinError| e.in_class(name);
+
+            let mut class = ClassSchema {
+                count: object["count"].as_usize().unwrap_or(0),
+                fields: HashMap::new(),
+            };
+            for (field_name, field) in object["fields"].entries() {
+                let field_name = string_to_hash_u32(field_name).map_err(in_class)?;
+                let in_field =
+                    |e: BinError| e.in_path(PathSegment::Field(field_name)).in_class(name);
+
+                let mut types = vec![(
+                    FieldType::from_json(field).map_err(in_field)?,
+                    field["count"].as_usize().unwrap_or(0),
+                )];
+                for conflict in field["conflicts"].members() {
+                    types.push((
+                        FieldType::from_json(conflict).map_err(in_field)?,
+                        conflict["count"].as_usize().unwrap_or(0),
+                    ));
+                }
+                class.fields.insert(field_name, FieldSchema { types });
+            }
+            schema.classes.insert(name, class);
+        }
+        Ok(schema)
+    }
+}
@@ -0,0 +1,609 @@
+use edit::{look_through_mut, resolve_mut, Target};
+use error::BinError;
+use hash_database::HashDatabase;
+use hashes::{fnv1a, xxhash};
+use json_reader::{string_to_hash_u32, string_to_hash_u64};
+use path::{BinPath, PathSegment};
+use query::{key_matches, look_through, Query};
+use schema::FieldType;
+use structs::*;
+use text_writer::{type_name, typed_value_to_text};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, AST, FLOAT, INT};
+
+use std::{cell::RefCell, rc::Rc};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Resolves a hash to its name in one of the dictionaries.
+type Lookup = fn(&HashDatabase, INT) -> String;
+
+/// The bin a script runs on, and whether the script changed it.
+struct ScriptState {
+    bin_file: BinFile,
+    changed: bool,
+}
+
+/// `bin` in scripts: the entries, linked files and version of the bin.
+#[derive(Clone)]
+struct ScriptBin {
+    state: Rc<RefCell<ScriptState>>,
+    hash_database: Rc<HashDatabase>,
+}
+
+/// A value of the bin by its path, read and written through the shared state
+/// so that changes made through one node are seen by all others.
+#[derive(Clone)]
+struct Node {
+    state: Rc<RefCell<ScriptState>>,
+    hash_database: Rc<HashDatabase>,
+    path: BinPath,
+}
+
+/// A compiled Rhai script with the bin API registered.
+///
+/// Scripts see the bin as `bin` and its file name as `file`:
+/// `bin.entries`, `bin.entry(name)` and `bin.query(query)` give nodes, which
+/// have `path`, `bin_type`, `class`, `value`, `len`, `children()` and
+/// `has(key)`, and are indexed by field name, map key or list index.
+/// Setting `value` or an index converts the new value to the existing type.
+/// `fnv1a`, `xxh64`, `hash_name`, `field_name`, `type_name` and `game_name`
+/// hash names and look hashes up in the dictionaries.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    hash_database: Rc<HashDatabase>,
+}
+
+impl Script {
+    pub fn compile(source: &str, hash_database: Rc<HashDatabase>) -> Result<Script, BinError> {
+        let mut engine = Engine::new();
+        register_api(&mut engine, &hash_database);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| BinError::script(e.to_string()))?;
+        Ok(Script {
+            engine,
+            ast,
+            hash_database,
+        })
+    }
+
+    /// Runs the script on `bin_file`, giving whether it changed any value.
+    pub fn run(&self, bin_file: &mut BinFile, file_name: &str) -> Result<bool, BinError> {
+        let state = Rc::new(RefCell::new(ScriptState {
+            bin_file: std::mem::replace(bin_file, empty_bin_file()),
+            changed: false,
+        }));
+
+        let mut scope = Scope::new();
+        scope.push(
+            "bin",
+            ScriptBin {
+                state: state.clone(),
+                hash_database: self.hash_database.clone(),
+            },
+        );
+        scope.push_constant("file", file_name.to_string());
+        let result = self.engine.run_ast_with_scope(&mut scope, &self.ast);
+
+        let state = state.replace(ScriptState {
+            bin_file: empty_bin_file(),
+            changed: false,
+        });
+        *bin_file = state.bin_file;
+        result.map_err(|e| BinError::script(e.to_string()))?;
+        Ok(state.changed)
+    }
+}
+
+fn empty_bin_file() -> BinFile {
+    let entries = Map::new(BinType::Hash, BinType::Embedded, Vec::new());
+    BinFile::new(false, None, 0, Vec::new(), entries, None)
+}
+
+fn register_api(engine: &mut Engine, hash_database: &Rc<HashDatabase>) {
+    engine.register_type_with_name::<ScriptBin>("Bin");
+    engine.register_get("entries", |bin: &mut ScriptBin| {
+        let state = bin.state.borrow();
+        let mut path = BinPath::new();
+        path.push(PathSegment::Root("Entries"));
+        state
+            .bin_file
+            .entries
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, pair)| {
+                let path = path.with(PathSegment::from_map_key(&pair.keydata, index));
+                Dynamic::from(bin.node(path))
+            })
+            .collect::<Array>()
+    });
+    engine.register_fn(
+        "entry",
+        |bin: &mut ScriptBin, name: &str| -> ScriptResult<Node> {
+            let state = bin.state.borrow();
+            let index = state
+                .bin_file
+                .entries
+                .items
+                .iter()
+                .position(|pair| key_matches(&pair.keydata, name))
+                .ok_or_else(|| format!("No entry {}", name))?;
+            let mut path = BinPath::new();
+            path.push(PathSegment::Root("Entries"));
+            path.push(PathSegment::from_map_key(
+                &state.bin_file.entries.items[index].keydata,
+                index,
+            ));
+            Ok(bin.node(path))
+        },
+    );
+    engine.register_fn(
+        "query",
+        |bin: &mut ScriptBin, query: &str| -> ScriptResult<Array> {
+            let query = Query::parse(query).map_err(|e| e.to_string())?;
+            let state = bin.state.borrow();
+            Ok(query
+                .run(&state.bin_file)
+                .into_iter()
+                .map(|found| Dynamic::from(bin.node(found.path)))
+                .collect())
+        },
+    );
+    engine.register_get_set(
+        "version",
+        |bin: &mut ScriptBin| bin.state.borrow().bin_file.version as INT,
+        |bin: &mut ScriptBin, version: INT| {
+            let mut state = bin.state.borrow_mut();
+            state.bin_file.version = version as u32;
+            state.changed = true;
+        },
+    );
+    engine.register_get("linked", |bin: &mut ScriptBin| {
+        let state = bin.state.borrow();
+        state
+            .bin_file
+            .linked_list
+            .iter()
+            .map(|linked| Dynamic::from(linked.clone()))
+            .collect::<Array>()
+    });
+
+    engine.register_type_with_name::<Node>("Node");
+    engine.register_get("path", |node: &mut Node| {
+        node.path.to_string_with(&node.hash_database)
+    });
+    engine.register_get("bin_type", |node: &mut Node| -> ScriptResult<String> {
+        node.with_value(|btype, _| type_name(&btype).to_string())
+    });
+    engine.register_get("class", |node: &mut Node| -> ScriptResult<String> {
+        let hash_database = node.hash_database.clone();
+        node.with_value(|_, data| match look_through_mut(data) {
+            BinData::PointerOrEmbedded(pe) if pe.name != 0 => hash_database.type_to_string(pe.name),
+            _ => String::new(),
+        })
+    });
+    engine.register_get("len", |node: &mut Node| -> ScriptResult<INT> {
+        node.with_value(|_, data| match look_through_mut(data) {
+            BinData::PointerOrEmbedded(pe) => pe.items.len() as INT,
+            BinData::ContainerOrStruct(cs) => cs.items.len() as INT,
+            BinData::Map(map) => map.items.len() as INT,
+            _ => 0,
+        })
+    });
+    engine.register_get_set(
+        "value",
+        |node: &mut Node| -> ScriptResult<Dynamic> {
+            let hash_database = node.hash_database.clone();
+            node.with_value(|_, data| to_dynamic(data, &hash_database))
+        },
+        |node: &mut Node, value: Dynamic| node.set(value),
+    );
+    engine.register_fn("children", |node: &mut Node| -> ScriptResult<Array> {
+        Ok(node
+            .child_segments()?
+            .into_iter()
+            .map(|segment| Dynamic::from(node.child(segment)))
+            .collect())
+    });
+    engine.register_fn("has", |node: &mut Node, key: &str| {
+        node.find_child(key).is_ok()
+    });
+    engine.register_indexer_get(|node: &mut Node, key: &str| -> ScriptResult<Node> {
+        let segment = node.find_child(key)?;
+        Ok(node.child(segment))
+    });
+    engine.register_indexer_get(|node: &mut Node, index: INT| -> ScriptResult<Node> {
+        Ok(node.child(PathSegment::Index(index as usize)))
+    });
+    engine.register_indexer_set(
+        |node: &mut Node, key: &str, value: Dynamic| -> ScriptResult<()> {
+            let segment = node.find_child(key)?;
+            node.child(segment).set(value)
+        },
+    );
+    engine.register_indexer_set(
+        |node: &mut Node, index: INT, value: Dynamic| -> ScriptResult<()> {
+            node.child(PathSegment::Index(index as usize)).set(value)
+        },
+    );
+    engine.register_fn("to_string", |node: &mut Node| -> ScriptResult<String> {
+        let hash_database = node.hash_database.clone();
+        node.with_value(|btype, data| typed_value_to_text(&btype, data, &hash_database))
+    });
+
+    engine.register_fn("fnv1a", |name: &str| fnv1a(name) as INT);
+    engine.register_fn("xxh64", |path: &str| xxhash(&path.to_lowercase()) as INT);
+    let lookups: [(&str, Lookup); 4] = [
+        ("hash_name", |hdb, hash| hdb.hash_to_string(hash as u32)),
+        ("field_name", |hdb, hash| hdb.field_to_string(hash as u32)),
+        ("type_name", |hdb, hash| hdb.type_to_string(hash as u32)),
+        ("game_name", |hdb, hash| hdb.game_to_string(hash as u64)),
+    ];
+    for (name, lookup) in lookups.iter() {
+        let (hash_database, lookup) = (hash_database.clone(), *lookup);
+        engine.register_fn(*name, move |hash: INT| lookup(&hash_database, hash));
+    }
+}
+
+impl ScriptBin {
+    fn node(&self, path: BinPath) -> Node {
+        Node {
+            state: self.state.clone(),
+            hash_database: self.hash_database.clone(),
+            path,
+        }
+    }
+}
+
+impl Node {
+    fn child(&self, segment: PathSegment) -> Node {
+        Node {
+            state: self.state.clone(),
+            hash_database: self.hash_database.clone(),
+            path: self.path.with(segment),
+        }
+    }
+
+    fn missing(&self) -> Box<EvalAltResult> {
+        format!(
+            "No value at {}",
+            self.path.to_string_with(&self.hash_database)
+        )
+        .into()
+    }
+
+    fn with_value<R, F>(&self, f: F) -> ScriptResult<R>
+    where
+        F: FnOnce(BinType, &mut BinData) -> R,
+    {
+        let mut state = self.state.borrow_mut();
+        match resolve_mut(&mut state.bin_file, &self.path) {
+            Some(Target::Value(btype, data)) => Ok(f(btype, data)),
+            _ => Err(self.missing()),
+        }
+    }
+
+    /// Sets the value, or copies the value of another node of the same type.
+    fn set(&self, value: Dynamic) -> ScriptResult<()> {
+        // Rhai writes indexed nodes back after calling a method on them.
+        let copied = match value.clone().try_cast::<Node>() {
+            Some(node) if node.path == self.path => return Ok(()),
+            Some(node) => Some(node.with_value(|btype, data| (btype, data.clone()))?),
+            None => None,
+        };
+
+        let mut state = self.state.borrow_mut();
+        let (btype, data) = match resolve_mut(&mut state.bin_file, &self.path) {
+            Some(Target::Value(btype, data)) => (btype, data),
+            _ => return Err(self.missing()),
+        };
+        let path = || self.path.to_string_with(&self.hash_database);
+        let (data, new_data) = match copied {
+            Some((other_type, other)) => {
+                let (expected, found) = (
+                    FieldType::of_data(btype, data),
+                    FieldType::of_data(other_type, &other),
+                );
+                if expected != found {
+                    return Err(
+                        format!("Cannot set {} to {} at {}", expected, found, path()).into(),
+                    );
+                }
+                (data, other)
+            }
+            None => {
+                let data = look_through_mut(data);
+                let new_data = from_dynamic(data, value).map_err(|message| {
+                    format!("{} at {}, which is {}", message, path(), type_name(&btype))
+                })?;
+                (data, new_data)
+            }
+        };
+        if *data != new_data {
+            *data = new_data;
+            state.changed = true;
+        }
+        Ok(())
+    }
+
+    fn child_segments(&self) -> ScriptResult<Vec<PathSegment>> {
+        self.with_value(|_, data| match look_through_mut(data) {
+            BinData::PointerOrEmbedded(pe) => pe
+                .items
+                .iter()
+                .map(|field| PathSegment::Field(field.name))
+                .collect(),
+            BinData::ContainerOrStruct(cs) => (0..cs.items.len()).map(PathSegment::Index).collect(),
+            BinData::Map(map) => map
+                .items
+                .iter()
+                .enumerate()
+                .map(|(index, pair)| PathSegment::from_map_key(&pair.keydata, index))
+                .collect(),
+            _ => Vec::new(),
+        })
+    }
+
+    /// The segment of a field by name, or of a map pair by key.
+    fn find_child(&self, key: &str) -> ScriptResult<PathSegment> {
+        let segment = self.with_value(|_, data| match look_through_mut(data) {
+            BinData::PointerOrEmbedded(pe) => {
+                let name = string_to_hash_u32(key).ok()?;
+                pe.items
+                    .iter()
+                    .find(|field| field.name == name)
+                    .map(|field| PathSegment::Field(field.name))
+            }
+            BinData::Map(map) => map
+                .items
+                .iter()
+                .enumerate()
+                .find(|(_, pair)| key_matches(&pair.keydata, key))
+                .map(|(index, pair)| PathSegment::from_map_key(&pair.keydata, index)),
+            _ => None,
+        })?;
+        segment.ok_or_else(|| {
+            format!(
+                "No {} in {}",
+                key,
+                self.path.to_string_with(&self.hash_database)
+            )
+            .into()
+        })
+    }
+}
+
+/// Numbers, booleans and strings as themselves, hashes as names, vectors and
+/// colors as arrays, and other values as `()`.
+fn to_dynamic(data: &BinData, hash_database: &HashDatabase) -> Dynamic {
+    let floats = |floats: &[f32]| {
+        floats
+            .iter()
+            .map(|float| Dynamic::from(*float as FLOAT))
+            .collect::<Array>()
+    };
+    match look_through(data) {
+        BinData::Bool(value) | BinData::Flag(value) => Dynamic::from(*value),
+        BinData::SInt8(value) => Dynamic::from(*value as INT),
+        BinData::UInt8(value) => Dynamic::from(*value as INT),
+        BinData::SInt16(value) => Dynamic::from(*value as INT),
+        BinData::UInt16(value) => Dynamic::from(*value as INT),
+        BinData::SInt32(value) => Dynamic::from(*value as INT),
+        BinData::UInt32(value) => Dynamic::from(*value as INT),
+        BinData::SInt64(value) => Dynamic::from(*value as INT),
+        BinData::UInt64(value) => Dynamic::from(*value as INT),
+        BinData::Float32(value) => Dynamic::from(*value as FLOAT),
+        BinData::Vector2(values)
+        | BinData::Vector3(values)
+        | BinData::Vector4(values)
+        | BinData::Matrix4x4(values) => Dynamic::from(floats(values)),
+        BinData::Rgba(values) => Dynamic::from(
+            values
+                .iter()
+                .map(|value| Dynamic::from(*value as INT))
+                .collect::<Array>(),
+        ),
+        BinData::String(value) => Dynamic::from(value.clone()),
+        BinData::Hash(hash) | BinData::Link(hash) => {
+            Dynamic::from(hash_database.hash_to_string(*hash))
+        }
+        BinData::WadEntryLink(hash) => Dynamic::from(hash_database.game_to_string(*hash)),
+        _ => Dynamic::UNIT,
+    }
+}
+
+/// Converts `value` to the type of `existing`, accepting integers for floats
+/// and names or integers for hashes.
+fn from_dynamic(existing: &BinData, value: Dynamic) -> Result<BinData, String> {
+    let wrong = |value: &Dynamic| format!("Cannot set {} {}", value.type_name(), value);
+    let int = |value: &Dynamic| value.as_int().map_err(|_| wrong(value));
+    let float = |value: &Dynamic| {
+        value
+            .as_float()
+            .or_else(|_| value.as_int().map(|int| int as FLOAT))
+            .map_err(|_| wrong(value))
+    };
+    let floats = |value: &Dynamic, count: usize| -> Result<Vec<f32>, String> {
+        let items = value
+            .clone()
+            .try_cast::<Array>()
+            .ok_or_else(|| wrong(value))?;
+        if items.len() != count {
+            return Err(format!(
+                "Expected {} numbers but found {}",
+                count,
+                items.len()
+            ));
+        }
+        items
+            .iter()
+            .map(|item| float(item).map(|float| float as f32))
+            .collect()
+    };
+    let ranged = |value: &Dynamic, min: INT, max: INT| {
+        let int = int(value)?;
+        if int < min || int > max {
+            return Err(format!("{} is out of range", int));
+        }
+        Ok(int)
+    };
+    let hash_u32 = |value: &Dynamic| -> Result<u32, String> {
+        match value.clone().into_string() {
+            Ok(name) => string_to_hash_u32(&name).map_err(|e| e.to_string()),
+            Err(_) => Ok(ranged(value, 0, u32::MAX as INT)? as u32),
+        }
+    };
+
+    let data = match existing {
+        BinData::Bool(_) => BinData::Bool(value.as_bool().map_err(|_| wrong(&value))?),
+        BinData::Flag(_) => BinData::Flag(value.as_bool().map_err(|_| wrong(&value))?),
+        BinData::SInt8(_) => BinData::SInt8(ranged(&value, i8::MIN as INT, i8::MAX as INT)? as i8),
+        BinData::UInt8(_) => BinData::UInt8(ranged(&value, 0, u8::MAX as INT)? as u8),
+        BinData::SInt16(_) => {
+            BinData::SInt16(ranged(&value, i16::MIN as INT, i16::MAX as INT)? as i16)
+        }
+        BinData::UInt16(_) => BinData::UInt16(ranged(&value, 0, u16::MAX as INT)? as u16),
+        BinData::SInt32(_) => {
+            BinData::SInt32(ranged(&value, i32::MIN as INT, i32::MAX as INT)? as i32)
+        }
+        BinData::UInt32(_) => BinData::UInt32(ranged(&value, 0, u32::MAX as INT)? as u32),
+        BinData::SInt64(_) => BinData::SInt64(int(&value)?),
+        BinData::UInt64(_) => BinData::UInt64(ranged(&value, 0, INT::MAX)? as u64),
+        BinData::Float32(_) => BinData::Float32(float(&value)? as f32),
+        BinData::Vector2(_) => BinData::Vector2(floats(&value, 2)?),
+        BinData::Vector3(_) => BinData::Vector3(floats(&value, 3)?),
+        BinData::Vector4(_) => BinData::Vector4(floats(&value, 4)?),
+        BinData::Matrix4x4(_) => BinData::Matrix4x4(floats(&value, 16)?),
+        BinData::Rgba(_) => {
+            let items = value
+                .clone()
+                .try_cast::<Array>()
+                .ok_or_else(|| wrong(&value))?;
+            if items.len() != 4 {
+                return Err(format!("Expected 4 numbers but found {}", items.len()));
+            }
+            let rgba: Result<Vec<u8>, String> = items
+                .iter()
+                .map(|item| ranged(item, 0, u8::MAX as INT).map(|int| int as u8))
+                .collect();
+            BinData::Rgba(rgba?)
+        }
+        BinData::String(_) => {
+            BinData::String(value.clone().into_string().map_err(|_| wrong(&value))?)
+        }
+        BinData::Hash(_) => BinData::Hash(hash_u32(&value)?),
+        BinData::Link(_) => BinData::Link(hash_u32(&value)?),
+        BinData::WadEntryLink(_) => BinData::WadEntryLink(match value.clone().into_string() {
+            Ok(path) => string_to_hash_u64(&path.to_lowercase()).map_err(|e| e.to_string())?,
+            Err(_) => int(&value)? as u64,
+        }),
+        _ => return Err("Cannot set lists, maps, embeds or pointers, set their items".to_string()),
+    };
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use text_reader::convert_text_to_bin;
+
+    fn bin_file() -> BinFile {
+        convert_text_to_bin(
+            "entries: map[hash,embed] = {
+                \"Skin0\" = SkinCharacterDataProperties {
+                    scale: u8 = 1
+                    size: u64 = 2
+                    target: hash = 0x00000000
+                }
+            }",
+        )
+        .unwrap()
+    }
+
+    fn run(bin_file: &mut BinFile, source: &str) -> Result<bool, BinError> {
+        Script::compile(source, Rc::new(HashDatabase::new()))?.run(bin_file, "test.bin")
+    }
+
+    fn field(bin_file: &BinFile, name: &str) -> BinData {
+        let query = Query::parse(&format!("Entries[*].{}", name)).unwrap();
+        query.run(bin_file)[0].data.clone()
+    }
+
+    fn script_error(result: Result<bool, BinError>) -> String {
+        match result {
+            Err(BinError::Script { message }) => message,
+            result => panic!("Expected a script error but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn checks_ranges() {
+        let mut bin_file = bin_file();
+        let error = script_error(run(
+            &mut bin_file,
+            "let skin = bin.entry(\"Skin0\"); skin[\"scale\"] = 300;",
+        ));
+        assert!(error.contains("300 is out of range"), "{}", error);
+        let error = script_error(run(
+            &mut bin_file,
+            "let skin = bin.entry(\"Skin0\"); skin[\"size\"] = -1;",
+        ));
+        assert!(error.contains("-1 is out of range"), "{}", error);
+        let error = script_error(run(
+            &mut bin_file,
+            "let skin = bin.entry(\"Skin0\"); skin[\"scale\"] = \"big\";",
+        ));
+        assert!(error.contains("Cannot set"), "{}", error);
+        assert_eq!(field(&bin_file, "scale"), BinData::UInt8(1));
+        assert_eq!(field(&bin_file, "size"), BinData::UInt64(2));
+    }
+
+    #[test]
+    fn converts_names_to_hashes() {
+        let mut bin_file = bin_file();
+        run(
+            &mut bin_file,
+            "let skin = bin.entry(\"Skin0\"); skin[\"target\"] = \"Skin1\";",
+        )
+        .unwrap();
+        assert_eq!(field(&bin_file, "target"), BinData::Hash(fnv1a("Skin1")));
+        run(
+            &mut bin_file,
+            "let skin = bin.entry(\"Skin0\"); skin[\"target\"] = \"0x0000000a\";",
+        )
+        .unwrap();
+        assert_eq!(field(&bin_file, "target"), BinData::Hash(0xA));
+        run(
+            &mut bin_file,
+            "let skin = bin.entry(\"Skin0\"); skin[\"target\"] = 11;",
+        )
+        .unwrap();
+        assert_eq!(field(&bin_file, "target"), BinData::Hash(11));
+    }
+
+    #[test]
+    fn tracks_changes() {
+        let mut bin_file = bin_file();
+        assert!(!run(
+            &mut bin_file,
+            "let scale = bin.entry(\"Skin0\")[\"scale\"].value;"
+        )
+        .unwrap());
+        assert!(!run(
+            &mut bin_file,
+            "let skin = bin.entry(\"Skin0\"); skin[\"scale\"] = 1;"
+        )
+        .unwrap());
+        assert!(run(
+            &mut bin_file,
+            "let skin = bin.entry(\"Skin0\"); skin[\"scale\"] = 2;"
+        )
+        .unwrap());
+        assert_eq!(field(&bin_file, "scale"), BinData::UInt8(2));
+        assert!(run(&mut bin_file, "bin.version = 3;").unwrap());
+        assert_eq!(bin_file.version, 3);
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BinFile {
     pub is_patch: bool,
     pub unknown: Option<u64>,
@@ -8,7 +8,7 @@ pub struct BinFile {
     pub patches: Option<Map>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum BinType {
     None = 0,
@@ -40,7 +40,7 @@ pub enum BinType {
     Flag = 26,      // 0x87
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BinData {
     None,
     Bool(bool),
@@ -69,38 +69,38 @@ pub enum BinData {
     Flag(bool),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BinField {
     pub name: u32,
     pub btype: BinType,
     pub data: Box<BinData>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ContainerOrStruct {
     pub btype: BinType,
     pub items: Vec<BinData>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PointerOrEmbedded {
     pub name: u32,
     pub items: Vec<BinField>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Optional {
     pub btype: BinType,
     pub data: Option<Box<BinData>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MapPair {
     pub keydata: Box<BinData>,
     pub valuedata: Box<BinData>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Map {
     pub keytype: BinType,
     pub valuetype: BinType,
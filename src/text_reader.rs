@@ -0,0 +1,733 @@
+use error::BinError;
+use hashes;
+use path::PathSegment;
+use structs::*;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Punct(char),
+    End,
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(contents: &'a str) -> Lexer<'a> {
+        Lexer {
+            chars: contents.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+            self.column = 1;
+        } else if c.is_some() {
+            self.column += 1;
+        }
+        c
+    }
+
+    fn error(&self, message: String) -> BinError {
+        BinError::Text {
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+
+    /// Returns the next token and the line and column it starts at.
+    fn next_token(&mut self) -> Result<(Token, usize, usize), BinError> {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('#') => {
+                    while let Some(c) = self.bump() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        let (line, column) = (self.line, self.column);
+
+        let token = match self.chars.peek().cloned() {
+            None => Token::End,
+            Some(c @ ':') | Some(c @ '=') | Some(c @ '{') | Some(c @ '}') | Some(c @ '[')
+            | Some(c @ ']') | Some(c @ ',') => {
+                self.bump();
+                Token::Punct(c)
+            }
+            Some(quote @ '"') | Some(quote @ '\'') => {
+                self.bump();
+                let mut string = String::new();
+                loop {
+                    match self.bump() {
+                        None => return Err(self.error("Unterminated string".to_string())),
+                        Some(c) if c == quote => break,
+                        Some('\\') => match self.bump() {
+                            Some('n') => string.push('\n'),
+                            Some('r') => string.push('\r'),
+                            Some('t') => string.push('\t'),
+                            Some('x') => {
+                                let mut hex = String::new();
+                                for _ in 0..2 {
+                                    if let Some(c) = self.bump() {
+                                        hex.push(c);
+                                    }
+                                }
+                                let value = u8::from_str_radix(&hex, 16).map_err(|_| {
+                                    self.error(format!("Invalid escape \\x{}", hex))
+                                })?;
+                                string.push(value as char);
+                            }
+                            Some(c) => string.push(c),
+                            None => return Err(self.error("Unterminated string".to_string())),
+                        },
+                        Some(c) => string.push(c),
+                    }
+                }
+                Token::String(string)
+            }
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_whitespace() || ":={}[],\"'#".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    self.bump();
+                }
+                Token::Word(word)
+            }
+        };
+
+        Ok((token, line, column))
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(contents: &'a str) -> Result<Parser<'a>, BinError> {
+        let mut lexer = Lexer::new(contents);
+        let (token, line, column) = lexer.next_token()?;
+        Ok(Parser {
+            lexer,
+            token,
+            line,
+            column,
+        })
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> BinError {
+        BinError::Text {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+
+    fn advance(&mut self) -> Result<Token, BinError> {
+        let (token, line, column) = self.lexer.next_token()?;
+        self.line = line;
+        self.column = column;
+        Ok(std::mem::replace(&mut self.token, token))
+    }
+
+    fn describe(&self) -> String {
+        match &self.token {
+            Token::Word(word) => format!("'{}'", word),
+            Token::String(string) => format!("string {:?}", string),
+            Token::Punct(c) => format!("'{}'", c),
+            Token::End => "end of file".to_string(),
+        }
+    }
+
+    fn expect_punct(&mut self, punct: char) -> Result<(), BinError> {
+        if self.token == Token::Punct(punct) {
+            self.advance()?;
+            Ok(())
+        } else {
+            Err(self.error(format!(
+                "Expected '{}' but found {}",
+                punct,
+                self.describe()
+            )))
+        }
+    }
+
+    fn eat_punct(&mut self, punct: char) -> Result<bool, BinError> {
+        if self.token == Token::Punct(punct) {
+            self.advance()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), BinError> {
+        match self.token {
+            Token::End => Ok(()),
+            _ => Err(self.error(format!("Unexpected {}", self.describe()))),
+        }
+    }
+
+    fn expect_word(&mut self) -> Result<String, BinError> {
+        match self.token {
+            Token::Word(_) => match self.advance()? {
+                Token::Word(word) => Ok(word),
+                _ => unreachable!(),
+            },
+            _ => Err(self.error(format!("Expected a word but found {}", self.describe()))),
+        }
+    }
+
+    /// A field or class name, either plain or as `0x` hex.
+    fn parse_name(&mut self) -> Result<u32, BinError> {
+        let word = match self.token {
+            Token::String(_) => match self.advance()? {
+                Token::String(string) => string,
+                _ => unreachable!(),
+            },
+            _ => self.expect_word()?,
+        };
+        match parse_hex_u64(&word) {
+            Some(hash) if hash <= u32::MAX as u64 => Ok(hash as u32),
+            Some(_) => Err(self.error(format!("Hash {} does not fit in 32 bits", word))),
+            None => Ok(hashes::fnv1a(&word)),
+        }
+    }
+
+    fn parse_hash_u32(&mut self) -> Result<u32, BinError> {
+        match &self.token {
+            Token::String(string) => {
+                let hash = hashes::fnv1a(string);
+                self.advance()?;
+                Ok(hash)
+            }
+            Token::Word(word) => match parse_hex_u64(word) {
+                Some(hash) if hash <= u32::MAX as u64 => {
+                    self.advance()?;
+                    Ok(hash as u32)
+                }
+                _ => Err(self.error(format!("Expected hash but found {}", self.describe()))),
+            },
+            _ => Err(self.error(format!("Expected hash but found {}", self.describe()))),
+        }
+    }
+
+    fn parse_hash_u64(&mut self) -> Result<u64, BinError> {
+        match &self.token {
+            Token::String(string) => {
+                let hash = hashes::xxhash(string);
+                self.advance()?;
+                Ok(hash)
+            }
+            Token::Word(word) => match parse_hex_u64(word) {
+                Some(hash) => {
+                    self.advance()?;
+                    Ok(hash)
+                }
+                None => {
+                    Err(self.error(format!("Expected file hash but found {}", self.describe())))
+                }
+            },
+            _ => Err(self.error(format!("Expected file hash but found {}", self.describe()))),
+        }
+    }
+
+    fn parse_number<T: std::str::FromStr>(&mut self, what: &str) -> Result<T, BinError> {
+        let parsed = match &self.token {
+            Token::Word(word) => word.parse::<T>().ok(),
+            _ => None,
+        };
+        match parsed {
+            Some(value) => {
+                self.advance()?;
+                Ok(value)
+            }
+            None => Err(self.error(format!("Expected {} but found {}", what, self.describe()))),
+        }
+    }
+
+    fn parse_bool(&mut self) -> Result<bool, BinError> {
+        let value = match &self.token {
+            Token::Word(word) if word == "true" => true,
+            Token::Word(word) if word == "false" => false,
+            _ => {
+                return Err(self.error(format!(
+                    "Expected true or false but found {}",
+                    self.describe()
+                )))
+            }
+        };
+        self.advance()?;
+        Ok(value)
+    }
+
+    fn parse_string(&mut self) -> Result<String, BinError> {
+        match self.token {
+            Token::String(_) => match self.advance()? {
+                Token::String(string) => Ok(string),
+                _ => unreachable!(),
+            },
+            _ => Err(self.error(format!("Expected string but found {}", self.describe()))),
+        }
+    }
+
+    fn parse_floats(&mut self, count: usize, what: &str) -> Result<Vec<f32>, BinError> {
+        self.expect_punct('{')?;
+        let mut floats = Vec::with_capacity(count);
+        for index in 0..count {
+            if index != 0 {
+                self.eat_punct(',')?;
+            }
+            floats.push(self.parse_number::<f32>(&format!("{} item", what))?);
+        }
+        self.expect_punct('}')?;
+        Ok(floats)
+    }
+
+    fn parse_type_name(&mut self) -> Result<BinType, BinError> {
+        let word = self.expect_word()?;
+        match type_from_name(&word) {
+            Some(bin_type) => Ok(bin_type),
+            None => Err(self.error(format!("Unknown bin type: {}", word))),
+        }
+    }
+
+    /// A full type, with element types for `list`, `list2`, `option` and `map`.
+    fn parse_type(&mut self) -> Result<(BinType, Vec<BinType>), BinError> {
+        let bin_type = self.parse_type_name()?;
+        let mut inner = Vec::new();
+        match bin_type {
+            BinType::Container | BinType::Struct | BinType::Optional => {
+                self.expect_punct('[')?;
+                inner.push(self.parse_type_name()?);
+                self.expect_punct(']')?;
+            }
+            BinType::Map => {
+                self.expect_punct('[')?;
+                inner.push(self.parse_type_name()?);
+                self.expect_punct(',')?;
+                inner.push(self.parse_type_name()?);
+                self.expect_punct(']')?;
+            }
+            _ => {}
+        }
+        Ok((bin_type, inner))
+    }
+
+    fn parse_value(&mut self, bin_type: &BinType, inner: &[BinType]) -> Result<BinData, BinError> {
+        let bin_data = match *bin_type {
+            BinType::None => {
+                self.expect_word()?;
+                BinData::None
+            }
+            BinType::Bool => BinData::Bool(self.parse_bool()?),
+            BinType::SInt8 => BinData::SInt8(self.parse_number("SInt8")?),
+            BinType::UInt8 => BinData::UInt8(self.parse_number("UInt8")?),
+            BinType::SInt16 => BinData::SInt16(self.parse_number("SInt16")?),
+            BinType::UInt16 => BinData::UInt16(self.parse_number("UInt16")?),
+            BinType::SInt32 => BinData::SInt32(self.parse_number("SInt32")?),
+            BinType::UInt32 => BinData::UInt32(self.parse_number("UInt32")?),
+            BinType::SInt64 => BinData::SInt64(self.parse_number("SInt64")?),
+            BinType::UInt64 => BinData::UInt64(self.parse_number("UInt64")?),
+            BinType::Float32 => BinData::Float32(self.parse_number("Float32")?),
+            BinType::Vector2 => BinData::Vector2(self.parse_floats(2, "Vector2")?),
+            BinType::Vector3 => BinData::Vector3(self.parse_floats(3, "Vector3")?),
+            BinType::Vector4 => BinData::Vector4(self.parse_floats(4, "Vector4")?),
+            BinType::Matrix4x4 => BinData::Matrix4x4(self.parse_floats(16, "Matrix4x4")?),
+            BinType::Rgba => {
+                self.expect_punct('{')?;
+                let mut rgba = Vec::with_capacity(4);
+                for index in 0..4 {
+                    if index != 0 {
+                        self.eat_punct(',')?;
+                    }
+                    rgba.push(self.parse_number::<u8>("Rgba item")?);
+                }
+                self.expect_punct('}')?;
+                BinData::Rgba(rgba)
+            }
+            BinType::String => BinData::String(self.parse_string()?),
+            BinType::Hash => BinData::Hash(self.parse_hash_u32()?),
+            BinType::WadEntryLink => BinData::WadEntryLink(self.parse_hash_u64()?),
+            BinType::Link => BinData::Link(self.parse_hash_u32()?),
+            BinType::Flag => BinData::Flag(self.parse_bool()?),
+            BinType::Container | BinType::Struct => {
+                let item_type = self.element_type(bin_type, inner, 0)?;
+                let mut items = Vec::new();
+                self.expect_punct('{')?;
+                while !self.eat_punct('}')? {
+                    let index = items.len();
+                    items.push(
+                        self.parse_value(item_type, &[])
+                            .map_err(|e| e.in_path(PathSegment::Index(index)))?,
+                    );
+                    self.eat_punct(',')?;
+                }
+                BinData::ContainerOrStruct(ContainerOrStruct::new(*item_type, items))
+            }
+            BinType::Pointer | BinType::Embedded => {
+                BinData::PointerOrEmbedded(self.parse_pointerorembedded()?)
+            }
+            BinType::Optional => {
+                let item_type = self.element_type(bin_type, inner, 0)?;
+                self.expect_punct('{')?;
+                let data = if self.eat_punct('}')? {
+                    None
+                } else {
+                    let data = self.parse_value(item_type, &[])?;
+                    self.eat_punct(',')?;
+                    self.expect_punct('}')?;
+                    Some(data)
+                };
+                BinData::Optional(Optional::new(*item_type, data))
+            }
+            BinType::Map => {
+                let keytype = self.element_type(bin_type, inner, 0)?;
+                let valuetype = self.element_type(bin_type, inner, 1)?;
+                BinData::Map(self.parse_map(keytype, valuetype)?)
+            }
+        };
+        Ok(bin_data)
+    }
+
+    /// The element type at `index` of a `list`, `list2`, `option` or `map`.
+    fn element_type<'t>(
+        &self,
+        bin_type: &BinType,
+        inner: &'t [BinType],
+        index: usize,
+    ) -> Result<&'t BinType, BinError> {
+        inner
+            .get(index)
+            .ok_or_else(|| self.error(format!("Expected element type for {:?}", bin_type)))
+    }
+
+    fn parse_pointerorembedded(&mut self) -> Result<PointerOrEmbedded, BinError> {
+        if self.token == Token::Word("null".to_string()) {
+            self.advance()?;
+            return Ok(PointerOrEmbedded::new(0, Vec::new()));
+        }
+        let name = self.parse_name()?;
+        let mut items = Vec::new();
+        self.expect_punct('{').map_err(|e| e.in_class(name))?;
+        while !self.eat_punct('}')? {
+            items.push(self.parse_field().map_err(|e| e.in_class(name))?);
+        }
+        Ok(PointerOrEmbedded::new(name, items))
+    }
+
+    fn parse_field(&mut self) -> Result<BinField, BinError> {
+        let name = self.parse_name()?;
+        let in_field = |e: BinError| e.in_path(PathSegment::Field(name));
+        self.expect_punct(':').map_err(in_field)?;
+        let (bin_type, inner) = self.parse_type().map_err(in_field)?;
+        self.expect_punct('=').map_err(in_field)?;
+        let data = self.parse_value(&bin_type, &inner).map_err(in_field)?;
+        Ok(BinField::new(name, bin_type, data))
+    }
+
+    fn parse_map(&mut self, keytype: &BinType, valuetype: &BinType) -> Result<Map, BinError> {
+        let mut items = Vec::new();
+        self.expect_punct('{')?;
+        while !self.eat_punct('}')? {
+            let index = items.len();
+            let keydata = self
+                .parse_value(keytype, &[])
+                .map_err(|e| e.in_path(PathSegment::Index(index)))?;
+            let segment = PathSegment::from_map_key(&keydata, index);
+            self.expect_punct('=')
+                .map_err(|e| e.in_path(segment.clone()))?;
+            let valuedata = self
+                .parse_value(valuetype, &[])
+                .map_err(|e| e.in_path(segment))?;
+            self.eat_punct(',')?;
+            items.push(MapPair::new(keydata, valuedata));
+        }
+        Ok(Map::new(*keytype, *valuetype, items))
+    }
+}
+
+/// Parses ritobin text back into a bin file.
+pub fn convert_text_to_bin(contents: &str) -> Result<BinFile, BinError> {
+    let mut parser = Parser::new(contents)?;
+
+    let mut is_patch = false;
+    let mut unknown: Option<u64> = None;
+    let mut version: u32 = 3;
+    let mut linked_list: Vec<String> = Vec::new();
+    let mut entries = Map::new(BinType::Hash, BinType::Embedded, Vec::new());
+    let mut patches: Option<Map> = None;
+
+    while parser.token != Token::End {
+        let section = parser.expect_word()?;
+        let in_section = |e: BinError| match section.as_str() {
+            "entries" => e.in_path(PathSegment::Root("Entries")),
+            "patches" => e.in_path(PathSegment::Root("Patches")),
+            "linked" => e.in_path(PathSegment::Root("LinkedList")),
+            _ => e,
+        };
+        parser.expect_punct(':')?;
+        let (bin_type, inner) = parser.parse_type()?;
+        parser.expect_punct('=')?;
+        let value = parser.parse_value(&bin_type, &inner).map_err(in_section)?;
+
+        match (section.as_str(), value) {
+            ("type", BinData::String(signature)) => match signature.as_str() {
+                "PROP" => is_patch = false,
+                "PTCH" => is_patch = true,
+                _ => return Err(parser.error(format!("Unknown bin type {:?}", signature))),
+            },
+            ("unknown", BinData::UInt64(value)) => unknown = Some(value),
+            ("version", BinData::UInt32(value)) => version = value,
+            ("linked", BinData::ContainerOrStruct(cs)) => {
+                linked_list = cs
+                    .items
+                    .into_iter()
+                    .map(|item| match item {
+                        BinData::String(string) => Ok(string),
+                        _ => Err(parser.error("Expected linked as list[string]")),
+                    })
+                    .collect::<Result<Vec<String>, BinError>>()?
+            }
+            ("entries", BinData::Map(map)) => entries = map,
+            ("patches", BinData::Map(map)) => patches = Some(map),
+            (section, _) => {
+                return Err(parser.error(format!("Unexpected section {} or its type", section)))
+            }
+        }
+    }
+
+    if is_patch && unknown.is_none() {
+        unknown = Some(0);
+    }
+    if is_patch && patches.is_none() {
+        patches = Some(Map::new(BinType::Hash, BinType::Embedded, Vec::new()));
+    }
+
+    Ok(BinFile::new(
+        is_patch,
+        unknown,
+        version,
+        linked_list,
+        entries,
+        patches,
+    ))
+}
+
+/// Parses a single value of `bin_type`, like the right hand side of a field.
+///
+/// `inner` holds the element types of a `list`, `list2` or `option` (one) or a `map` (two).
+pub fn parse_text_value(
+    contents: &str,
+    bin_type: &BinType,
+    inner: &[BinType],
+) -> Result<BinData, BinError> {
+    let needed = match bin_type {
+        BinType::Container | BinType::Struct | BinType::Optional => 1,
+        BinType::Map => 2,
+        _ => 0,
+    };
+    if inner.len() < needed {
+        return Err(BinError::invalid_data(format!(
+            "Expected {} element type(s) for {:?}",
+            needed, bin_type
+        )));
+    }
+    let mut parser = Parser::new(contents)?;
+    let value = parser.parse_value(bin_type, inner)?;
+    parser.expect_end()?;
+    Ok(value)
+}
+
+/// Parses a single field, like `skinScale: f32 = 1.2`.
+pub fn parse_text_field(contents: &str) -> Result<BinField, BinError> {
+    let mut parser = Parser::new(contents)?;
+    let field = parser.parse_field()?;
+    parser.expect_end()?;
+    Ok(field)
+}
+
+/// Parses a single map pair, like `"keyA" = "a"`.
+///
+/// `value_inner` holds the element types of the value, as for `parse_text_value`.
+pub fn parse_text_map_pair(
+    contents: &str,
+    keytype: &BinType,
+    valuetype: &BinType,
+    value_inner: &[BinType],
+) -> Result<MapPair, BinError> {
+    let mut parser = Parser::new(contents)?;
+    let keydata = parser.parse_value(keytype, &[])?;
+    parser.expect_punct('=')?;
+    let valuedata = parser.parse_value(valuetype, value_inner)?;
+    parser.expect_end()?;
+    Ok(MapPair::new(keydata, valuedata))
+}
+
+/// The `BinType` of a ritobin type name like `u32` or `embed`.
+pub fn type_from_name(name: &str) -> Option<BinType> {
+    let bin_type = match name {
+        "none" => BinType::None,
+        "bool" => BinType::Bool,
+        "i8" => BinType::SInt8,
+        "u8" => BinType::UInt8,
+        "i16" => BinType::SInt16,
+        "u16" => BinType::UInt16,
+        "i32" => BinType::SInt32,
+        "u32" => BinType::UInt32,
+        "i64" => BinType::SInt64,
+        "u64" => BinType::UInt64,
+        "f32" => BinType::Float32,
+        "vec2" => BinType::Vector2,
+        "vec3" => BinType::Vector3,
+        "vec4" => BinType::Vector4,
+        "mtx44" => BinType::Matrix4x4,
+        "rgba" => BinType::Rgba,
+        "string" => BinType::String,
+        "hash" => BinType::Hash,
+        "file" => BinType::WadEntryLink,
+        "list" => BinType::Container,
+        "list2" => BinType::Struct,
+        "pointer" => BinType::Pointer,
+        "embed" => BinType::Embedded,
+        "link" => BinType::Link,
+        "option" => BinType::Optional,
+        "map" => BinType::Map,
+        "flag" => BinType::Flag,
+        _ => return None,
+    };
+    Some(bin_type)
+}
+
+fn parse_hex_u64(word: &str) -> Option<u64> {
+    if word.starts_with("0x") || word.starts_with("0X") {
+        u64::from_str_radix(&word[2..], 16).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_error(error: BinError) -> (usize, usize, String) {
+        match error {
+            BinError::At { source, .. } => text_error(*source),
+            BinError::Text {
+                line,
+                column,
+                message,
+            } => (line, column, message),
+            error => panic!("Expected a text error but got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn parses_nested_values() {
+        let list = parse_text_value("{ 1, 2 3 }", &BinType::Container, &[BinType::UInt32]).unwrap();
+        assert_eq!(
+            list,
+            BinData::ContainerOrStruct(ContainerOrStruct::new(
+                BinType::UInt32,
+                vec![BinData::UInt32(1), BinData::UInt32(2), BinData::UInt32(3)]
+            ))
+        );
+
+        let map = parse_text_value(
+            "{ \"a\" = { 1 0 0 } 0x00000002 = { 0, 1, 0 } }",
+            &BinType::Map,
+            &[BinType::Hash, BinType::Vector3],
+        )
+        .unwrap();
+        assert_eq!(
+            map,
+            BinData::Map(Map::new(
+                BinType::Hash,
+                BinType::Vector3,
+                vec![
+                    MapPair::new(
+                        BinData::Hash(hashes::fnv1a("a")),
+                        BinData::Vector3(vec![1.0, 0.0, 0.0])
+                    ),
+                    MapPair::new(BinData::Hash(2), BinData::Vector3(vec![0.0, 1.0, 0.0])),
+                ]
+            ))
+        );
+
+        let empty = parse_text_value("{}", &BinType::Optional, &[BinType::String]).unwrap();
+        assert_eq!(
+            empty,
+            BinData::Optional(Optional::new(BinType::String, None))
+        );
+
+        let field = parse_text_field("mData: embed = Data { value: option[u8] = { 7 } }").unwrap();
+        assert_eq!(field.name, hashes::fnv1a("mData"));
+        assert_eq!(field.btype, BinType::Embedded);
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let error =
+            parse_text_value("{ 1 x }", &BinType::Container, &[BinType::UInt8]).unwrap_err();
+        assert_eq!(
+            text_error(error),
+            (1, 5, "Expected UInt8 but found 'x'".to_string())
+        );
+
+        let error = parse_text_value("1 2", &BinType::UInt8, &[]).unwrap_err();
+        assert_eq!(text_error(error).1, 3);
+    }
+
+    #[test]
+    fn rejects_missing_element_types() {
+        assert!(parse_text_value("{}", &BinType::Map, &[BinType::Hash]).is_err());
+
+        let text =
+            "entries: map[hash,embed] = {\n  \"a\" = C {\n    f: list[list] = { { } }\n  }\n}\n";
+        let (line, _, message) = text_error(convert_text_to_bin(text).unwrap_err());
+        assert_eq!(line, 3);
+        assert_eq!(message, "Expected element type for Container");
+
+        let pair = parse_text_map_pair("\"k\" = { }", &BinType::Hash, &BinType::Container, &[]);
+        assert!(pair.is_err());
+        let pair = parse_text_map_pair(
+            "\"k\" = { 4 }",
+            &BinType::Hash,
+            &BinType::Container,
+            &[BinType::UInt32],
+        )
+        .unwrap();
+        assert_eq!(
+            *pair.valuedata,
+            BinData::ContainerOrStruct(ContainerOrStruct::new(
+                BinType::UInt32,
+                vec![BinData::UInt32(4)]
+            ))
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use hash_database::HashDatabase;
+use hashes::{fnv1a, xxhash};
+use structs::*;
+
+/// ritobin name of a type, without element types.
+pub fn type_name(bin_type: &BinType) -> &'static str {
+    match bin_type {
+        BinType::None => "none",
+        BinType::Bool => "bool",
+        BinType::SInt8 => "i8",
+        BinType::UInt8 => "u8",
+        BinType::SInt16 => "i16",
+        BinType::UInt16 => "u16",
+        BinType::SInt32 => "i32",
+        BinType::UInt32 => "u32",
+        BinType::SInt64 => "i64",
+        BinType::UInt64 => "u64",
+        BinType::Float32 => "f32",
+        BinType::Vector2 => "vec2",
+        BinType::Vector3 => "vec3",
+        BinType::Vector4 => "vec4",
+        BinType::Matrix4x4 => "mtx44",
+        BinType::Rgba => "rgba",
+        BinType::String => "string",
+        BinType::Hash => "hash",
+        BinType::WadEntryLink => "file",
+        BinType::Container => "list",
+        BinType::Struct => "list2",
+        BinType::Pointer => "pointer",
+        BinType::Embedded => "embed",
+        BinType::Link => "link",
+        BinType::Optional => "option",
+        BinType::Map => "map",
+        BinType::Flag => "flag",
+    }
+}
+
+/// Escapes a string as a double quoted ritobin string literal.
+pub fn quote_string(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\x{:02X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn is_word(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with("0x")
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub struct TextWriter<'a> {
+    buf: String,
+    dent: usize,
+    hash_database: &'a HashDatabase,
+}
+
+impl<'a> TextWriter<'a> {
+    pub fn new(hash_database: &'a HashDatabase) -> TextWriter<'a> {
+        TextWriter {
+            buf: String::with_capacity(1024),
+            dent: 0,
+            hash_database,
+        }
+    }
+
+    pub fn consume(self) -> String {
+        self.buf
+    }
+
+    fn new_line(&mut self) {
+        self.buf.push('\n');
+        for _ in 0..self.dent {
+            self.buf.push_str("    ");
+        }
+    }
+
+    fn write(&mut self, string: &str) {
+        self.buf.push_str(string);
+    }
+
+    /// Writes a class or field name resolved from `name`, but only if hashing
+    /// it back gives the same hash so the text round-trips.
+    fn write_name(&mut self, hash: u32, name: Option<&str>) {
+        let name = match name.filter(|name| fnv1a(name) == hash) {
+            Some(name) if is_word(name) => name.to_string(),
+            Some(name) => quote_string(name),
+            None => format!("0x{:08x}", hash),
+        };
+        self.write(&name);
+    }
+
+    fn write_hash_u32(&mut self, hash: u32) {
+        let name = self.hash_database.get_hash(hash);
+        let hash = match name.filter(|name| fnv1a(name) == hash) {
+            Some(name) => quote_string(name),
+            None => format!("0x{:08x}", hash),
+        };
+        self.write(&hash);
+    }
+
+    fn write_hash_u64(&mut self, hash: u64) {
+        let name = self.hash_database.get_game(hash);
+        let hash = match name.filter(|name| xxhash(name) == hash) {
+            Some(name) => quote_string(name),
+            None => format!("0x{:016x}", hash),
+        };
+        self.write(&hash);
+    }
+
+    fn write_f32(&mut self, float: f32) {
+        let mut buffer = dtoa::Buffer::new();
+        let float_str = buffer.format(float);
+        self.write(float_str.trim_end_matches(".0"));
+    }
+
+    fn write_floats(&mut self, floats: &[f32]) {
+        self.write("{ ");
+        for (index, float) in floats.iter().enumerate() {
+            if index != 0 {
+                self.write(", ");
+            }
+            self.write_f32(*float);
+        }
+        self.write(" }");
+    }
+
+    /// Writes a type with its element types, like `map[hash,embed]`.
+    pub fn write_type(&mut self, bin_type: &BinType, bin_data: Option<&BinData>) {
+        self.write(type_name(bin_type));
+        match bin_data {
+            Some(BinData::ContainerOrStruct(cs)) => {
+                self.write("[");
+                self.write(type_name(&cs.btype));
+                self.write("]");
+            }
+            Some(BinData::Optional(optional)) => {
+                self.write("[");
+                self.write(type_name(&optional.btype));
+                self.write("]");
+            }
+            Some(BinData::Map(map)) => {
+                self.write("[");
+                self.write(type_name(&map.keytype));
+                self.write(",");
+                self.write(type_name(&map.valuetype));
+                self.write("]");
+            }
+            _ => {}
+        }
+    }
+
+    pub fn write_value(&mut self, bin_data: &BinData) {
+        match bin_data {
+            BinData::None => self.write("null"),
+            BinData::Bool(bool) | BinData::Flag(bool) => {
+                self.write(if *bool { "true" } else { "false" })
+            }
+            BinData::SInt8(i8) => self.write(&i8.to_string()),
+            BinData::UInt8(u8) => self.write(&u8.to_string()),
+            BinData::SInt16(i16) => self.write(&i16.to_string()),
+            BinData::UInt16(u16) => self.write(&u16.to_string()),
+            BinData::SInt32(i32) => self.write(&i32.to_string()),
+            BinData::UInt32(u32) => self.write(&u32.to_string()),
+            BinData::SInt64(i64) => self.write(&i64.to_string()),
+            BinData::UInt64(u64) => self.write(&u64.to_string()),
+            BinData::Float32(f32) => self.write_f32(*f32),
+            BinData::Vector2(vec) | BinData::Vector3(vec) | BinData::Vector4(vec) => {
+                self.write_floats(vec)
+            }
+            BinData::Matrix4x4(mtx44) => {
+                self.write("{");
+                self.dent += 1;
+                for row in mtx44.chunks(4) {
+                    self.new_line();
+                    for (index, float) in row.iter().enumerate() {
+                        if index != 0 {
+                            self.write(", ");
+                        }
+                        self.write_f32(*float);
+                    }
+                }
+                self.dent -= 1;
+                self.new_line();
+                self.write("}");
+            }
+            BinData::Rgba(rgba) => {
+                let rgba: Vec<String> = rgba.iter().map(|value| value.to_string()).collect();
+                self.write(&format!("{{ {} }}", rgba.join(", ")));
+            }
+            BinData::String(string) => self.write(&quote_string(string)),
+            BinData::Hash(hash) | BinData::Link(hash) => self.write_hash_u32(*hash),
+            BinData::WadEntryLink(hash) => self.write_hash_u64(*hash),
+            BinData::ContainerOrStruct(cs) => self.write_items(&cs.items),
+            BinData::PointerOrEmbedded(pe) => self.write_pointerorembedded(pe),
+            BinData::Optional(optional) => match &optional.data {
+                Some(data) => {
+                    self.write("{");
+                    self.dent += 1;
+                    self.new_line();
+                    self.write_value(data);
+                    self.dent -= 1;
+                    self.new_line();
+                    self.write("}");
+                }
+                None => self.write("{}"),
+            },
+            BinData::Map(map) => self.write_map(map),
+        }
+    }
+
+    fn write_items(&mut self, items: &[BinData]) {
+        if items.is_empty() {
+            self.write("{}");
+            return;
+        }
+        self.write("{");
+        self.dent += 1;
+        for item in items {
+            self.new_line();
+            self.write_value(item);
+        }
+        self.dent -= 1;
+        self.new_line();
+        self.write("}");
+    }
+
+    pub fn write_field(&mut self, field: &BinField) {
+        self.write_name(field.name, self.hash_database.get_field(field.name));
+        self.write(": ");
+        self.write_type(&field.btype, Some(&field.data));
+        self.write(" = ");
+        self.write_value(&field.data);
+    }
+
+    fn write_pointerorembedded(&mut self, pe: &PointerOrEmbedded) {
+        if pe.name == 0 {
+            self.write("null");
+            return;
+        }
+        self.write_name(pe.name, self.hash_database.get_type(pe.name));
+        if pe.items.is_empty() {
+            self.write(" {}");
+            return;
+        }
+        self.write(" {");
+        self.dent += 1;
+        for field in &pe.items {
+            self.new_line();
+            self.write_field(field);
+        }
+        self.dent -= 1;
+        self.new_line();
+        self.write("}");
+    }
+
+    pub fn write_mappair(&mut self, mappair: &MapPair) {
+        self.write_value(&mappair.keydata);
+        self.write(" = ");
+        self.write_value(&mappair.valuedata);
+    }
+
+    fn write_map(&mut self, map: &Map) {
+        if map.items.is_empty() {
+            self.write("{}");
+            return;
+        }
+        self.write("{");
+        self.dent += 1;
+        for mappair in &map.items {
+            self.new_line();
+            self.write_mappair(mappair);
+        }
+        self.dent -= 1;
+        self.new_line();
+        self.write("}");
+    }
+
+    fn write_section(&mut self, name: &str, bin_type: &str, value: &str) {
+        self.write(name);
+        self.write(": ");
+        self.write(bin_type);
+        self.write(" = ");
+        self.write(value);
+        self.new_line();
+    }
+
+    fn write_map_section(&mut self, name: &str, map: &Map) {
+        self.write(name);
+        self.write(": map[hash,embed] = ");
+        self.write_map(map);
+        self.new_line();
+    }
+
+    pub fn write_bin_file(&mut self, bin_file: &BinFile) {
+        self.write("#PROP_text");
+        self.new_line();
+
+        let signature = if bin_file.is_patch { "PTCH" } else { "PROP" };
+        self.write_section("type", "string", &quote_string(signature));
+        if let Some(unknown) = bin_file.unknown {
+            self.write_section("unknown", "u64", &unknown.to_string());
+        }
+        self.write_section("version", "u32", &bin_file.version.to_string());
+
+        self.write("linked: list[string] = ");
+        let linked_list: Vec<BinData> = bin_file
+            .linked_list
+            .iter()
+            .map(|linked| BinData::String(linked.clone()))
+            .collect();
+        self.write_items(&linked_list);
+        self.new_line();
+
+        self.write_map_section("entries", &bin_file.entries);
+
+        if let Some(patches) = &bin_file.patches {
+            self.write_map_section("patches", patches);
+        }
+    }
+}
+
+/// Converts a bin file to ritobin text, resolving hashes through `hash_database`.
+pub fn convert_bin_to_text(bin_file: &BinFile, hash_database: &HashDatabase) -> String {
+    let mut writer = TextWriter::new(hash_database);
+    writer.write_bin_file(bin_file);
+    writer.consume()
+}
+
+/// Formats a value as ritobin `type = value`, like a field without its name.
+pub fn typed_value_to_text(
+    bin_type: &BinType,
+    bin_data: &BinData,
+    hash_database: &HashDatabase,
+) -> String {
+    let mut type_writer = TextWriter::new(hash_database);
+    type_writer.write_type(bin_type, Some(bin_data));
+    let mut value_writer = TextWriter::new(hash_database);
+    value_writer.write_value(bin_data);
+    format!("{} = {}", type_writer.consume(), value_writer.consume())
+}
@@ -0,0 +1,175 @@
+use hash_database::HashDatabase;
+use path::{BinPath, PathSegment};
+use structs::*;
+use walk::for_each_value;
+
+use std::collections::HashMap;
+
+/// What a hash in a bin names, which decides the dictionary it belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HashRole {
+    /// Class name of a Pointer or Embedded.
+    Class,
+    Field,
+    /// Key of an entry or a patch.
+    Entry,
+    /// A `Hash` or `Link` value, including map keys.
+    HashValue,
+    WadLink,
+}
+
+impl HashRole {
+    pub fn name(self) -> &'static str {
+        match self {
+            HashRole::Class => "class",
+            HashRole::Field => "field",
+            HashRole::Entry => "entry",
+            HashRole::HashValue => "hash value",
+            HashRole::WadLink => "wad link",
+        }
+    }
+
+    /// Dictionary file a name for this role is added to.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            HashRole::Class => "hashes.bintypes.txt",
+            HashRole::Field => "hashes.binfields.txt",
+            HashRole::Entry => "hashes.binentries.txt",
+            HashRole::HashValue => "hashes.binhashes.txt",
+            HashRole::WadLink => "hashes.game.txt",
+        }
+    }
+
+    /// Whether `hash_database` has a name for `hash` in this role's table.
+    pub fn is_resolved(self, hash: u64, hash_database: &HashDatabase) -> bool {
+        match self {
+            HashRole::Class => hash_database.get_type(hash as u32).is_some(),
+            HashRole::Field => hash_database.get_field(hash as u32).is_some(),
+            HashRole::Entry | HashRole::HashValue => hash_database.get_hash(hash as u32).is_some(),
+            HashRole::WadLink => hash_database.get_game(hash).is_some(),
+        }
+    }
+}
+
+/// Calls `f` with the role, value and path of every hash in `bin_file`.
+pub fn for_each_hash<F>(bin_file: &BinFile, mut f: F)
+where
+    F: FnMut(HashRole, u64, &BinPath),
+{
+    let sections = [
+        ("Entries", Some(&bin_file.entries)),
+        ("Patches", bin_file.patches.as_ref()),
+    ];
+    for (name, map) in sections.iter() {
+        if let Some(map) = map {
+            for (index, entry) in map.items.iter().enumerate() {
+                if let BinData::Hash(hash) = *entry.keydata {
+                    let path = BinPath::new()
+                        .with(PathSegment::Root(name))
+                        .with(PathSegment::from_map_key(&entry.keydata, index));
+                    f(HashRole::Entry, hash as u64, &path);
+                }
+            }
+        }
+    }
+
+    for_each_value(bin_file, |path, bin_data| match bin_data {
+        BinData::Hash(hash) | BinData::Link(hash) => f(HashRole::HashValue, *hash as u64, path),
+        BinData::WadEntryLink(hash) => f(HashRole::WadLink, *hash, path),
+        BinData::PointerOrEmbedded(pe) => {
+            if pe.name != 0 {
+                f(HashRole::Class, pe.name as u64, path);
+            }
+            for field in &pe.items {
+                f(
+                    HashRole::Field,
+                    field.name as u64,
+                    &path.with(PathSegment::Field(field.name)),
+                );
+            }
+        }
+        _ => {}
+    });
+}
+
+/// How often an unresolved hash was seen, and one path it was seen at.
+#[derive(Debug, Clone)]
+pub struct Occurrence {
+    pub count: usize,
+    pub example: BinPath,
+}
+
+/// Hashes that `HashDatabase` could not name, collected over one or more bins.
+#[derive(Debug, Default)]
+pub struct UnresolvedHashes {
+    pub hashes: HashMap<(HashRole, u64), Occurrence>,
+}
+
+impl UnresolvedHashes {
+    pub fn new() -> UnresolvedHashes {
+        UnresolvedHashes::default()
+    }
+
+    /// Records every hash of `bin_file` that has no name in its role's table.
+    pub fn collect(&mut self, bin_file: &BinFile, hash_database: &HashDatabase) {
+        for_each_hash(bin_file, |role, hash, path| {
+            if role.is_resolved(hash, hash_database) {
+                return;
+            }
+            self.hashes
+                .entry((role, hash))
+                .or_insert_with(|| Occurrence {
+                    count: 0,
+                    example: path.clone(),
+                })
+                .count += 1;
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Lists the hashes grouped by role, most frequent first, as
+    /// `hash count example` lines under a `# role` header.
+    pub fn to_string_with(&self, hash_database: &HashDatabase) -> String {
+        let mut sorted: Vec<(&(HashRole, u64), &Occurrence)> = self.hashes.iter().collect();
+        sorted.sort_by(|(a_key, a), (b_key, b)| {
+            a_key
+                .0
+                .cmp(&b_key.0)
+                .then(b.count.cmp(&a.count))
+                .then(a_key.1.cmp(&b_key.1))
+        });
+
+        let mut string = String::new();
+        let mut current_role = None;
+        for ((role, hash), occurrence) in sorted {
+            if current_role != Some(*role) {
+                let unique = self.hashes.keys().filter(|(r, _)| r == role).count();
+                if current_role.is_some() {
+                    string.push('\n');
+                }
+                string.push_str(&format!(
+                    "# {} ({} unique, for {})\n",
+                    role.name(),
+                    unique,
+                    role.file_name()
+                ));
+                current_role = Some(*role);
+            }
+            let hash = if *role == HashRole::WadLink {
+                format!("{:016x}", hash)
+            } else {
+                format!("{:08x}", hash)
+            };
+            string.push_str(&format!(
+                "{} {} {}\n",
+                hash,
+                occurrence.count,
+                occurrence.example.to_string_with(hash_database)
+            ));
+        }
+        string
+    }
+}
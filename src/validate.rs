@@ -0,0 +1,130 @@
+use hash_database::HashDatabase;
+use path::{BinPath, PathSegment};
+use schema::{FieldType, Schema};
+use structs::*;
+use walk::for_each_value;
+
+/// What is wrong with a value, compared to the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    /// A class the schema has never seen.
+    UnknownClass,
+    /// A field the schema has never seen in its class.
+    UnknownField,
+    /// A field with another type than the schema gives it.
+    WrongType {
+        expected: FieldType,
+        found: FieldType,
+    },
+    /// A container, struct or optional with another element type.
+    WrongElementType {
+        expected: FieldType,
+        found: FieldType,
+    },
+    /// A map with another key type.
+    WrongKeyType {
+        expected: FieldType,
+        found: FieldType,
+    },
+    /// A field every instance of the class had in the schema's bins.
+    MissingField,
+}
+
+/// A value that does not match the schema, with the class it is in.
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub path: BinPath,
+    pub class: u32,
+    pub kind: IssueKind,
+}
+
+impl Issue {
+    /// Formats the issue, resolving path and class names through `hash_database`.
+    pub fn to_string_with(&self, hash_database: &HashDatabase) -> String {
+        let message = match &self.kind {
+            IssueKind::UnknownClass => "Unknown class".to_string(),
+            IssueKind::UnknownField => "Unknown field".to_string(),
+            IssueKind::WrongType { expected, found } => {
+                format!("Field is {} but expected {}", found, expected)
+            }
+            IssueKind::WrongElementType { expected, found } => {
+                format!("Element type is {} but expected {}", found, expected)
+            }
+            IssueKind::WrongKeyType { expected, found } => {
+                format!("Map key type is {} but expected {}", found, expected)
+            }
+            IssueKind::MissingField => "Missing field".to_string(),
+        };
+        format!(
+            "{} at {} in {}",
+            message,
+            self.path.to_string_with(hash_database),
+            hash_database.type_to_string(self.class)
+        )
+    }
+}
+
+/// Checks every Pointer and Embedded of `bin_file` against `schema`.
+///
+/// A field seen with several types in the schema's bins may have any of them.
+/// A field is only required when every instance of its class had it.
+pub fn validate(bin_file: &BinFile, schema: &Schema) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for_each_value(bin_file, |path, bin_data| {
+        let pe = match bin_data {
+            BinData::PointerOrEmbedded(pe) if pe.name != 0 => pe,
+            _ => return,
+        };
+        let mut issue = |path: BinPath, kind: IssueKind| {
+            issues.push(Issue {
+                path,
+                class: pe.name,
+                kind,
+            })
+        };
+        let class = match schema.classes.get(&pe.name) {
+            Some(class) => class,
+            None => return issue(path.clone(), IssueKind::UnknownClass),
+        };
+
+        for field in &pe.items {
+            let field_path = path.with(PathSegment::Field(field.name));
+            let field_schema = match class.fields.get(&field.name) {
+                Some(field_schema) => field_schema,
+                None => {
+                    issue(field_path, IssueKind::UnknownField);
+                    continue;
+                }
+            };
+            let found = FieldType::of(field);
+            if field_schema.types.iter().any(|(known, _)| *known == found) {
+                continue;
+            }
+            let expected = field_schema.field_type();
+            let kind = if found.btype != expected.btype {
+                IssueKind::WrongType { expected, found }
+            } else if found.keytype != expected.keytype {
+                IssueKind::WrongKeyType { expected, found }
+            } else {
+                IssueKind::WrongElementType { expected, found }
+            };
+            issue(field_path, kind);
+        }
+
+        let mut missing: Vec<u32> = class
+            .fields
+            .iter()
+            .filter(|(name, field_schema)| {
+                class.count > 0
+                    && field_schema.count() >= class.count
+                    && !pe.items.iter().any(|field| field.name == **name)
+            })
+            .map(|(name, _)| *name)
+            .collect();
+        missing.sort();
+        for name in missing {
+            issue(path.with(PathSegment::Field(name)), IssueKind::MissingField);
+        }
+    });
+    issues
+}
@@ -0,0 +1,363 @@
+use error::BinError;
+use reader::read;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use flate2::read::GzDecoder;
+use std::io::{Cursor, Read};
+
+/// First bytes of a zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// How the data of a WAD entry is stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WadCompression {
+    None,
+    Gzip,
+    /// The entry names a file in another WAD instead of holding data.
+    Satellite,
+    Zstd,
+    /// zstd frames mixed with chunks stored as they are.
+    ZstdMulti,
+}
+
+#[derive(Debug, Clone)]
+pub struct WadEntry {
+    /// xxhash64 of the lowercase game path, as in `WadEntryLink` values.
+    pub path_hash: u64,
+    pub offset: u32,
+    pub compressed_size: u32,
+    pub size: u32,
+    pub compression: WadCompression,
+    /// The data is shared with an earlier entry.
+    pub duplicate: bool,
+}
+
+/// A `RW` WAD archive, version 1 to 3, over its bytes.
+#[derive(Debug)]
+pub struct Wad<'a> {
+    pub major: u8,
+    pub minor: u8,
+    pub entries: Vec<WadEntry>,
+    contents: &'a [u8],
+}
+
+impl WadCompression {
+    fn from_type(type_byte: u8) -> Option<WadCompression> {
+        // Version 3.4 keeps the subchunk count in the high nibble.
+        match type_byte & 0x0F {
+            0 => Some(WadCompression::None),
+            1 => Some(WadCompression::Gzip),
+            2 => Some(WadCompression::Satellite),
+            3 => Some(WadCompression::Zstd),
+            4 => Some(WadCompression::ZstdMulti),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WadCompression::None => "none",
+            WadCompression::Gzip => "gzip",
+            WadCompression::Satellite => "satellite",
+            WadCompression::Zstd => "zstd",
+            WadCompression::ZstdMulti => "zstd-multi",
+        }
+    }
+}
+
+impl<'a> Wad<'a> {
+    /// Parses the header and table of contents of a WAD archive.
+    pub fn read(contents: &'a [u8]) -> Result<Wad<'a>, BinError> {
+        let mut reader = Cursor::new(contents);
+
+        let mut signature = vec![0u8; 2];
+        read(&mut reader, "signature", |r| r.read_exact(&mut signature))?;
+        if signature != b"RW" {
+            return Err(BinError::wad(format!(
+                "WAD has no valid signature: {:?}",
+                String::from_utf8_lossy(&signature)
+            )));
+        }
+        let major = read(&mut reader, "major version", |r| r.read_u8())?;
+        let minor = read(&mut reader, "minor version", |r| r.read_u8())?;
+
+        let (toc_offset, entry_size, entry_count) = match major {
+            1 | 2 => {
+                if major == 2 {
+                    // ECDSA signature length and signature, then checksum.
+                    reader.set_position(4 + 1 + 83 + 8);
+                }
+                let toc_offset = read(&mut reader, "TOC offset", |r| r.read_u16::<LittleEndian>())?;
+                let entry_size = read(&mut reader, "TOC entry size", |r| {
+                    r.read_u16::<LittleEndian>()
+                })?;
+                let entry_count =
+                    read(&mut reader, "entry count", |r| r.read_u32::<LittleEndian>())?;
+                (toc_offset as u64, entry_size as u64, entry_count)
+            }
+            3 => {
+                // ECDSA signature, then checksum.
+                reader.set_position(4 + 256 + 8);
+                let entry_count =
+                    read(&mut reader, "entry count", |r| r.read_u32::<LittleEndian>())?;
+                (reader.position(), 32, entry_count)
+            }
+            _ => {
+                return Err(BinError::wad(format!(
+                    "Unsupported WAD version {}.{}",
+                    major, minor
+                )))
+            }
+        };
+        if entry_size < 24 {
+            return Err(BinError::wad(format!(
+                "WAD TOC entries of {} bytes are too small",
+                entry_size
+            )));
+        }
+
+        let toc_size = entry_count as u64 * entry_size;
+        if toc_offset + toc_size > contents.len() as u64 {
+            return Err(BinError::Truncated {
+                offset: toc_offset,
+                what: format!("WAD TOC of {} entries", entry_count),
+            });
+        }
+
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        for index in 0..entry_count as u64 {
+            reader.set_position(toc_offset + index * entry_size);
+            let path_hash = read(&mut reader, "entry path hash", |r| {
+                r.read_u64::<LittleEndian>()
+            })?;
+            let offset = read(&mut reader, "entry offset", |r| {
+                r.read_u32::<LittleEndian>()
+            })?;
+            let compressed_size = read(&mut reader, "entry compressed size", |r| {
+                r.read_u32::<LittleEndian>()
+            })?;
+            let size = read(&mut reader, "entry size", |r| r.read_u32::<LittleEndian>())?;
+            let type_offset = reader.position();
+            let type_byte = read(&mut reader, "entry type", |r| r.read_u8())?;
+            let compression = WadCompression::from_type(type_byte).ok_or_else(|| {
+                BinError::wad(format!(
+                    "Unknown WAD entry type {} at offset {}",
+                    type_byte, type_offset
+                ))
+            })?;
+            let duplicate = read(&mut reader, "entry duplicate flag", |r| r.read_u8())? != 0;
+            entries.push(WadEntry {
+                path_hash,
+                offset,
+                compressed_size,
+                size,
+                compression,
+                duplicate,
+            });
+        }
+
+        Ok(Wad {
+            major,
+            minor,
+            entries,
+            contents,
+        })
+    }
+
+    pub fn find(&self, path_hash: u64) -> Option<&WadEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.path_hash == path_hash)
+    }
+
+    /// Reads and decompresses the data of `entry`.
+    pub fn extract(&self, entry: &WadEntry) -> Result<Vec<u8>, BinError> {
+        let start = entry.offset as usize;
+        let end = start + entry.compressed_size as usize;
+        let data = self
+            .contents
+            .get(start..end)
+            .ok_or_else(|| BinError::Truncated {
+                offset: start as u64,
+                what: format!("WAD entry data of {} bytes", entry.compressed_size),
+            })?;
+
+        let decompressed = match entry.compression {
+            WadCompression::None => data.to_vec(),
+            WadCompression::Gzip => read_limited(GzDecoder::new(data), entry.size, "gzip")?,
+            WadCompression::Satellite => {
+                return Err(BinError::wad(
+                    "Entry is a satellite, its data is in another WAD",
+                ))
+            }
+            WadCompression::Zstd => zstd_decompress(data, entry.size)?,
+            WadCompression::ZstdMulti => zstd_multi_decompress(data, entry.size)?,
+        };
+
+        if decompressed.len() != entry.size as usize {
+            return Err(BinError::SizeMismatch {
+                what: "WAD entry".to_string(),
+                offset: start as u64,
+                expected: entry.size as u64,
+                actual: decompressed.len() as u64,
+            });
+        }
+        Ok(decompressed)
+    }
+}
+
+/// Reads what `decoder` decompresses, up to one byte past `size` so a wrong
+/// size is caught without decompressing all of a corrupt entry.
+fn read_limited<R: Read>(decoder: R, size: u32, what: &str) -> Result<Vec<u8>, BinError> {
+    let mut decompressed = Vec::new();
+    decoder
+        .take(size as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| BinError::wad(format!("Could not decompress {} data: {}", what, e)))?;
+    Ok(decompressed)
+}
+
+fn zstd_decompress(data: &[u8], size: u32) -> Result<Vec<u8>, BinError> {
+    let decoder = zstd::stream::read::Decoder::with_buffer(data)
+        .map_err(|e| BinError::wad(format!("Could not decompress zstd data: {}", e)))?;
+    read_limited(decoder, size, "zstd")
+}
+
+/// Decompresses zstd frames and copies the chunks between them as they are.
+fn zstd_multi_decompress(data: &[u8], size: u32) -> Result<Vec<u8>, BinError> {
+    let mut decompressed = Vec::new();
+    let mut position = 0;
+    while position < data.len() && decompressed.len() <= size as usize {
+        let rest = &data[position..];
+        if rest.starts_with(&ZSTD_MAGIC) {
+            let frame_size = zstd::zstd_safe::find_frame_compressed_size(rest)
+                .map_err(|_| BinError::wad("Could not find the end of a zstd frame"))?;
+            let left = (size as usize).saturating_sub(decompressed.len()) as u32;
+            decompressed.extend(zstd_decompress(&rest[..frame_size], left)?);
+            position += frame_size;
+        } else {
+            let raw_size = rest
+                .windows(ZSTD_MAGIC.len())
+                .position(|window| window == ZSTD_MAGIC)
+                .unwrap_or(rest.len());
+            decompressed.extend_from_slice(&rest[..raw_size]);
+            position += raw_size;
+        }
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::WriteBytesExt;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    /// A version 3.4 WAD of `(path_hash, stored data, size, type)` entries.
+    fn wad_v3(entries: &[(u64, Vec<u8>, u32, u8)]) -> Vec<u8> {
+        let mut wad = b"RW\x03\x04".to_vec();
+        wad.extend(vec![0u8; 256 + 8]);
+        wad.write_u32::<LittleEndian>(entries.len() as u32).unwrap();
+        let mut offset = wad.len() + entries.len() * 32;
+        for (path_hash, data, size, type_byte) in entries {
+            wad.write_u64::<LittleEndian>(*path_hash).unwrap();
+            wad.write_u32::<LittleEndian>(offset as u32).unwrap();
+            wad.write_u32::<LittleEndian>(data.len() as u32).unwrap();
+            wad.write_u32::<LittleEndian>(*size).unwrap();
+            wad.extend([*type_byte, 0, 0, 0]);
+            wad.write_u64::<LittleEndian>(0).unwrap();
+            offset += data.len();
+        }
+        for (_, data, _, _) in entries {
+            wad.extend(data);
+        }
+        wad
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn extracts_every_compression() {
+        let data: Vec<u8> = (0..200u8).collect();
+        let zstd = zstd::encode_all(&data[..], 0).unwrap();
+        let mut multi = data[..50].to_vec();
+        multi.extend(zstd::encode_all(&data[50..120], 0).unwrap());
+        multi.extend(&data[120..]);
+        let contents = wad_v3(&[
+            (1, data.clone(), 200, 0),
+            (2, gzip(&data), 200, 1),
+            (3, zstd, 200, 3),
+            (4, multi, 200, 4 | (3 << 4)),
+            (5, b"other.wad.client".to_vec(), 16, 2),
+        ]);
+
+        let wad = Wad::read(&contents).unwrap();
+        assert_eq!((wad.major, wad.minor), (3, 4));
+        assert_eq!(wad.entries.len(), 5);
+        for path_hash in 1..5 {
+            let entry = wad.find(path_hash).unwrap();
+            assert_eq!(wad.extract(entry).unwrap(), data, "entry {}", path_hash);
+        }
+        let satellite = wad.find(5).unwrap();
+        assert_eq!(satellite.compression, WadCompression::Satellite);
+        assert!(wad.extract(satellite).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_sizes() {
+        let data = vec![7u8; 100];
+        let contents = wad_v3(&[(1, gzip(&data), 10, 1), (2, data.clone(), 100, 0)]);
+        let wad = Wad::read(&contents).unwrap();
+        match wad.extract(&wad.entries[0]) {
+            Err(BinError::SizeMismatch {
+                expected, actual, ..
+            }) => assert_eq!((expected, actual), (10, 11)),
+            result => panic!("Expected a size mismatch but got {:?}", result),
+        }
+
+        let mut entry = wad.entries[1].clone();
+        entry.compressed_size = 1000;
+        assert!(matches!(
+            wad.extract(&entry),
+            Err(BinError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert!(matches!(Wad::read(b"PROP"), Err(BinError::Wad { .. })));
+        assert!(matches!(
+            Wad::read(b"RW\x09\x00"),
+            Err(BinError::Wad { .. })
+        ));
+
+        let mut truncated = b"RW\x03\x00".to_vec();
+        truncated.extend(vec![0u8; 200]);
+        match Wad::read(&truncated) {
+            Err(BinError::Truncated { offset, what }) => {
+                assert_eq!((offset, what.as_str()), (268, "entry count"))
+            }
+            result => panic!("Expected a truncated header but got {:?}", result),
+        }
+
+        let mut huge = b"RW\x03\x00".to_vec();
+        huge.extend(vec![0u8; 264]);
+        huge.extend([0xFF; 4]);
+        match Wad::read(&huge) {
+            Err(BinError::Truncated { offset, .. }) => assert_eq!(offset, 272),
+            result => panic!("Expected a truncated TOC but got {:?}", result),
+        }
+
+        let mut short_toc = wad_v3(&[(1, vec![1, 2, 3], 3, 0)]);
+        short_toc.truncate(290);
+        assert!(matches!(
+            Wad::read(&short_toc),
+            Err(BinError::Truncated { .. })
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+use path::{BinPath, PathSegment};
+use structs::*;
+
+/// Calls `f` with every value of `bin_file` and its path, parents before
+/// children. Entry and patch values are visited, their keys are not.
+pub fn for_each_value<F>(bin_file: &BinFile, mut f: F)
+where
+    F: FnMut(&BinPath, &BinData),
+{
+    let sections = [
+        ("Entries", Some(&bin_file.entries)),
+        ("Patches", bin_file.patches.as_ref()),
+    ];
+    for (name, map) in sections.iter() {
+        let map = match map {
+            Some(map) => map,
+            None => continue,
+        };
+        let mut path = BinPath::new();
+        path.push(PathSegment::Root(name));
+        for (index, entry) in map.items.iter().enumerate() {
+            path.push(PathSegment::from_map_key(&entry.keydata, index));
+            walk_data(&entry.valuedata, &mut path, &mut f);
+            path.pop();
+        }
+    }
+}
+
+/// Calls `f` with `bin_data` and every value nested in it, extending `path`.
+pub fn walk_data<F>(bin_data: &BinData, path: &mut BinPath, f: &mut F)
+where
+    F: FnMut(&BinPath, &BinData),
+{
+    f(path, bin_data);
+    match bin_data {
+        BinData::ContainerOrStruct(cs) => {
+            for (index, item) in cs.items.iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                walk_data(item, path, f);
+                path.pop();
+            }
+        }
+        BinData::PointerOrEmbedded(pe) => {
+            for field in &pe.items {
+                path.push(PathSegment::Field(field.name));
+                walk_data(&field.data, path, f);
+                path.pop();
+            }
+        }
+        BinData::Optional(optional) => {
+            if let Some(data) = &optional.data {
+                walk_data(data, path, f);
+            }
+        }
+        BinData::Map(map) => {
+            for (index, mappair) in map.items.iter().enumerate() {
+                path.push(PathSegment::from_map_key(&mappair.keydata, index));
+                walk_data(&mappair.keydata, path, f);
+                walk_data(&mappair.valuedata, path, f);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,61 +1,139 @@
+use error::BinError;
+use path::{BinPath, PathSegment};
 use structs::*;
 
 use byteorder::{LittleEndian, WriteBytesExt};
-use std::io::Write;
-
-fn write_string(writer: &mut Vec<u8>, string: &str) {
-    writer
-        .write_u16::<LittleEndian>(string.len() as u16)
-        .expect("Could not write string length");
-    writer
-        .write_all(string.as_bytes())
-        .expect("Could not write string");
+use std::io::{self, Write};
+
+fn write_string<W: Write>(writer: &mut W, string: &str) -> Result<(), BinError> {
+    if string.len() > u16::MAX as usize {
+        return Err(BinError::invalid_data(format!(
+            "String of {} bytes is longer than {}",
+            string.len(),
+            u16::MAX
+        )));
+    }
+    writer.write_u16::<LittleEndian>(string.len() as u16)?;
+    writer.write_all(string.as_bytes())?;
+    Ok(())
 }
 
-pub fn write_bin(bin_file: &BinFile) -> Vec<u8> {
-    println!("Writing bin file");
+fn field_count_u16(pe: &PointerOrEmbedded) -> Result<u16, BinError> {
+    if pe.items.len() > u16::MAX as usize {
+        return Err(BinError::invalid_data(format!(
+            "Pointer or Embedded with {} fields has more than {}",
+            pe.items.len(),
+            u16::MAX
+        )));
+    }
+    Ok(pe.items.len() as u16)
+}
 
+/// Serializes a bin file back to its binary `PROP` or `PTCH` form.
+pub fn write_bin(bin_file: &BinFile) -> Result<Vec<u8>, BinError> {
     let mut writer: Vec<u8> = Vec::new();
+    write_bin_to(&mut writer, bin_file)?;
+    Ok(writer)
+}
+
+/// Counts written bytes and fails the write that reaches `offset`.
+struct OffsetLocator {
+    written: u64,
+    offset: u64,
+}
+
+impl Write for OffsetLocator {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() as u64 > self.offset {
+            return Err(io::Error::other("offset reached"));
+        }
+        self.written += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Logical path and innermost class of the value `write_bin` writes at byte `offset`.
+///
+/// Returns an empty path for the header and `None` if the offset is past the end.
+pub fn locate_offset(bin_file: &BinFile, offset: u64) -> Option<(BinPath, Option<u32>)> {
+    let mut locator = OffsetLocator { written: 0, offset };
+    match write_bin_to(&mut locator, bin_file) {
+        Ok(()) => None,
+        Err(BinError::At { path, class, .. }) => Some((path, class)),
+        Err(_) => Some((BinPath::new(), None)),
+    }
+}
+
+/// Compares a rewritten bin with the `original` bytes `bin_file` was read from.
+///
+/// The first difference is reported as a `RoundTrip` error at the logical
+/// field of `bin_file` that covers that offset.
+pub fn check_round_trip(
+    bin_file: &BinFile,
+    original: &[u8],
+    rewritten: &[u8],
+) -> Result<(), BinError> {
+    let offset = match original.iter().zip(rewritten).position(|(a, b)| a != b) {
+        Some(offset) => offset,
+        None if original.len() == rewritten.len() => return Ok(()),
+        None => original.len().min(rewritten.len()),
+    };
+
+    let error = BinError::RoundTrip {
+        offset: offset as u64,
+        expected: original.get(offset).cloned(),
+        actual: rewritten.get(offset).cloned(),
+    };
+    match locate_offset(bin_file, offset as u64) {
+        Some((path, class)) if !path.is_empty() || class.is_some() => Err(BinError::At {
+            path,
+            class,
+            source: Box::new(error),
+        }),
+        _ => Err(error),
+    }
+}
 
+fn write_bin_to<W: Write>(writer: &mut W, bin_file: &BinFile) -> Result<(), BinError> {
     if bin_file.is_patch {
-        writer
-            .write_all("PTCH".as_bytes())
-            .expect("Could not write PTCH");
+        writer.write_all("PTCH".as_bytes())?;
         if let Some(unknown) = &bin_file.unknown {
-            writer
-                .write_u64::<LittleEndian>(*unknown)
-                .expect("Could not write unknown data");
+            writer.write_u64::<LittleEndian>(*unknown)?;
         }
     }
 
-    writer
-        .write_all("PROP".as_bytes())
-        .expect("Could not write PROP");
+    writer.write_all("PROP".as_bytes())?;
 
-    writer
-        .write_u32::<LittleEndian>(bin_file.version)
-        .expect("Could not write version");
+    writer.write_u32::<LittleEndian>(bin_file.version)?;
 
     if bin_file.version >= 2 {
-        writer
-            .write_u32::<LittleEndian>(bin_file.linked_list.len() as u32)
-            .expect("Could not write linked list count");
-        for linked in &bin_file.linked_list {
-            write_string(&mut writer, linked);
+        writer.write_u32::<LittleEndian>(bin_file.linked_list.len() as u32)?;
+        for (index, linked) in bin_file.linked_list.iter().enumerate() {
+            write_string(writer, linked).map_err(|e| {
+                e.in_path(PathSegment::Index(index))
+                    .in_path(PathSegment::Root("LinkedList"))
+            })?;
         }
     }
 
-    writer
-        .write_u32::<LittleEndian>(bin_file.entries.items.len() as u32)
-        .expect("Could not write entries count");
+    writer.write_u32::<LittleEndian>(bin_file.entries.items.len() as u32)?;
 
     for entry in &bin_file.entries.items {
         if let BinData::PointerOrEmbedded(pe) = &*entry.valuedata {
-            writer
-                .write_u32::<LittleEndian>(pe.name)
-                .expect("Could not write entry type");
+            writer.write_u32::<LittleEndian>(pe.name).map_err(|e| {
+                BinError::from(e)
+                    .in_class(pe.name)
+                    .in_path(PathSegment::from_map_key(&entry.keydata, 0))
+                    .in_path(PathSegment::Root("Entries"))
+            })?;
         } else {
-            panic!("Expected Pointer or Embedded in entry valuedata");
+            return Err(BinError::invalid_data(
+                "Expected Pointer or Embedded in entry valuedata",
+            ));
         }
     }
 
@@ -63,56 +141,58 @@ pub fn write_bin(bin_file: &BinFile) -> Vec<u8> {
         let entry_name = if let BinData::Hash(hash) = &*entry.keydata {
             *hash
         } else {
-            panic!("Expected Hash in entry keydata");
+            return Err(BinError::invalid_data("Expected Hash in entry keydata"));
         };
         if let BinData::PointerOrEmbedded(pe) = &*entry.valuedata {
-            let field_count = pe.items.len() as u16;
+            let field_count = field_count_u16(pe)?;
 
             let mut entry_length = 4 + 2;
             for field in &pe.items {
                 entry_length += get_total_bin_data_size(&field.data) + 4 + 1;
             }
 
+            let in_entry = |e: BinError| {
+                e.in_class(pe.name)
+                    .in_path(PathSegment::Hash(entry_name))
+                    .in_path(PathSegment::Root("Entries"))
+            };
+
             writer
                 .write_u32::<LittleEndian>(entry_length)
-                .expect("Could not write entry length");
+                .map_err(|e| in_entry(e.into()))?;
             writer
                 .write_u32::<LittleEndian>(entry_name)
-                .expect("Could not write entry name");
+                .map_err(|e| in_entry(e.into()))?;
             writer
                 .write_u16::<LittleEndian>(field_count)
-                .expect("Could not write entry field count");
-
-            for field in &pe.items {
-                let ftype = type_to_u8(&field.btype);
+                .map_err(|e| in_entry(e.into()))?;
 
-                writer
-                    .write_u32::<LittleEndian>(field.name)
-                    .expect("Could not write entry field name");
-                writer
-                    .write_u8(ftype)
-                    .expect("Could not write entry field type");
-
-                write_value_by_bin_data(&mut writer, &field.data, &field.btype);
-            }
+            write_fields(writer, &pe.items).map_err(in_entry)?;
         } else {
-            panic!("Expected Pointer or Embedded in entry valuedata");
+            return Err(BinError::invalid_data(
+                "Expected Pointer or Embedded in entry valuedata",
+            ));
         }
     }
 
     if bin_file.is_patch {
         if let Some(patches) = &bin_file.patches {
-            writer
-                .write_u32::<LittleEndian>(patches.items.len() as u32)
-                .expect("Could not write patches count");
+            writer.write_u32::<LittleEndian>(patches.items.len() as u32)?;
 
             for patch in &patches.items {
                 let patch_name = if let BinData::Hash(hash) = &*patch.keydata {
                     *hash
                 } else {
-                    panic!("Expected Hash in patch keydata");
+                    return Err(BinError::invalid_data("Expected Hash in patch keydata"));
                 };
                 if let BinData::PointerOrEmbedded(pe) = &*patch.valuedata {
+                    if pe.items.len() != 2 {
+                        return Err(BinError::invalid_data(format!(
+                            "Expected path and value in patch but found {} fields",
+                            pe.items.len()
+                        )));
+                    }
+
                     let first_field = &pe.items[0].data;
                     let second_field = &pe.items[1].data;
 
@@ -120,129 +200,137 @@ pub fn write_bin(bin_file: &BinFile) -> Vec<u8> {
                     patch_length += get_total_bin_data_size(first_field);
                     patch_length += get_total_bin_data_size(second_field);
 
+                    let in_patch = |e: BinError| {
+                        e.in_path(PathSegment::Hash(patch_name))
+                            .in_path(PathSegment::Root("Patches"))
+                    };
+
                     writer
                         .write_u32::<LittleEndian>(patch_name)
-                        .expect("Could not write patch name");
+                        .map_err(|e| in_patch(e.into()))?;
                     writer
                         .write_u32::<LittleEndian>(patch_length)
-                        .expect("Could not write patch length");
+                        .map_err(|e| in_patch(e.into()))?;
 
                     let ftype = type_to_u8(&pe.items[1].btype);
-                    writer
-                        .write_u8(ftype)
-                        .expect("Could not write patch field type");
+                    writer.write_u8(ftype).map_err(|e| in_patch(e.into()))?;
 
-                    write_value_by_bin_data(&mut writer, first_field, &pe.items[0].btype);
-                    write_value_by_bin_data(&mut writer, second_field, &pe.items[1].btype);
+                    for field in &pe.items {
+                        write_value_by_bin_data(writer, &field.data, &field.btype)
+                            .map_err(|e| in_patch(e.in_path(PathSegment::Field(field.name))))?;
+                    }
+                } else {
+                    return Err(BinError::invalid_data(
+                        "Expected Pointer or Embedded in patch valuedata",
+                    ));
                 }
             }
         } else {
-            panic!("Expected patches in patch file");
+            return Err(BinError::invalid_data("Expected patches in patch file"));
         }
     }
 
-    println!("Finished writing bin file");
+    Ok(())
+}
+
+fn write_fields<W: Write>(writer: &mut W, fields: &[BinField]) -> Result<(), BinError> {
+    for field in fields {
+        let ftype = type_to_u8(&field.btype);
+        let in_field = |e: BinError| e.in_path(PathSegment::Field(field.name));
 
-    writer
+        writer
+            .write_u32::<LittleEndian>(field.name)
+            .map_err(|e| in_field(e.into()))?;
+        writer.write_u8(ftype).map_err(|e| in_field(e.into()))?;
+
+        write_value_by_bin_data(writer, &field.data, &field.btype).map_err(in_field)?;
+    }
+    Ok(())
 }
 
-fn write_value_by_bin_data(writer: &mut Vec<u8>, bin_data: &BinData, bin_type: &BinType) {
+fn write_value_by_bin_data<W: Write>(
+    writer: &mut W,
+    bin_data: &BinData,
+    bin_type: &BinType,
+) -> Result<(), BinError> {
+    if !data_matches_type(bin_data, bin_type) {
+        return Err(BinError::invalid_data(format!(
+            "Expected {:?} but found {}",
+            bin_type,
+            data_variant_name(bin_data)
+        )));
+    }
+
     match bin_data {
         BinData::None => {}
         BinData::Bool(bool) => {
-            writer.write_u8(*bool as u8).expect("Could not write Bool");
+            writer.write_u8(*bool as u8)?;
         }
         BinData::SInt8(i8) => {
-            writer.write_i8(*i8).expect("Could not write SInt8");
+            writer.write_i8(*i8)?;
         }
         BinData::UInt8(u8) => {
-            writer.write_u8(*u8).expect("Could not write Uint8");
+            writer.write_u8(*u8)?;
         }
         BinData::SInt16(i16) => {
-            writer
-                .write_i16::<LittleEndian>(*i16)
-                .expect("Could not write SInt16");
+            writer.write_i16::<LittleEndian>(*i16)?;
         }
         BinData::UInt16(u16) => {
-            writer
-                .write_u16::<LittleEndian>(*u16)
-                .expect("Could not write UInt16");
+            writer.write_u16::<LittleEndian>(*u16)?;
         }
         BinData::SInt32(i32) => {
-            writer
-                .write_i32::<LittleEndian>(*i32)
-                .expect("Could not write SInt32");
+            writer.write_i32::<LittleEndian>(*i32)?;
         }
         BinData::UInt32(u32) => {
-            writer
-                .write_u32::<LittleEndian>(*u32)
-                .expect("Could not write UInt32");
+            writer.write_u32::<LittleEndian>(*u32)?;
         }
         BinData::SInt64(i64) => {
-            writer
-                .write_i64::<LittleEndian>(*i64)
-                .expect("Could not write SInt64");
+            writer.write_i64::<LittleEndian>(*i64)?;
         }
         BinData::UInt64(u64) => {
-            writer
-                .write_u64::<LittleEndian>(*u64)
-                .expect("Could not write UInt64");
+            writer.write_u64::<LittleEndian>(*u64)?;
         }
         BinData::Float32(f32) => {
-            writer
-                .write_f32::<LittleEndian>(*f32)
-                .expect("Could not write Float32");
+            writer.write_f32::<LittleEndian>(*f32)?;
         }
         BinData::Vector2(vec2) => {
-            assert_eq!(vec2.len(), 2);
+            check_length(vec2.len(), 2, "Vector2")?;
             for value in vec2 {
-                writer
-                    .write_f32::<LittleEndian>(*value)
-                    .expect("Could not write Vector2");
+                writer.write_f32::<LittleEndian>(*value)?;
             }
         }
         BinData::Vector3(vec3) => {
-            assert_eq!(vec3.len(), 3);
+            check_length(vec3.len(), 3, "Vector3")?;
             for value in vec3 {
-                writer
-                    .write_f32::<LittleEndian>(*value)
-                    .expect("Could not write Vector3");
+                writer.write_f32::<LittleEndian>(*value)?;
             }
         }
         BinData::Vector4(vec4) => {
-            assert_eq!(vec4.len(), 4);
+            check_length(vec4.len(), 4, "Vector4")?;
             for value in vec4 {
-                writer
-                    .write_f32::<LittleEndian>(*value)
-                    .expect("Could not write Vector4");
+                writer.write_f32::<LittleEndian>(*value)?;
             }
         }
         BinData::Matrix4x4(mtx44) => {
-            assert_eq!(mtx44.len(), 16);
+            check_length(mtx44.len(), 16, "Matrix4x4")?;
             for value in mtx44 {
-                writer
-                    .write_f32::<LittleEndian>(*value)
-                    .expect("Could not write Matrix4x4");
+                writer.write_f32::<LittleEndian>(*value)?;
             }
         }
         BinData::Rgba(rgba) => {
-            assert_eq!(rgba.len(), 4);
+            check_length(rgba.len(), 4, "Rgba")?;
             for value in rgba {
-                writer.write_u8(*value).expect("Could not write Rgba");
+                writer.write_u8(*value)?;
             }
         }
         BinData::String(string) => {
-            write_string(writer, string);
+            write_string(writer, string)?;
         }
         BinData::Hash(hash) => {
-            writer
-                .write_u32::<LittleEndian>(*hash)
-                .expect("Could not write Hash");
+            writer.write_u32::<LittleEndian>(*hash)?;
         }
         BinData::WadEntryLink(wadentrylink) => {
-            writer
-                .write_u64::<LittleEndian>(*wadentrylink)
-                .expect("Could not write WadEntryLink");
+            writer.write_u64::<LittleEndian>(*wadentrylink)?;
         }
         BinData::ContainerOrStruct(cs) => {
             let mut length: u32 = 4;
@@ -253,73 +341,47 @@ fn write_value_by_bin_data(writer: &mut Vec<u8>, bin_data: &BinData, bin_type: &
                 length += get_total_bin_data_size(field);
             }
 
-            writer
-                .write_u8(ftype)
-                .unwrap_or_else(|_| panic!("Could not write {:?} type", bin_type));
-            writer
-                .write_u32::<LittleEndian>(length)
-                .unwrap_or_else(|_| panic!("Could not write {:?} length", bin_type));
-            writer
-                .write_u32::<LittleEndian>(field_count)
-                .unwrap_or_else(|_| panic!("Could not write {:?} field count", bin_type));
+            writer.write_u8(ftype)?;
+            writer.write_u32::<LittleEndian>(length)?;
+            writer.write_u32::<LittleEndian>(field_count)?;
 
-            for field in &cs.items {
-                write_value_by_bin_data(writer, field, &cs.btype);
+            for (index, field) in cs.items.iter().enumerate() {
+                write_value_by_bin_data(writer, field, &cs.btype)
+                    .map_err(|e| e.in_path(PathSegment::Index(index)))?;
             }
         }
         BinData::PointerOrEmbedded(pe) => {
-            writer
-                .write_u32::<LittleEndian>(pe.name)
-                .unwrap_or_else(|_| panic!("Could not write {:?} name", bin_type));
+            writer.write_u32::<LittleEndian>(pe.name)?;
             if pe.name == 0 {
-                return;
+                return Ok(());
             }
 
             let mut length: u32 = 2;
-            let field_count = pe.items.len() as u16;
+            let field_count = field_count_u16(pe)?;
 
             for field in &pe.items {
                 length += get_total_bin_data_size(&field.data) + 4 + 1;
             }
 
-            writer
-                .write_u32::<LittleEndian>(length)
-                .unwrap_or_else(|_| panic!("Could not write {:?} length", bin_type));
-            writer
-                .write_u16::<LittleEndian>(field_count)
-                .unwrap_or_else(|_| panic!("Could not write {:?} field count", bin_type));
-
-            for field in &pe.items {
-                let ftype = type_to_u8(&field.btype);
-
-                writer
-                    .write_u32::<LittleEndian>(field.name)
-                    .unwrap_or_else(|_| panic!("Could not write {:?} field name", bin_type));
-                writer
-                    .write_u8(ftype)
-                    .unwrap_or_else(|_| panic!("Could not write {:?} field type", bin_type));
+            writer.write_u32::<LittleEndian>(length)?;
+            writer.write_u16::<LittleEndian>(field_count)?;
 
-                write_value_by_bin_data(writer, &field.data, &field.btype);
-            }
+            write_fields(writer, &pe.items).map_err(|e| e.in_class(pe.name))?;
         }
         BinData::Link(link) => {
-            writer
-                .write_u32::<LittleEndian>(*link)
-                .expect("Could not write link");
+            writer.write_u32::<LittleEndian>(*link)?;
         }
         BinData::Optional(option) => {
             let ftype = type_to_u8(&option.btype);
 
-            writer
-                .write_u8(ftype)
-                .expect("Could not write Optional type");
+            writer.write_u8(ftype)?;
 
             if let Some(data) = &option.data {
-                writer.write_u8(1).expect("Could not write Optional data");
+                writer.write_u8(1)?;
 
-                write_value_by_bin_data(writer, data, &option.btype);
+                write_value_by_bin_data(writer, data, &option.btype)?;
             } else {
-                writer.write_u8(0).expect("Could not write Optional data");
+                writer.write_u8(0)?;
             }
         }
         BinData::Map(map) => {
@@ -334,29 +396,98 @@ fn write_value_by_bin_data(writer: &mut Vec<u8>, bin_data: &BinData, bin_type: &
             let fkeytype = type_to_u8(&map.keytype);
             let fvaluetype = type_to_u8(&map.valuetype);
 
-            writer
-                .write_u8(fkeytype)
-                .expect("Could not write Map key type");
-            writer
-                .write_u8(fvaluetype)
-                .expect("Could not write Map value type");
+            writer.write_u8(fkeytype)?;
+            writer.write_u8(fvaluetype)?;
 
-            writer
-                .write_u32::<LittleEndian>(length)
-                .expect("Could not write Map length");
-            writer
-                .write_u32::<LittleEndian>(field_count)
-                .expect("Could not write Map field count");
+            writer.write_u32::<LittleEndian>(length)?;
+            writer.write_u32::<LittleEndian>(field_count)?;
 
-            for mappair in &map.items {
-                write_value_by_bin_data(writer, &mappair.keydata, &map.keytype);
-                write_value_by_bin_data(writer, &mappair.valuedata, &map.valuetype);
+            for (index, mappair) in map.items.iter().enumerate() {
+                write_value_by_bin_data(writer, &mappair.keydata, &map.keytype)
+                    .map_err(|e| e.in_path(PathSegment::Index(index)))?;
+                write_value_by_bin_data(writer, &mappair.valuedata, &map.valuetype)
+                    .map_err(|e| e.in_path(PathSegment::from_map_key(&mappair.keydata, index)))?;
             }
         }
         BinData::Flag(flag) => {
-            writer.write_u8(*flag as u8).expect("Could not write Flag");
+            writer.write_u8(*flag as u8)?;
         }
     }
+
+    Ok(())
+}
+
+fn check_length(length: usize, expected: usize, what: &str) -> Result<(), BinError> {
+    if length != expected {
+        return Err(BinError::invalid_data(format!(
+            "Expected {} values in {} but found {}",
+            expected, what, length
+        )));
+    }
+    Ok(())
+}
+
+fn data_matches_type(bin_data: &BinData, bin_type: &BinType) -> bool {
+    matches!(
+        (bin_data, bin_type),
+        (BinData::None, BinType::None)
+            | (BinData::Bool(_), BinType::Bool)
+            | (BinData::SInt8(_), BinType::SInt8)
+            | (BinData::UInt8(_), BinType::UInt8)
+            | (BinData::SInt16(_), BinType::SInt16)
+            | (BinData::UInt16(_), BinType::UInt16)
+            | (BinData::SInt32(_), BinType::SInt32)
+            | (BinData::UInt32(_), BinType::UInt32)
+            | (BinData::SInt64(_), BinType::SInt64)
+            | (BinData::UInt64(_), BinType::UInt64)
+            | (BinData::Float32(_), BinType::Float32)
+            | (BinData::Vector2(_), BinType::Vector2)
+            | (BinData::Vector3(_), BinType::Vector3)
+            | (BinData::Vector4(_), BinType::Vector4)
+            | (BinData::Matrix4x4(_), BinType::Matrix4x4)
+            | (BinData::Rgba(_), BinType::Rgba)
+            | (BinData::String(_), BinType::String)
+            | (BinData::Hash(_), BinType::Hash)
+            | (BinData::WadEntryLink(_), BinType::WadEntryLink)
+            | (BinData::ContainerOrStruct(_), BinType::Container)
+            | (BinData::ContainerOrStruct(_), BinType::Struct)
+            | (BinData::PointerOrEmbedded(_), BinType::Pointer)
+            | (BinData::PointerOrEmbedded(_), BinType::Embedded)
+            | (BinData::Link(_), BinType::Link)
+            | (BinData::Optional(_), BinType::Optional)
+            | (BinData::Map(_), BinType::Map)
+            | (BinData::Flag(_), BinType::Flag)
+    )
+}
+
+fn data_variant_name(bin_data: &BinData) -> &'static str {
+    match bin_data {
+        BinData::None => "None",
+        BinData::Bool(_) => "Bool",
+        BinData::SInt8(_) => "SInt8",
+        BinData::UInt8(_) => "UInt8",
+        BinData::SInt16(_) => "SInt16",
+        BinData::UInt16(_) => "UInt16",
+        BinData::SInt32(_) => "SInt32",
+        BinData::UInt32(_) => "UInt32",
+        BinData::SInt64(_) => "SInt64",
+        BinData::UInt64(_) => "UInt64",
+        BinData::Float32(_) => "Float32",
+        BinData::Vector2(_) => "Vector2",
+        BinData::Vector3(_) => "Vector3",
+        BinData::Vector4(_) => "Vector4",
+        BinData::Matrix4x4(_) => "Matrix4x4",
+        BinData::Rgba(_) => "Rgba",
+        BinData::String(_) => "String",
+        BinData::Hash(_) => "Hash",
+        BinData::WadEntryLink(_) => "WadEntryLink",
+        BinData::ContainerOrStruct(_) => "ContainerOrStruct",
+        BinData::PointerOrEmbedded(_) => "PointerOrEmbedded",
+        BinData::Optional(_) => "Optional",
+        BinData::Link(_) => "Link",
+        BinData::Map(_) => "Map",
+        BinData::Flag(_) => "Flag",
+    }
 }
 
 fn get_total_bin_data_size(bin_data: &BinData) -> u32 {